@@ -1,10 +1,12 @@
 use clap::Parser;
-use cli::{ctx::CliCtx, Cli};
+use cli::{completion, ctx::CliCtx, Cli};
 use cli_trace::init_tracing;
 use tracing::debug;
 
 #[tokio::main]
 async fn main() {
+    // Shell and kubectl completion requests exit here, before anything is printed
+    completion::complete();
     let mut cli = Cli::parse();
 
     let ctx = CliCtx::from(cli.clone());
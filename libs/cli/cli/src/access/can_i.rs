@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+
+use futures::future::join_all;
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+    SelfSubjectRulesReview, SelfSubjectRulesReviewSpec,
+};
+use kube::{api::PostParams, Api};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    cli_config::cli_server_config::CliServerConfig,
+    ctx::{CliCtx, ContextFormat},
+    error::ProxyAuthK8sError,
+    output::render,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CanIOutput {
+    pub cluster: String,
+    pub namespace: String,
+    /// Kubernetes namespace the access is reviewed in
+    pub target_namespace: String,
+    pub resource: String,
+    /// Verb to decision, empty on error
+    pub allowed: BTreeMap<String, bool>,
+    pub error: Option<String>,
+}
+
+impl CanIOutput {
+    pub fn to_row(&self, verbs: &[String]) -> Vec<String> {
+        let mut row = vec![
+            self.cluster.clone(),
+            self.namespace.clone(),
+            self.target_namespace.clone(),
+            self.resource.clone(),
+        ];
+        for verb in verbs {
+            row.push(match self.allowed.get(verb) {
+                Some(true) => "yes".to_string(),
+                Some(false) => "no".to_string(),
+                None => "?".to_string(),
+            });
+        }
+        row.push(self.error.clone().unwrap_or_default());
+        row
+    }
+
+    pub fn to_row_headers(verbs: &[String]) -> Vec<String> {
+        let mut headers = vec![
+            "CLUSTER".to_string(),
+            "NAMESPACE".to_string(),
+            "TARGET NAMESPACE".to_string(),
+            "RESOURCE".to_string(),
+        ];
+        headers.extend(verbs.iter().map(|verb| verb.to_uppercase()));
+        headers.push("ERROR".to_string());
+        headers
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RulesOutput {
+    pub cluster: String,
+    pub namespace: String,
+    pub target_namespace: String,
+    pub verbs: Vec<String>,
+    pub api_groups: Vec<String>,
+    pub resources: Vec<String>,
+    /// The authorizer couldn't list every rule, such as with webhook authorizers
+    pub incomplete: bool,
+    pub error: Option<String>,
+}
+
+impl RulesOutput {
+    pub fn to_row(&self) -> Vec<String> {
+        vec![
+            self.cluster.clone(),
+            self.namespace.clone(),
+            self.target_namespace.clone(),
+            self.verbs.join(","),
+            self.api_groups.join(","),
+            self.resources.join(","),
+            self.incomplete.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+
+    pub fn to_row_headers() -> Vec<String> {
+        vec![
+            "CLUSTER".to_string(),
+            "NAMESPACE".to_string(),
+            "TARGET NAMESPACE".to_string(),
+            "VERBS".to_string(),
+            "API GROUPS".to_string(),
+            "RESOURCES".to_string(),
+            "INCOMPLETE".to_string(),
+            "ERROR".to_string(),
+        ]
+    }
+}
+
+fn to_output<T: Serialize>(
+    items: &[T],
+    format: ContextFormat,
+    headers: Vec<String>,
+    row: impl Fn(&T) -> Vec<String>,
+    name: impl Fn(&T) -> String,
+) -> String {
+    render(
+        &items,
+        items,
+        &format,
+        |_| headers.clone(),
+        |item, _| row(item),
+        name,
+    )
+}
+
+/// Split `deployments.apps` or `pods/log` into resource, group and subresource
+pub fn parse_resource(resource: &str) -> (String, String, Option<String>) {
+    let (resource, subresource) = match resource.split_once('/') {
+        Some((resource, subresource)) => (resource, Some(subresource.to_string())),
+        None => (resource, None),
+    };
+    match resource.split_once('.') {
+        Some((resource, group)) => (resource.to_string(), group.to_string(), subresource),
+        None => (resource.to_string(), String::new(), subresource),
+    }
+}
+
+/// One row per cluster and resource, in the order of the clusters then of the resources
+fn matrix_rows<'a>(
+    targets: &'a [(String, String)],
+    resources: &'a [String],
+) -> Vec<(&'a String, &'a String, &'a String)> {
+    targets
+        .iter()
+        .flat_map(|(ns, cluster)| {
+            resources
+                .iter()
+                .map(move |resource| (ns, cluster, resource))
+        })
+        .collect()
+}
+
+/// Fill the rows with the decisions, the reviews are in the order of the rows then of the verbs
+fn assemble_matrix(
+    rows: &[(&String, &String, &String)],
+    verbs: &[String],
+    target_namespace: &str,
+    reviews: impl IntoIterator<Item = Result<bool, ProxyAuthK8sError>>,
+) -> Vec<CanIOutput> {
+    let mut reviews = reviews.into_iter();
+    let mut outputs = Vec::new();
+    for (ns, cluster, resource) in rows {
+        debug!("Reviewed the access on cluster {}/{}", ns, cluster);
+        let mut output = CanIOutput {
+            cluster: cluster.to_string(),
+            namespace: ns.to_string(),
+            target_namespace: target_namespace.to_string(),
+            resource: resource.to_string(),
+            allowed: BTreeMap::new(),
+            error: None,
+        };
+        for (verb, review) in verbs.iter().zip(reviews.by_ref()) {
+            match review {
+                Ok(allowed) => {
+                    output.allowed.insert(verb.clone(), allowed);
+                }
+                // The first error of the row is reported, its verbs without decision print ?
+                Err(e) => {
+                    output.error.get_or_insert_with(|| e.to_string());
+                }
+            }
+        }
+        outputs.push(output);
+    }
+    outputs
+}
+
+impl CliServerConfig {
+    pub async fn self_subject_access_review(
+        &self,
+        ns: &str,
+        cluster: &str,
+        target_namespace: &str,
+        verb: &str,
+        resource: &str,
+    ) -> Result<bool, ProxyAuthK8sError> {
+        let (resource, group, subresource) = parse_resource(resource);
+        let api: Api<SelfSubjectAccessReview> = Api::all(self.cluster_client(ns, cluster)?);
+        let review = SelfSubjectAccessReview {
+            spec: SelfSubjectAccessReviewSpec {
+                resource_attributes: Some(ResourceAttributes {
+                    namespace: Some(target_namespace.to_string()),
+                    verb: Some(verb.to_string()),
+                    group: Some(group),
+                    resource: Some(resource),
+                    subresource,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let review = api
+            .create(&PostParams::default(), &review)
+            .await
+            .map_err(|e| ProxyAuthK8sError::RemoteServerError(e.to_string()))?;
+        Ok(review.status.is_some_and(|status| status.allowed))
+    }
+
+    pub async fn self_subject_rules_review(
+        &self,
+        ns: &str,
+        cluster: &str,
+        target_namespace: &str,
+    ) -> Result<SelfSubjectRulesReview, ProxyAuthK8sError> {
+        let api: Api<SelfSubjectRulesReview> = Api::all(self.cluster_client(ns, cluster)?);
+        let review = SelfSubjectRulesReview {
+            spec: SelfSubjectRulesReviewSpec {
+                namespace: Some(target_namespace.to_string()),
+            },
+            ..Default::default()
+        };
+        api.create(&PostParams::default(), &review)
+            .await
+            .map_err(|e| ProxyAuthK8sError::RemoteServerError(e.to_string()))
+    }
+}
+
+impl CliCtx {
+    /// Permission matrix of each logged in cluster, or the rules of each cluster with `rules`
+    pub async fn handle_can_i(
+        &mut self,
+        cluster_name: Option<String>,
+        verbs: Vec<String>,
+        resources: Vec<String>,
+        target_namespace: String,
+        rules: bool,
+    ) {
+        let Some((server_config, targets)) = self.access_targets(cluster_name).await else {
+            return;
+        };
+        if rules {
+            // The clusters are reviewed concurrently, the output keeps their order
+            let reviews = join_all(targets.iter().map(|(ns, cluster)| {
+                debug!("Reviewing the rules on cluster {}/{}", ns, cluster);
+                server_config.self_subject_rules_review(ns, cluster, &target_namespace)
+            }))
+            .await;
+            let mut outputs = Vec::new();
+            for ((ns, cluster), review) in targets.into_iter().zip(reviews) {
+                match review {
+                    Ok(review) => {
+                        let status = review.status.unwrap_or_default();
+                        for rule in status.resource_rules {
+                            outputs.push(RulesOutput {
+                                cluster: cluster.clone(),
+                                namespace: ns.clone(),
+                                target_namespace: target_namespace.clone(),
+                                verbs: rule.verbs,
+                                api_groups: rule.api_groups.unwrap_or_default(),
+                                resources: rule.resources.unwrap_or_default(),
+                                incomplete: status.incomplete,
+                                error: status.evaluation_error.clone(),
+                            });
+                        }
+                    }
+                    Err(e) => outputs.push(RulesOutput {
+                        cluster,
+                        namespace: ns,
+                        target_namespace: target_namespace.clone(),
+                        verbs: vec![],
+                        api_groups: vec![],
+                        resources: vec![],
+                        incomplete: true,
+                        error: Some(e.to_string()),
+                    }),
+                }
+            }
+            println!(
+                "{}",
+                to_output(
+                    &outputs,
+                    self.format.clone(),
+                    RulesOutput::to_row_headers(),
+                    RulesOutput::to_row,
+                    |output| format!("rules/{}/{}", output.namespace, output.cluster)
+                )
+            );
+            return;
+        }
+
+        // One review per cluster, resource and verb, all sent concurrently
+        let rows = matrix_rows(&targets, &resources);
+        let reviews = join_all(rows.iter().flat_map(|(ns, cluster, resource)| {
+            verbs.iter().map(|verb| {
+                server_config.self_subject_access_review(
+                    ns,
+                    cluster,
+                    &target_namespace,
+                    verb,
+                    resource,
+                )
+            })
+        }))
+        .await;
+        let outputs = assemble_matrix(&rows, &verbs, &target_namespace, reviews);
+        println!(
+            "{}",
+            to_output(
+                &outputs,
+                self.format.clone(),
+                CanIOutput::to_row_headers(&verbs),
+                |output| output.to_row(&verbs),
+                |output| format!(
+                    "access/{}/{}/{}",
+                    output.namespace, output.cluster, output.resource
+                )
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_assemble_matrix() {
+        let targets = vec![
+            ("dev".to_string(), "c1".to_string()),
+            ("prod".to_string(), "c2".to_string()),
+        ];
+        let resources = strings(&["pods", "deployments.apps"]);
+        let verbs = strings(&["get", "delete"]);
+        let rows = matrix_rows(&targets, &resources);
+        let reviews = vec![
+            Ok(true),
+            Ok(false),
+            Ok(true),
+            Ok(true),
+            Err(ProxyAuthK8sError::RemoteServerError(
+                "forbidden".to_string(),
+            )),
+            Ok(false),
+            Err(ProxyAuthK8sError::RemoteServerError("timeout".to_string())),
+            Err(ProxyAuthK8sError::RemoteServerError(
+                "unreachable".to_string(),
+            )),
+        ];
+        let outputs = assemble_matrix(&rows, &verbs, "default", reviews);
+
+        let table: Vec<Vec<String>> = outputs.iter().map(|output| output.to_row(&verbs)).collect();
+        let error =
+            |message: &str| ProxyAuthK8sError::RemoteServerError(message.to_string()).to_string();
+        assert_eq!(
+            table,
+            vec![
+                [
+                    strings(&["c1", "dev", "default", "pods", "yes", "no"]),
+                    vec![String::new()]
+                ]
+                .concat(),
+                [
+                    strings(&["c1", "dev", "default", "deployments.apps", "yes", "yes"]),
+                    vec![String::new()]
+                ]
+                .concat(),
+                [
+                    strings(&["c2", "prod", "default", "pods", "?", "no"]),
+                    vec![error("forbidden")]
+                ]
+                .concat(),
+                [
+                    strings(&["c2", "prod", "default", "deployments.apps", "?", "?"]),
+                    vec![error("timeout")]
+                ]
+                .concat(),
+            ]
+        );
+        assert_eq!(
+            CanIOutput::to_row_headers(&verbs)[4..6],
+            strings(&["GET", "DELETE"])
+        );
+    }
+
+    #[test]
+    fn test_parse_resource() {
+        assert_eq!(
+            parse_resource("deployments.apps"),
+            ("deployments".to_string(), "apps".to_string(), None)
+        );
+        assert_eq!(
+            parse_resource("pods/log"),
+            ("pods".to_string(), String::new(), Some("log".to_string()))
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use k8s_openapi::{api::authentication::v1::SelfSubjectReview, jiff::Timestamp};
+use kube::{api::PostParams, Api, Client, Config};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{
+    cli_config::cli_server_config::CliServerConfig,
+    context::sync::proxy_cluster_url,
+    ctx::{CliCtx, ContextFormat},
+    error::ProxyAuthK8sError,
+    output::{to_output, OutputItem},
+};
+
+pub mod can_i;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WhoamiOutput {
+    pub cluster: String,
+    pub namespace: String,
+    pub username: Option<String>,
+    pub groups: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl OutputItem for WhoamiOutput {
+    const KIND: &'static str = "whoami";
+
+    fn to_row_headers(_wide: bool) -> Vec<String> {
+        vec![
+            "CLUSTER".to_string(),
+            "NAMESPACE".to_string(),
+            "USERNAME".to_string(),
+            "GROUPS".to_string(),
+            "ERROR".to_string(),
+        ]
+    }
+
+    fn to_row(&self, _wide: bool) -> Vec<String> {
+        vec![
+            self.cluster.clone(),
+            self.namespace.clone(),
+            self.username.clone().unwrap_or_default(),
+            self.groups.join(","),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+
+    fn name(&self) -> String {
+        format!("{}/{}", self.namespace, self.cluster)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VecWhoamiOutput {
+    pub api_version: String,
+    pub kind: String,
+    pub metadata: Option<serde_json::Value>,
+    pub items: Vec<WhoamiOutput>,
+}
+
+impl VecWhoamiOutput {
+    pub fn new(items: Vec<WhoamiOutput>) -> Self {
+        VecWhoamiOutput {
+            api_version: "v1".to_string(),
+            kind: "List".to_string(),
+            metadata: None,
+            items,
+        }
+    }
+
+    pub fn to_output(&self, format: ContextFormat) -> String {
+        to_output(self, &self.items, &format)
+    }
+}
+
+impl CliServerConfig {
+    /// Kubernetes client reaching the cluster through the proxy with the stored cluster token
+    pub fn cluster_client(&self, ns: &str, cluster: &str) -> Result<Client, ProxyAuthK8sError> {
+        let token = self.get_cluster_token(ns.to_string(), cluster.to_string())?;
+        self.cluster_client_with_token(ns, cluster, token)
+    }
+
+    /// Kubernetes client reaching the cluster through the proxy with the given token
+    pub fn cluster_client_with_token(
+        &self,
+        ns: &str,
+        cluster: &str,
+        token: String,
+    ) -> Result<Client, ProxyAuthK8sError> {
+        let url = proxy_cluster_url(&self.url, ns, cluster);
+        let mut config = Config::new(
+            url.parse()
+                .map_err(|e| ProxyAuthK8sError::InvalidServerUrl(url.clone(), format!("{}", e)))?,
+        );
+        config.auth_info.token = Some(SecretString::from(token));
+        Client::try_from(config).map_err(|e| {
+            ProxyAuthK8sError::RemoteServerError(format!("Couldn't build the client: {}", e))
+        })
+    }
+
+    /// Identity of the cluster token as seen by the cluster
+    pub async fn self_subject_review(
+        &self,
+        ns: &str,
+        cluster: &str,
+    ) -> Result<(String, Vec<String>), ProxyAuthK8sError> {
+        review_identity(self.cluster_client(ns, cluster)?).await
+    }
+}
+
+/// Username and groups of the client token, from a SelfSubjectReview
+pub async fn review_identity(client: Client) -> Result<(String, Vec<String>), ProxyAuthK8sError> {
+    let api: Api<SelfSubjectReview> = Api::all(client);
+    let review = api
+        .create(&PostParams::default(), &SelfSubjectReview::default())
+        .await
+        .map_err(|e| ProxyAuthK8sError::RemoteServerError(e.to_string()))?;
+    let user_info = review
+        .status
+        .and_then(|status| status.user_info)
+        .unwrap_or_default();
+    Ok((
+        user_info.username.unwrap_or_default(),
+        user_info.groups.unwrap_or_default(),
+    ))
+}
+
+impl CliCtx {
+    /// Server and logged in clusters targeted by whoami and can-i, filtered by name and namespace
+    pub(crate) async fn access_targets(
+        &mut self,
+        cluster_name: Option<String>,
+    ) -> Option<(CliServerConfig, Vec<(String, String)>)> {
+        let server_config =
+            match self
+                .config
+                .get_server_config_by_url(if self.server_url.is_empty() {
+                    None
+                } else {
+                    Some(self.server_url.clone())
+                }) {
+                Ok(config) => config.clone(),
+                Err(e) => {
+                    error!(
+                        "Error retrieving server configuration, please login to server first: {}",
+                        e
+                    );
+                    return None;
+                }
+            };
+        let clusters = match server_config.get_clusters_from_remote().await {
+            Ok(clusters) => clusters.clusters,
+            Err(e) => {
+                error!("Failed to retrieve clusters: {}", e);
+                return None;
+            }
+        };
+        let mut targets: Vec<(String, String)> = clusters
+            .into_iter()
+            .filter(|cluster| {
+                cluster.enabled
+                    && (self.namespace.is_empty() || cluster.namespace == self.namespace)
+                    && cluster_name
+                        .as_ref()
+                        .is_none_or(|name| cluster.name == *name)
+                    // Only the clusters with a token can be queried
+                    && server_config
+                        .clusters
+                        .contains_key(&format!("{}/{}", cluster.namespace, cluster.name))
+            })
+            .map(|cluster| (cluster.namespace, cluster.name))
+            .collect();
+        targets.sort();
+        if targets.is_empty() {
+            error!("No logged in cluster matches, please login to a cluster first.");
+            return None;
+        }
+        let server_config = self.refresh_access_tokens(server_config, &targets).await;
+        Some((server_config, targets))
+    }
+
+    /// Refresh the expiring tokens of the targets as get-token does, without interactive login
+    /// A token that can't be refreshed is still used, the review reports the error of the cluster
+    async fn refresh_access_tokens(
+        &mut self,
+        mut server_config: CliServerConfig,
+        targets: &[(String, String)],
+    ) -> CliServerConfig {
+        let now = Timestamp::now().as_second();
+        let mut renewed = false;
+        for (ns, cluster) in targets {
+            if !server_config.cluster_token_expiring(ns, cluster, now) {
+                continue;
+            }
+            debug!(
+                "Token of cluster '{}/{}' is about to expire, refreshing it.",
+                ns, cluster
+            );
+            match server_config.refresh_cluster_token(ns, cluster).await {
+                Ok(()) => renewed = true,
+                Err(e) => warn!("{}. Please login using 'login {} -n {}'.", e, cluster, ns),
+            }
+        }
+        if renewed {
+            self.config.servers.insert(
+                CliServerConfig::url_to_name_from_string(server_config.url.clone()),
+                server_config.clone(),
+            );
+            if let Err(e) = self.config.write_to_file(self.config_path.clone()) {
+                error!("Failed to update config file: {}", e);
+            }
+        }
+        server_config
+    }
+
+    pub async fn handle_whoami(&mut self, cluster_name: Option<String>) {
+        let Some((server_config, targets)) = self.access_targets(cluster_name).await else {
+            return;
+        };
+        let mut outputs = Vec::new();
+        for (ns, cluster) in targets {
+            debug!("Reviewing the identity on cluster {}/{}", ns, cluster);
+            let output = match server_config.self_subject_review(&ns, &cluster).await {
+                Ok((username, groups)) => WhoamiOutput {
+                    cluster,
+                    namespace: ns,
+                    username: Some(username),
+                    groups,
+                    error: None,
+                },
+                Err(e) => WhoamiOutput {
+                    cluster,
+                    namespace: ns,
+                    username: None,
+                    groups: vec![],
+                    error: Some(e.to_string()),
+                },
+            };
+            outputs.push(output);
+        }
+        println!(
+            "{}",
+            VecWhoamiOutput::new(outputs).to_output(self.format.clone())
+        );
+    }
+}
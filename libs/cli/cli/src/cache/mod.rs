@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    cli_config::{
+        catalog::CatalogCache, cli_server_config::CliServerConfig, error::CliConfigError, CliConfig,
+    },
+    ctx::{CliCtx, ContextFormat},
+    output::{to_output, OutputItem},
+};
+
+/// Cached cluster list of a server
+const CACHED_CATALOG: &str = "cluster list";
+/// Recorded expiration of a cluster token
+const CACHED_EXPIRATION: &str = "token expiration";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheClearOutput {
+    pub server: String,
+    /// `cluster list` or `token expiration`
+    pub data: String,
+    /// Empty for the cluster list, which is cached for the whole server
+    pub namespace: String,
+    pub cluster: String,
+}
+
+impl OutputItem for CacheClearOutput {
+    const KIND: &'static str = "cache";
+
+    fn to_row_headers(_wide: bool) -> Vec<String> {
+        vec![
+            "SERVER".to_string(),
+            "DATA".to_string(),
+            "NAMESPACE".to_string(),
+            "CLUSTER".to_string(),
+        ]
+    }
+
+    fn to_row(&self, _wide: bool) -> Vec<String> {
+        let or_all = |value: &str| {
+            if value.is_empty() {
+                "*".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        vec![
+            self.server.clone(),
+            self.data.clone(),
+            or_all(&self.namespace),
+            or_all(&self.cluster),
+        ]
+    }
+
+    fn name(&self) -> String {
+        if self.cluster.is_empty() {
+            self.server.clone()
+        } else {
+            format!("{}/{}", self.namespace, self.cluster)
+        }
+    }
+}
+
+impl CacheClearOutput {
+    pub fn to_output(items: &[CacheClearOutput], format: ContextFormat) -> String {
+        to_output(&items, items, &format)
+    }
+}
+
+/// Names of the servers to clear, every configured server with `all_servers`
+fn target_servers(
+    config: &CliConfig,
+    server_url: &str,
+    all_servers: bool,
+) -> Result<Vec<String>, CliConfigError> {
+    if all_servers {
+        let mut server_names: Vec<String> = config.servers.keys().cloned().collect();
+        server_names.sort();
+        return Ok(server_names);
+    }
+    let config = config.get_server_config_by_url(if server_url.is_empty() {
+        None
+    } else {
+        Some(server_url.to_string())
+    })?;
+    Ok(vec![CliServerConfig::url_to_name_from_string(
+        config.url.clone(),
+    )])
+}
+
+/// Clear the cached cluster list of the server and the token expirations of its clusters in `namespace`, all if empty
+fn clear_server(
+    catalog: &CatalogCache,
+    server_config: &mut CliServerConfig,
+    namespace: &str,
+) -> Vec<CacheClearOutput> {
+    let mut cleared = Vec::new();
+    if catalog.clear(&server_config.url) {
+        cleared.push(CacheClearOutput {
+            server: server_config.url.clone(),
+            data: CACHED_CATALOG.to_string(),
+            namespace: String::new(),
+            cluster: String::new(),
+        });
+    }
+    let mut keys: Vec<String> = server_config.clusters.keys().cloned().collect();
+    keys.sort();
+    for key in keys {
+        let (ns, cluster) = key.split_once('/').unwrap_or(("", key.as_str()));
+        if !namespace.is_empty() && ns != namespace {
+            continue;
+        }
+        let Some(cluster_config) = server_config.clusters.get_mut(&key) else {
+            continue;
+        };
+        if cluster_config.expires_at.take().is_none() {
+            continue;
+        }
+        cleared.push(CacheClearOutput {
+            server: server_config.url.clone(),
+            data: CACHED_EXPIRATION.to_string(),
+            namespace: ns.to_string(),
+            cluster: cluster.to_string(),
+        });
+    }
+    cleared
+}
+
+impl CliCtx {
+    /// Remove the data derived from the tokens: the recorded token expirations and the cached cluster lists
+    /// The expirations are read again from the tokens, use logout to remove the tokens
+    pub fn handle_cache(&mut self, clear: bool, all_servers: bool) {
+        if !clear {
+            warn!("Please provide --clear to clear the cached data.");
+            return;
+        }
+        let server_names = match target_servers(&self.config, &self.server_url, all_servers) {
+            Ok(server_names) => server_names,
+            Err(e) => {
+                error!("Error retrieving server configuration: {}", e);
+                return;
+            }
+        };
+
+        let mut cleared = Vec::new();
+        for server_name in server_names {
+            let Some(server_config) = self.config.servers.get_mut(&server_name) else {
+                continue;
+            };
+            cleared.extend(clear_server(
+                CatalogCache::current(),
+                server_config,
+                &self.namespace,
+            ));
+        }
+
+        if let Err(e) = self.config.write_to_file(self.config_path.clone()) {
+            error!("Failed to update config file: {}", e);
+            return;
+        }
+        let catalogs = cleared
+            .iter()
+            .filter(|item| item.data == CACHED_CATALOG)
+            .count();
+        info!(
+            "Cleared {} cached cluster list(s) and {} cached token expiration(s).",
+            catalogs,
+            cleared.len() - catalogs
+        );
+        if !cleared.is_empty() {
+            println!(
+                "{}",
+                CacheClearOutput::to_output(&cleared, self.format.clone())
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::cli_config::{catalog::CachedCatalog, cli_cluster_config::CliClusterConfig};
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("proxyauth-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn server(url: &str) -> CliServerConfig {
+        let mut server = CliServerConfig::new(url.to_string());
+        for key in ["dev/c1", "dev/c2", "prod/c1"] {
+            let mut cluster = CliClusterConfig::new();
+            cluster.expires_at = Some(1_700_000_000);
+            server.clusters.insert(key.to_string(), cluster);
+        }
+        server
+    }
+
+    fn config() -> CliConfig {
+        let mut config = CliConfig::new();
+        for url in ["https://a.example.com", "https://b.example.com"] {
+            config.servers.insert(
+                CliServerConfig::url_to_name_from_string(url.to_string()),
+                server(url),
+            );
+        }
+        config.default_server_name =
+            CliServerConfig::url_to_name_from_string("https://b.example.com".to_string());
+        config
+    }
+
+    #[test]
+    fn test_target_servers() {
+        let config = config();
+        let a = CliServerConfig::url_to_name_from_string("https://a.example.com".to_string());
+        let b = config.default_server_name.clone();
+        assert_eq!(
+            target_servers(&config, "", true).unwrap(),
+            vec![a.clone(), b.clone()]
+        );
+        assert_eq!(target_servers(&config, "", false).unwrap(), vec![b]);
+        assert_eq!(
+            target_servers(&config, "https://a.example.com", false).unwrap(),
+            vec![a]
+        );
+        assert!(target_servers(&config, "https://c.example.com", false).is_err());
+    }
+
+    #[test]
+    fn test_clear_server_namespace_filter() {
+        let dir = temp_dir("cache-clear");
+        let catalog = CatalogCache::new(&dir.join("proxyauth_config.yaml"), 300);
+        let mut server = server("https://a.example.com");
+        catalog.write(
+            &server.url,
+            &CachedCatalog {
+                etag: None,
+                fetched_at: 0,
+                clusters: Default::default(),
+            },
+        );
+
+        let cleared = clear_server(&catalog, &mut server, "dev");
+        let rows: Vec<(&str, &str, &str)> = cleared
+            .iter()
+            .map(|item| {
+                (
+                    item.data.as_str(),
+                    item.namespace.as_str(),
+                    item.cluster.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (CACHED_CATALOG, "", ""),
+                (CACHED_EXPIRATION, "dev", "c1"),
+                (CACHED_EXPIRATION, "dev", "c2"),
+            ]
+        );
+        assert!(catalog.read(&server.url).is_none());
+        assert!(server.clusters["prod/c1"].expires_at.is_some());
+
+        // Nothing is left to report for dev, prod is cleared without a namespace
+        assert!(clear_server(&catalog, &mut server, "dev").is_empty());
+        let cleared = clear_server(&catalog, &mut server, "");
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(cleared[0].name(), "prod/c1");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,115 @@
+use client_api::models::GetAllVisibleClusterBody;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+use tracing::debug;
+
+use crate::cli_config::cli_server_config::CliServerConfig;
+
+/// Seconds a cached cluster list is used without asking the server
+pub const DEFAULT_CATALOG_TTL: u64 = 300;
+
+static CURRENT: OnceLock<CatalogCache> = OnceLock::new();
+
+/// Last cluster list returned by a server, with the ETag to revalidate it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedCatalog {
+    pub etag: Option<String>,
+    /// Last time the server returned or confirmed the list, in seconds since the epoch
+    pub fetched_at: i64,
+    pub clusters: GetAllVisibleClusterBody,
+}
+
+/// Cluster lists cached per server in `proxyauth_cache/`, next to the configuration
+pub struct CatalogCache {
+    dir: PathBuf,
+    ttl: u64,
+    /// Servers whose cached list was served while they were unreachable, with its `fetched_at`
+    stale: Mutex<HashMap<String, i64>>,
+}
+
+impl CatalogCache {
+    pub fn new(config_path: &Path, ttl: u64) -> Self {
+        CatalogCache {
+            dir: config_path.with_file_name("proxyauth_cache"),
+            ttl,
+            stale: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Select the cache used by the whole process, only the first call is kept
+    pub fn init(config_path: &Path, ttl: u64) {
+        let _ = CURRENT.set(CatalogCache::new(config_path, ttl));
+    }
+
+    /// Cache selected by `init`, the default TTL in the working directory if it was never called
+    pub fn current() -> &'static CatalogCache {
+        CURRENT.get_or_init(|| {
+            CatalogCache::new(Path::new("proxyauth_config.yaml"), DEFAULT_CATALOG_TTL)
+        })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}.json",
+            CliServerConfig::url_to_name_from_string(url.to_string())
+        ))
+    }
+
+    /// Whether a list fetched at `fetched_at` can be used without asking the server
+    pub fn is_fresh(&self, catalog: &CachedCatalog, now: i64) -> bool {
+        now - catalog.fetched_at < self.ttl as i64
+    }
+
+    /// Cached list of the server, None if missing or unreadable
+    pub fn read(&self, url: &str) -> Option<CachedCatalog> {
+        let content = fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str(&content)
+            .inspect_err(|e| debug!("Ignoring the cached cluster list of {}: {}", url, e))
+            .ok()
+    }
+
+    /// Store the list, a failure only costs a request on the next call
+    pub fn write(&self, url: &str, catalog: &CachedCatalog) {
+        let result = fs::create_dir_all(&self.dir).and_then(|_| {
+            fs::write(
+                self.path(url),
+                serde_json::to_string(catalog).unwrap_or_default(),
+            )
+        });
+        if let Err(e) = result {
+            debug!("Failed to cache the cluster list of {}: {}", url, e);
+        }
+    }
+
+    /// Remove the cached list of the server, when its token or user changed
+    /// Return whether a cached list was removed
+    pub fn clear(&self, url: &str) -> bool {
+        match fs::remove_file(self.path(url)) {
+            Ok(()) => {
+                debug!("Removed the cached cluster list of {}", url);
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => {
+                debug!("Failed to remove the cached cluster list of {}: {}", url, e);
+                false
+            }
+        }
+    }
+
+    pub fn mark_stale(&self, url: &str, fetched_at: i64) {
+        if let Ok(mut stale) = self.stale.lock() {
+            stale.insert(url.to_string(), fetched_at);
+        }
+    }
+
+    /// `fetched_at` of the cached list served for the server in this process, if it was stale
+    pub fn stale_since(&self, url: &str) -> Option<i64> {
+        self.stale.lock().ok()?.get(url).copied()
+    }
+}
@@ -1,8 +1,117 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use serde::{Deserialize, Serialize};
 
+/// Client of a cluster logged in with the client credentials grant, such as a CI runner
+/// The secret is never stored, it is read again from the environment variable on each renewal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientCredentialsConfig {
+    pub client_id: String,
+    pub client_secret_env: String,
+}
+
+/// Step-up authentication asked by a cluster (RFC 9470), requested again on each browser login
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StepUp {
+    /// Space separated ACR values, one of them must be in the acr claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr_values: Option<String>,
+    /// Seconds since the user last authenticated, from the auth_time claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<i64>,
+}
+
+impl StepUp {
+    /// Requirement of a `WWW-Authenticate` challenge
+    /// Return None unless the challenge is an insufficient_user_authentication error
+    pub fn from_challenge(challenge: &str) -> Option<Self> {
+        let params = challenge
+            .trim()
+            .strip_prefix("Bearer")
+            .map(challenge_params)?;
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        if param("error").as_deref() != Some("insufficient_user_authentication") {
+            return None;
+        }
+        Some(StepUp {
+            acr_values: param("acr_values").filter(|acr_values| !acr_values.is_empty()),
+            max_age: param("max_age").and_then(|max_age| max_age.parse().ok()),
+        })
+    }
+
+    /// Flags of `login` requesting the requirement
+    pub fn login_flags(&self) -> String {
+        let mut flags = String::new();
+        if let Some(acr_values) = &self.acr_values {
+            flags.push_str(&format!(" --acr-values \"{}\"", acr_values));
+        }
+        if let Some(max_age) = self.max_age {
+            flags.push_str(&format!(" --max-age {}", max_age));
+        }
+        flags
+    }
+
+    /// Whether the claims of a token satisfy the requirement, the token is not verified
+    pub fn is_satisfied_by(&self, claims: Option<&serde_json::Value>, now: i64) -> bool {
+        let Some(claims) = claims else {
+            return false;
+        };
+        let acr_ok = self.acr_values.as_ref().is_none_or(|acr_values| {
+            claims
+                .get("acr")
+                .and_then(|acr| acr.as_str())
+                .is_some_and(|acr| acr_values.split_whitespace().any(|value| value == acr))
+        });
+        let max_age_ok = self.max_age.is_none_or(|max_age| {
+            claims
+                .get("auth_time")
+                .and_then(|auth_time| auth_time.as_i64())
+                .is_some_and(|auth_time| now - auth_time <= max_age)
+        });
+        acr_ok && max_age_ok
+    }
+}
+
+/// `key=value` and `key="value"` parameters of a challenge, separated by commas
+fn challenge_params(params: &str) -> Vec<(String, String)> {
+    let mut parsed = vec![];
+    let mut rest = params.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let value = value.trim_start();
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, next)) => (value, next),
+                None => (quoted, ""),
+            },
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        parsed.push((key, value.trim().to_string()));
+        rest = next.trim_start().trim_start_matches(',');
+    }
+    parsed
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CliClusterConfig {
     pub token_exist: bool,
+    /// Expiration of the cluster token, unix timestamp in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Set when the token was obtained with the client credentials grant, to renew it the same way
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_credentials: Option<ClientCredentialsConfig>,
+    /// Step-up authentication last asked by the cluster, requested on the next browser logins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_up: Option<StepUp>,
+    /// Set when the cluster refused the current token with an insufficient_user_authentication challenge,
+    /// cleared by the next browser login
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub step_up_challenged: bool,
 }
 
 impl Default for CliClusterConfig {
@@ -13,6 +122,100 @@ impl Default for CliClusterConfig {
 
 impl CliClusterConfig {
     pub fn new() -> Self {
-        CliClusterConfig { token_exist: true }
+        CliClusterConfig {
+            token_exist: true,
+            expires_at: None,
+            client_credentials: None,
+            step_up: None,
+            step_up_challenged: false,
+        }
+    }
+
+    /// Claims of a JWT, the signature is not checked
+    /// Return None for opaque tokens
+    pub fn jwt_claims(token: &str) -> Option<serde_json::Value> {
+        let payload = token.split('.').nth(1)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        serde_json::from_slice(&payload).ok()
+    }
+
+    /// Expiration of a JWT from its exp claim, the signature is not checked
+    /// Return None for opaque tokens
+    pub fn jwt_expiry(token: &str) -> Option<i64> {
+        Self::jwt_claims(token)?.get("exp")?.as_i64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_up_from_challenge() {
+        assert_eq!(
+            StepUp::from_challenge(
+                "Bearer error=\"insufficient_user_authentication\", acr_values=\"mfa phr\", max_age=300"
+            ),
+            Some(StepUp {
+                acr_values: Some("mfa phr".to_string()),
+                max_age: Some(300),
+            })
+        );
+        assert_eq!(
+            StepUp::from_challenge("Bearer error=\"insufficient_user_authentication\""),
+            Some(StepUp::default())
+        );
+        assert_eq!(
+            StepUp::from_challenge("Bearer error=\"invalid_token\", acr_values=\"mfa\""),
+            None
+        );
+        assert_eq!(StepUp::from_challenge("Basic realm=\"kube\""), None);
+        // Unquoted values and parameter names in any case
+        assert_eq!(
+            StepUp::from_challenge(
+                "Bearer ERROR=insufficient_user_authentication, Max_Age=60, acr_values=\"\""
+            ),
+            Some(StepUp {
+                acr_values: None,
+                max_age: Some(60),
+            })
+        );
+        assert_eq!(
+            StepUp::from_challenge(
+                "Bearer error=\"insufficient_user_authentication\", max_age=soon"
+            ),
+            Some(StepUp::default())
+        );
+        assert_eq!(StepUp::from_challenge(""), None);
+    }
+
+    #[test]
+    fn test_step_up_is_satisfied_by() {
+        let step_up = StepUp {
+            acr_values: Some("mfa phr".to_string()),
+            max_age: Some(300),
+        };
+        let claims = serde_json::json!({"acr": "mfa", "auth_time": 1000});
+        assert!(step_up.is_satisfied_by(Some(&claims), 1200));
+        assert!(!step_up.is_satisfied_by(Some(&claims), 1400));
+        let claims = serde_json::json!({"acr": "pwd", "auth_time": 1000});
+        assert!(!step_up.is_satisfied_by(Some(&claims), 1200));
+        assert!(!step_up.is_satisfied_by(None, 1200));
+        assert!(StepUp::default().is_satisfied_by(Some(&claims), 1200));
+
+        // Each part of the requirement is checked on its own
+        let acr_only = StepUp {
+            acr_values: Some("mfa".to_string()),
+            max_age: None,
+        };
+        assert!(acr_only.is_satisfied_by(Some(&serde_json::json!({"acr": "mfa"})), 1200));
+        assert!(!acr_only.is_satisfied_by(Some(&serde_json::json!({"acr": ["mfa"]})), 1200));
+        assert!(!acr_only.is_satisfied_by(Some(&serde_json::json!({})), 1200));
+        let max_age_only = StepUp {
+            acr_values: None,
+            max_age: Some(300),
+        };
+        assert!(max_age_only.is_satisfied_by(Some(&serde_json::json!({"auth_time": 900})), 1200));
+        assert!(!max_age_only.is_satisfied_by(Some(&serde_json::json!({"acr": "mfa"})), 1200));
     }
 }
@@ -1,12 +1,25 @@
-use crate::{cli_config::cli_cluster_config::CliClusterConfig, error::ProxyAuthK8sError};
+use crate::{
+    cli_config::{
+        catalog::{CachedCatalog, CatalogCache},
+        cli_cluster_config::{CliClusterConfig, StepUp},
+        credential_store::{CredentialKey, CredentialStore},
+    },
+    error::ProxyAuthK8sError,
+};
 use client_api::{
-    apis::{api_clusters_api::get_all_visible_cluster, configuration::Configuration},
-    models::GetAllVisibleClusterBody,
+    apis::{api_clusters_api::GetAllVisibleClusterError, configuration::Configuration},
+    models::{CallbackModel, GetAllVisibleClusterBody},
 };
-use keyring::Entry;
+use k8s_openapi::jiff::Timestamp;
+use reqwest::{header, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tracing::{debug, error};
+use std::{collections::HashMap, time::Duration};
+use tracing::{debug, error, info, warn};
+
+/// Time to reach the server, past it the cached cluster list is used and a token check fails
+const CATALOG_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time for a whole request, such as the cluster list
+const CATALOG_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CliServerConfig {
@@ -23,6 +36,20 @@ impl CliServerConfig {
             clusters: vec![].into_iter().collect(),
         }
     }
+    /// Client of the calls the CLI makes by itself, bounded so a down server doesn't hang kubectl
+    pub fn http_client() -> Result<reqwest::Client, ProxyAuthK8sError> {
+        reqwest::Client::builder()
+            .connect_timeout(CATALOG_CONNECT_TIMEOUT)
+            .timeout(CATALOG_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| {
+                ProxyAuthK8sError::RemoteServerError(format!(
+                    "Couldn't build the HTTP client: {}",
+                    e
+                ))
+            })
+    }
+
     pub fn url_to_name(&self) -> String {
         let url = self.url.replace("https://", "").replace("http://", "");
         url.replace(".", "-").replace(":", "-")
@@ -51,72 +78,46 @@ impl CliServerConfig {
     }
 
     pub fn get_server_token(&self) -> Result<String, ProxyAuthK8sError> {
-        let entry = match Entry::new("proxyauthk8s", &self.url) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringReadError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
-            }
-        };
-        match entry.get_password() {
-            Ok(token) => Ok(token),
-            Err(err) => {
-                debug!("Keyring read error: {}", err);
-                Err(ProxyAuthK8sError::KeyringReadError(format!(
-                    "Failed to read token from keyring for server URL: {}",
-                    &self.url
-                )))
-            }
-        }
+        CredentialStore::current().get_required(&CredentialKey::server(&self.url))
     }
 
+    /// Store the server token, the cached cluster list is removed as the token may belong to another user
     pub fn set_server_token(&self, token: String) -> Result<(), ProxyAuthK8sError> {
-        let entry = match Entry::new("proxyauthk8s", &self.url) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringWriteError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
-            }
-        };
-        match entry.set_password(&token) {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                debug!("Keyring write error: {}", err);
-                Err(ProxyAuthK8sError::KeyringWriteError(format!(
-                    "Failed to write token to keyring for server URL: {}",
-                    &self.url
-                )))
-            }
+        CredentialStore::current().set(&CredentialKey::server(&self.url), &token)?;
+        CatalogCache::current().clear(&self.url);
+        Ok(())
+    }
+
+    /// Key of a secret attached to the server token, such as its refresh token
+    fn server_secret_key(&self, kind: &str) -> CredentialKey {
+        CredentialKey::server(&self.url).with_kind(kind)
+    }
+
+    pub fn get_server_refresh_token(&self) -> Option<String> {
+        CredentialStore::current()
+            .get(&self.server_secret_key("refresh_token"))
+            .unwrap_or_else(|err| {
+                debug!("Couldn't read the server refresh token: {}", err);
+                None
+            })
+    }
+
+    /// Store the refresh token of the OIDC login to the server, remove it if there is none
+    pub fn set_server_refresh_token(
+        &self,
+        refresh_token: Option<String>,
+    ) -> Result<(), ProxyAuthK8sError> {
+        let key = self.server_secret_key("refresh_token");
+        match refresh_token {
+            Some(refresh_token) => CredentialStore::current().set(&key, &refresh_token),
+            None => CredentialStore::current().delete(&key),
         }
     }
 
     pub fn clear_server_token(&self) -> Result<(), ProxyAuthK8sError> {
-        let entry = match Entry::new("proxyauthk8s", &self.url) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringDeleteError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
-            }
-        };
-        match entry.delete_credential() {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                debug!("Keyring delete error: {}", err);
-                Err(ProxyAuthK8sError::KeyringDeleteError(format!(
-                    "Failed to delete token from keyring for server URL: {}",
-                    &self.url
-                )))
-            }
-        }
+        // Only servers logged in through OIDC have a refresh token
+        CredentialStore::current().delete(&self.server_secret_key("refresh_token"))?;
+        CredentialStore::current().delete(&CredentialKey::server(&self.url))
     }
 
     pub fn get_base_configuration(&self) -> Result<Configuration, ProxyAuthK8sError> {
@@ -128,15 +129,118 @@ impl CliServerConfig {
         })
     }
 
+    /// Fetch the clusters visible to the user
+    /// The cached list is used within the TTL, then revalidated with its ETag
+    /// If the server can't be reached the cached list is used and marked as stale
+    /// An expired server token is refreshed once if the server was logged in through OIDC
     pub async fn get_clusters_from_remote(
         &self,
     ) -> Result<GetAllVisibleClusterBody, ProxyAuthK8sError> {
-        get_all_visible_cluster(&self.get_base_configuration()?)
-            .await
-            .map_err(|e| {
-                debug!("Error fetching clusters from remote: {:?}", e);
-                e.into()
-            })
+        let cache = CatalogCache::current();
+        let now = Timestamp::now().as_second();
+        let cached = cache.read(&self.url);
+        if let Some(cached) = cached.as_ref().filter(|cached| cache.is_fresh(cached, now)) {
+            debug!(
+                "Using the cluster list of {} cached at {}",
+                self.url, cached.fetched_at
+            );
+            return Ok(cached.clusters.clone());
+        }
+
+        let etag = cached.as_ref().and_then(|cached| cached.etag.clone());
+        let result = match self.fetch_clusters_from_remote(etag.as_deref()).await {
+            Err(ProxyAuthK8sError::Unauthenticated(_))
+                if self.get_server_refresh_token().is_some() =>
+            {
+                info!("Server token rejected, refreshing it.");
+                self.refresh_server_token().await?;
+                self.fetch_clusters_from_remote(etag.as_deref()).await
+            }
+            result => result,
+        };
+        match (result, cached) {
+            (Ok(Some(fetched)), _) => {
+                cache.write(&self.url, &fetched);
+                Ok(fetched.clusters)
+            }
+            (Ok(None), Some(mut cached)) => {
+                debug!("Cluster list of {} unchanged", self.url);
+                cached.fetched_at = now;
+                cache.write(&self.url, &cached);
+                Ok(cached.clusters)
+            }
+            (Ok(None), None) => Err(ProxyAuthK8sError::RemoteServerError(
+                "Not modified answered without a cached cluster list".to_string(),
+            )),
+            (Err(ProxyAuthK8sError::RemoteServerError(e)), Some(cached)) => {
+                warn!(
+                    "{}. Using the cluster list cached at {}, it may be stale.",
+                    e,
+                    Timestamp::from_second(cached.fetched_at)
+                        .map(|t| t.to_string())
+                        .unwrap_or_default()
+                );
+                cache.mark_stale(&self.url, cached.fetched_at);
+                Ok(cached.clusters)
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Fetch the list, None when the server answered that the one with the ETag is unchanged
+    /// The generated client can't send back the ETag nor handle the empty 304
+    async fn fetch_clusters_from_remote(
+        &self,
+        etag: Option<&str>,
+    ) -> Result<Option<CachedCatalog>, ProxyAuthK8sError> {
+        let configuration = self.get_base_configuration()?;
+        let url = format!("{}/api/v1/clusters", configuration.base_path);
+        let client = Self::http_client()?;
+        let mut request = client
+            .get(&url)
+            .bearer_auth(configuration.bearer_access_token.unwrap_or_default());
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.map_err(|e| {
+            debug!("Error fetching clusters from remote: {:?}", e);
+            ProxyAuthK8sError::RemoteServerError(format!("Couldn't reach {}: {}", self.url, e))
+        })?;
+        match response.status() {
+            StatusCode::NOT_MODIFIED => Ok(None),
+            StatusCode::UNAUTHORIZED => Err(GetAllVisibleClusterError::Status401().into()),
+            status if status.is_success() => {
+                let etag = response
+                    .headers()
+                    .get(header::ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string);
+                let content = response.text().await.map_err(|e| {
+                    ProxyAuthK8sError::RemoteServerError(format!(
+                        "Couldn't read the clusters: {}",
+                        e
+                    ))
+                })?;
+                let clusters = serde_json::from_str(&content).map_err(|e| {
+                    ProxyAuthK8sError::RemoteServerError(format!("Serialization error: {}", e))
+                })?;
+                Ok(Some(CachedCatalog {
+                    etag,
+                    fetched_at: Timestamp::now().as_second(),
+                    clusters,
+                }))
+            }
+            status => {
+                debug!(
+                    "Error fetching clusters from remote: {:?}",
+                    response.text().await
+                );
+                Err(ProxyAuthK8sError::RemoteServerError(format!(
+                    "Unexpected status {} from {}",
+                    status, self.url
+                )))
+            }
+        }
     }
 
     pub fn set_cluster_token(
@@ -146,30 +250,10 @@ impl CliServerConfig {
         token: String,
     ) -> Result<(), ProxyAuthK8sError> {
         let key = format!("{}/{}", ns, cluster);
-        let _ = self
-            .clusters
-            .entry(key.clone())
-            .or_insert(CliClusterConfig { token_exist: true });
-        let entry = match Entry::new("proxyauthk8s", &format!("{}::{}", &self.url, &key)) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringWriteError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
-            }
-        };
-        match entry.set_password(&token) {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                debug!("Keyring write error: {}", err);
-                Err(ProxyAuthK8sError::KeyringWriteError(format!(
-                    "Failed to write token to keyring for server URL: {}",
-                    &self.url
-                )))
-            }
-        }
+        let cluster_config = self.clusters.entry(key.clone()).or_default();
+        cluster_config.token_exist = true;
+        cluster_config.expires_at = CliClusterConfig::jwt_expiry(&token);
+        CredentialStore::current().set(&CredentialKey::cluster(&self.url, &ns, &cluster), &token)
     }
 
     pub fn get_cluster_token(
@@ -177,27 +261,18 @@ impl CliServerConfig {
         ns: String,
         cluster: String,
     ) -> Result<String, ProxyAuthK8sError> {
-        let key = format!("{}/{}", ns, cluster);
-        let entry = match Entry::new("proxyauthk8s", &format!("{}::{}", &self.url, &key)) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringReadError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
-            }
-        };
-        match entry.get_password() {
-            Ok(token) => Ok(token),
-            Err(err) => {
-                debug!("Keyring read error: {}", err);
-                Err(ProxyAuthK8sError::KeyringReadError(format!(
-                    "Failed to read token from keyring for server URL: {}",
-                    &self.url
-                )))
-            }
-        }
+        CredentialStore::current().get_required(&CredentialKey::cluster(&self.url, &ns, &cluster))
+    }
+
+    /// Expiration of the cluster token, from the exp claim of the stored token when it isn't recorded
+    pub fn get_cluster_expiry(&self, ns: &str, cluster: &str) -> Option<i64> {
+        self.get_clusters_from_name_ns(cluster.to_string(), Some(ns.to_string()))
+            .and_then(|cluster_config| cluster_config.expires_at)
+            .or_else(|| {
+                self.get_cluster_token(ns.to_string(), cluster.to_string())
+                    .ok()
+                    .and_then(|token| CliClusterConfig::jwt_expiry(&token))
+            })
     }
 
     pub fn clear_cluster_token(
@@ -205,27 +280,133 @@ impl CliServerConfig {
         ns: String,
         cluster: String,
     ) -> Result<(), ProxyAuthK8sError> {
-        let key = format!("{}/{}", ns, cluster);
-        let entry = match Entry::new("proxyauthk8s", &format!("{}::{}", &self.url, &key)) {
-            Ok(entry) => entry,
-            Err(err) => {
-                debug!("Keyring entry creation error: {}", err);
-                return Err(ProxyAuthK8sError::KeyringDeleteError(format!(
-                    "Failed to create keyring entry for server URL: {}",
-                    &self.url
-                )));
+        // Only clusters logged in through SSO have these secrets
+        for kind in ["refresh_token", "id_token"] {
+            CredentialStore::current().delete(&self.cluster_secret_key(&ns, &cluster, kind))?;
+        }
+        CredentialStore::current().delete(&CredentialKey::cluster(&self.url, &ns, &cluster))
+    }
+
+    /// Key of a secret attached to a cluster token, such as its refresh token
+    fn cluster_secret_key(&self, ns: &str, cluster: &str, kind: &str) -> CredentialKey {
+        CredentialKey::cluster(&self.url, ns, cluster).with_kind(kind)
+    }
+
+    /// Store the tokens returned by the SSO login of a cluster
+    /// The access token is the cluster token, the refresh and ID tokens are kept next to it
+    pub fn set_cluster_credentials(
+        &mut self,
+        ns: String,
+        cluster: String,
+        credentials: &CallbackModel,
+    ) -> Result<(), ProxyAuthK8sError> {
+        self.set_cluster_token(
+            ns.clone(),
+            cluster.clone(),
+            credentials.access_token.clone(),
+        )?;
+        // The lifetime given by the OIDC provider wins over the exp claim, the access token may be opaque
+        if let Some(expires_in) = credentials.expires_in.flatten() {
+            if let Some(cluster_config) = self.clusters.get_mut(&format!("{}/{}", ns, cluster)) {
+                cluster_config.expires_at = Some(Timestamp::now().as_second() + expires_in);
             }
-        };
-        match entry.delete_credential() {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                debug!("Keyring delete error: {}", err);
-                Err(ProxyAuthK8sError::KeyringDeleteError(format!(
-                    "Failed to delete token from keyring for server URL: {}",
-                    &self.url
-                )))
+        }
+        for (kind, secret) in [
+            ("refresh_token", &credentials.refresh_token),
+            ("id_token", &credentials.id_token),
+        ] {
+            let key = self.cluster_secret_key(&ns, &cluster, kind);
+            if secret.is_empty() {
+                CredentialStore::current().delete(&key)?;
+            } else {
+                CredentialStore::current().set(&key, secret)?;
             }
         }
+        Ok(())
+    }
+
+    /// Claims of the cluster tokens, the access token first then the ID token of the SSO login
+    pub fn get_cluster_token_claims(&self, ns: &str, cluster: &str) -> Vec<serde_json::Value> {
+        let tokens = [
+            CredentialKey::cluster(&self.url, ns, cluster),
+            self.cluster_secret_key(ns, cluster, "id_token"),
+        ];
+        tokens
+            .iter()
+            .filter_map(|key| CredentialStore::current().get(key).ok().flatten())
+            .filter_map(|token| CliClusterConfig::jwt_claims(&token))
+            .collect()
+    }
+
+    /// Step-up requirement recorded for the cluster
+    pub fn get_cluster_step_up(&self, ns: &str, cluster: &str) -> Option<StepUp> {
+        self.clusters
+            .get(&format!("{}/{}", ns, cluster))
+            .and_then(|cluster_config| cluster_config.step_up.clone())
+    }
+
+    /// Whether the tokens of the cluster satisfy its recorded step-up requirement
+    pub fn satisfies_cluster_step_up(&self, ns: &str, cluster: &str, now: i64) -> bool {
+        match self.get_cluster_step_up(ns, cluster) {
+            Some(step_up) => self
+                .get_cluster_token_claims(ns, cluster)
+                .iter()
+                .any(|claims| step_up.is_satisfied_by(Some(claims), now)),
+            None => true,
+        }
+    }
+
+    /// Whether the cluster refused the current token asking for a step-up its tokens don't satisfy
+    /// Without a challenge for the current token the recorded requirement doesn't force a new login
+    pub fn cluster_step_up_missing(&self, ns: &str, cluster: &str, now: i64) -> bool {
+        self.clusters
+            .get(&format!("{}/{}", ns, cluster))
+            .is_some_and(|cluster_config| cluster_config.step_up_challenged)
+            && !self.satisfies_cluster_step_up(ns, cluster, now)
+    }
+
+    /// Record the step-up requirement used by the last browser login, clearing the pending challenge
+    pub fn set_cluster_step_up(&mut self, ns: &str, cluster: &str, step_up: Option<StepUp>) {
+        if let Some(cluster_config) = self.clusters.get_mut(&format!("{}/{}", ns, cluster)) {
+            cluster_config.step_up = step_up;
+            cluster_config.step_up_challenged = false;
+        }
+    }
+
+    /// Record the step-up requirement of an insufficient_user_authentication challenge of the cluster
+    pub fn record_cluster_step_up_challenge(&mut self, ns: &str, cluster: &str, step_up: StepUp) {
+        if let Some(cluster_config) = self.clusters.get_mut(&format!("{}/{}", ns, cluster)) {
+            cluster_config.step_up = Some(step_up);
+            cluster_config.step_up_challenged = true;
+        }
+    }
+
+    pub fn get_cluster_refresh_token(
+        &self,
+        ns: String,
+        cluster: String,
+    ) -> Result<String, ProxyAuthK8sError> {
+        CredentialStore::current().get_required(&self.cluster_secret_key(
+            &ns,
+            &cluster,
+            "refresh_token",
+        ))
+    }
+
+    /// Keys of all the secrets that may be stored for this server and its clusters
+    pub fn credential_keys(&self) -> Vec<CredentialKey> {
+        let mut keys = vec![
+            CredentialKey::server(&self.url),
+            self.server_secret_key("refresh_token"),
+        ];
+        for cluster in self.clusters.keys() {
+            let (ns, name) = cluster.split_once('/').unwrap_or(("", cluster.as_str()));
+            keys.push(CredentialKey::cluster(&self.url, ns, name));
+            for kind in ["refresh_token", "id_token"] {
+                keys.push(self.cluster_secret_key(ns, name, kind));
+            }
+        }
+        keys
     }
 
     pub fn clear_all_tokens(&self) {
@@ -240,5 +421,44 @@ impl CliServerConfig {
         if let Err(err) = self.clear_server_token() {
             error!("Error clearing server token: {}", err);
         }
+        CatalogCache::current().clear(&self.url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_up_challenge() {
+        let mut server_config = CliServerConfig::new("https://proxyauth.example.com".to_string());
+        server_config
+            .clusters
+            .insert("dev/c1".to_string(), CliClusterConfig::new());
+        let step_up = StepUp {
+            acr_values: Some("mfa".to_string()),
+            max_age: None,
+        };
+
+        // A requirement recorded by a login doesn't force a new login on its own
+        server_config.set_cluster_step_up("dev", "c1", Some(step_up.clone()));
+        assert!(!server_config.clusters["dev/c1"].step_up_challenged);
+        assert!(!server_config.cluster_step_up_missing("dev", "c1", 0));
+
+        server_config.record_cluster_step_up_challenge("dev", "c1", step_up.clone());
+        assert!(server_config.clusters["dev/c1"].step_up_challenged);
+        assert_eq!(
+            server_config.get_cluster_step_up("dev", "c1"),
+            Some(step_up.clone())
+        );
+
+        // The next browser login clears the challenge and keeps the requirement
+        server_config.set_cluster_step_up("dev", "c1", Some(step_up.clone()));
+        assert!(!server_config.clusters["dev/c1"].step_up_challenged);
+        assert_eq!(
+            server_config.get_cluster_step_up("dev", "c1"),
+            Some(step_up)
+        );
+        assert!(!server_config.cluster_step_up_missing("dev", "other", 0));
     }
 }
@@ -0,0 +1,584 @@
+use age::{scrypt, secrecy::SecretString};
+use clap::ValueEnum;
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+use tracing::debug;
+
+use crate::error::ProxyAuthK8sError;
+
+const KEYRING_SERVICE: &str = "proxyauthk8s";
+/// Environment variable overriding the backend of the configuration
+pub const BACKEND_ENV: &str = "PROXYAUTH_CREDENTIAL_BACKEND";
+/// Environment variable holding the passphrase of the encrypted credential file
+pub const PASSPHRASE_ENV: &str = "PROXYAUTH_CREDENTIALS_PASSPHRASE";
+/// Prefix of the environment variables read by the env backend
+pub const ENV_PREFIX: &str = "PROXYAUTH_";
+
+static CURRENT: OnceLock<CredentialStore> = OnceLock::new();
+
+/// Where the CLI stores its tokens
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialBackend {
+    /// OS keyring (Secret Service, macOS Keychain, Windows Credential Manager)
+    #[default]
+    Keyring,
+    /// File under ~/.kube encrypted with a passphrase (age)
+    File,
+    /// Read-only environment variables, for CI
+    Env,
+}
+
+impl fmt::Display for CredentialBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialBackend::Keyring => write!(f, "keyring"),
+            CredentialBackend::File => write!(f, "file"),
+            CredentialBackend::Env => write!(f, "env"),
+        }
+    }
+}
+
+/// Identify a secret of a server, or of one of its clusters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialKey {
+    pub url: String,
+    /// `{ns}/{cluster}` for the secrets of a cluster
+    pub cluster: Option<String>,
+    /// Kind of secret next to the token, such as `refresh_token`
+    pub kind: Option<String>,
+}
+
+impl CredentialKey {
+    pub fn server(url: &str) -> Self {
+        CredentialKey {
+            url: url.to_string(),
+            cluster: None,
+            kind: None,
+        }
+    }
+
+    pub fn cluster(url: &str, ns: &str, cluster: &str) -> Self {
+        CredentialKey {
+            url: url.to_string(),
+            cluster: Some(format!("{}/{}", ns, cluster)),
+            kind: None,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    /// Environment variable of the secret, parts are separated by `__` and escaped by `env_var_part`
+    /// e.g. `PROXYAUTH_SERVER_TOKEN__LOCALHOST_3A5437` or `PROXYAUTH_REFRESH_TOKEN__LOCALHOST_3A5437__DEFAULT__LOCAL_2DSSO`
+    pub fn env_var(&self) -> String {
+        let server = self
+            .url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_end_matches('/');
+        // The kind is a fixed identifier of the CLI, such as `refresh_token`
+        let kind = self.kind.as_deref().unwrap_or("token").to_ascii_uppercase();
+        let mut parts = vec![env_var_part(server)];
+        let prefix = match &self.cluster {
+            None => format!("SERVER_{}", kind),
+            Some(cluster) => {
+                let (ns, name) = cluster.split_once('/').unwrap_or(("", cluster.as_str()));
+                parts.push(env_var_part(ns));
+                parts.push(env_var_part(name));
+                kind
+            }
+        };
+        format!("{}{}__{}", ENV_PREFIX, prefix, parts.join("__"))
+    }
+}
+
+/// Letters and digits are kept uppercased, any other character is written `_` and its hex code
+/// so that `team-b` and `team.b` don't share a variable, and `__` only separates parts
+fn env_var_part(value: &str) -> String {
+    let mut part = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            part.push(byte.to_ascii_uppercase() as char);
+        } else {
+            part.push_str(&format!("_{:02X}", byte));
+        }
+    }
+    part
+}
+
+/// Keyring user of the secret, `{url}`, `{url}::{kind}`, `{url}::{ns}/{cluster}` or `{url}::{ns}/{cluster}::{kind}`
+impl fmt::Display for CredentialKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.url)?;
+        if let Some(cluster) = &self.cluster {
+            write!(f, "::{}", cluster)?;
+        }
+        if let Some(kind) = &self.kind {
+            write!(f, "::{}", kind)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decrypted content of the credential file, reloaded when another process changed it
+struct FileState {
+    passphrase: SecretString,
+    secrets: HashMap<String, String>,
+    /// Modification time of the file the secrets were read from or written to, None if missing
+    modified: Option<SystemTime>,
+}
+
+pub struct CredentialStore {
+    pub backend: CredentialBackend,
+    file_path: PathBuf,
+    file_state: Mutex<Option<FileState>>,
+}
+
+impl CredentialStore {
+    /// The credential file sits next to the CLI configuration file
+    pub fn new(backend: CredentialBackend, config_path: &Path) -> Self {
+        CredentialStore {
+            backend,
+            file_path: config_path.with_file_name("proxyauth_credentials.age"),
+            file_state: Mutex::new(None),
+        }
+    }
+
+    /// Select the store used by the whole process, only the first call is kept
+    pub fn init(backend: CredentialBackend, config_path: &Path) {
+        let _ = CURRENT.set(CredentialStore::new(backend, config_path));
+    }
+
+    /// Store selected by `init`, the OS keyring if it was never called
+    pub fn current() -> &'static CredentialStore {
+        CURRENT.get_or_init(|| {
+            CredentialStore::new(
+                CredentialBackend::Keyring,
+                Path::new("proxyauth_config.yaml"),
+            )
+        })
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Read a secret, `Ok(None)` if it doesn't exist
+    pub fn get(&self, key: &CredentialKey) -> Result<Option<String>, ProxyAuthK8sError> {
+        match self.backend {
+            CredentialBackend::Keyring => match keyring_entry(key)?.get_password() {
+                Ok(secret) => Ok(Some(secret)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(err) => Err(keyring_error(err, |e| {
+                    ProxyAuthK8sError::KeyringReadError(format!("{}: {}", key, e))
+                })),
+            },
+            CredentialBackend::File => self.with_file(false, |secrets| {
+                Ok((secrets.get(&key.to_string()).cloned(), false))
+            }),
+            CredentialBackend::Env => Ok(env::var(key.env_var()).ok().filter(|s| !s.is_empty())),
+        }
+    }
+
+    /// Read a secret that must exist
+    pub fn get_required(&self, key: &CredentialKey) -> Result<String, ProxyAuthK8sError> {
+        self.get(key)?.ok_or_else(|| {
+            ProxyAuthK8sError::CredentialNotFound(match self.backend {
+                CredentialBackend::Env => format!("{} is not set", key.env_var()),
+                backend => format!("{} in the {} backend, please login first", key, backend),
+            })
+        })
+    }
+
+    pub fn set(&self, key: &CredentialKey, secret: &str) -> Result<(), ProxyAuthK8sError> {
+        match self.backend {
+            CredentialBackend::Keyring => keyring_entry(key)?.set_password(secret).map_err(|err| {
+                keyring_error(err, |e| {
+                    ProxyAuthK8sError::KeyringWriteError(format!("{}: {}", key, e))
+                })
+            }),
+            CredentialBackend::File => self.with_file(true, |secrets| {
+                secrets.insert(key.to_string(), secret.to_string());
+                Ok(((), true))
+            }),
+            // Storing the value already exported is a no-op, e.g. a login with the token of the variable
+            CredentialBackend::Env
+                if env::var(key.env_var()).is_ok_and(|value| value == secret) =>
+            {
+                Ok(())
+            }
+            CredentialBackend::Env => Err(ProxyAuthK8sError::CredentialStoreReadOnly(format!(
+                "export {} instead of logging in",
+                key.env_var()
+            ))),
+        }
+    }
+
+    /// Remove a secret, removing a missing secret is not an error
+    pub fn delete(&self, key: &CredentialKey) -> Result<(), ProxyAuthK8sError> {
+        match self.backend {
+            CredentialBackend::Keyring => match keyring_entry(key)?.delete_credential() {
+                Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(err) => Err(keyring_error(err, |e| {
+                    ProxyAuthK8sError::KeyringDeleteError(format!("{}: {}", key, e))
+                })),
+            },
+            CredentialBackend::File => self.with_file(false, |secrets| {
+                let removed = secrets.remove(&key.to_string()).is_some();
+                Ok(((), removed))
+            }),
+            // The variables belong to the caller, there is nothing to remove
+            CredentialBackend::Env => Ok(()),
+        }
+    }
+
+    /// Run `f` on the decrypted secrets, the file is rewritten if `f` reports a change
+    /// The file is locked for the whole call, so the change of another process is read before being written over
+    fn with_file<T>(
+        &self,
+        create: bool,
+        f: impl FnOnce(&mut HashMap<String, String>) -> Result<(T, bool), ProxyAuthK8sError>,
+    ) -> Result<T, ProxyAuthK8sError> {
+        let mut state = self.file_state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.is_none() && !self.file_path.exists() && !create {
+            // Nothing stored yet, no need to ask for the passphrase
+            let mut secrets = HashMap::new();
+            return f(&mut secrets).map(|(result, _)| result);
+        }
+        let _lock = self.lock_file()?;
+        let modified = self.modified();
+        let file_state = match state.take() {
+            Some(file_state) if file_state.modified == modified => file_state,
+            Some(file_state) => {
+                debug!("{} changed, reloading it", self.file_path.display());
+                let secrets = self.read_secrets(&file_state.passphrase)?;
+                FileState {
+                    passphrase: file_state.passphrase,
+                    secrets,
+                    modified,
+                }
+            }
+            None => {
+                let passphrase = passphrase(&self.file_path)?;
+                let secrets = self.read_secrets(&passphrase)?;
+                FileState {
+                    passphrase,
+                    secrets,
+                    modified,
+                }
+            }
+        };
+        let file_state = state.insert(file_state);
+        let (result, changed) = f(&mut file_state.secrets)?;
+        if changed {
+            self.save_file(file_state)?;
+            file_state.modified = self.modified();
+        }
+        Ok(result)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Exclusive lock of the credential file, held by other CLI processes while they read or write it
+    fn lock_file(&self) -> Result<fs::File, ProxyAuthK8sError> {
+        let path = self.file_path.with_extension("age.lock");
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| {
+                ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+                    "Couldn't open {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        file.lock().map_err(|e| {
+            ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+                "Couldn't lock {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(file)
+    }
+
+    /// Decrypt the secrets of the file, none if it doesn't exist yet
+    fn read_secrets(
+        &self,
+        passphrase: &SecretString,
+    ) -> Result<HashMap<String, String>, ProxyAuthK8sError> {
+        if !self.file_path.exists() {
+            return Ok(HashMap::new());
+        }
+        let ciphertext = fs::read(&self.file_path).map_err(|e| {
+            ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+                "Couldn't read {}: {}",
+                self.file_path.display(),
+                e
+            ))
+        })?;
+        let plaintext = age::decrypt(&scrypt::Identity::new(passphrase.clone()), &ciphertext)
+            .map_err(|e| {
+                ProxyAuthK8sError::CredentialDecryptError(format!(
+                    "{}: {}, check {}",
+                    self.file_path.display(),
+                    e,
+                    PASSPHRASE_ENV
+                ))
+            })?;
+        serde_json::from_slice(&plaintext).map_err(|e| {
+            ProxyAuthK8sError::CredentialDecryptError(format!(
+                "{} is corrupted: {}",
+                self.file_path.display(),
+                e
+            ))
+        })
+    }
+
+    fn save_file(&self, state: &FileState) -> Result<(), ProxyAuthK8sError> {
+        let plaintext = serde_json::to_vec(&state.secrets)
+            .map_err(|e| ProxyAuthK8sError::CredentialWriteError(e.to_string()))?;
+        #[allow(unused_mut)]
+        let mut recipient = scrypt::Recipient::new(state.passphrase.clone());
+        // The default work factor takes a second per encryption, too slow for the tests
+        #[cfg(test)]
+        recipient.set_work_factor(10);
+        let ciphertext = age::encrypt(&recipient, &plaintext).map_err(|e| {
+            ProxyAuthK8sError::CredentialWriteError(format!(
+                "Couldn't encrypt {}: {}",
+                self.file_path.display(),
+                e
+            ))
+        })?;
+        write_private(&self.file_path, &ciphertext).map_err(|e| {
+            ProxyAuthK8sError::CredentialWriteError(format!(
+                "Couldn't write {}: {}",
+                self.file_path.display(),
+                e
+            ))
+        })
+    }
+}
+
+fn keyring_entry(key: &CredentialKey) -> Result<Entry, ProxyAuthK8sError> {
+    Entry::new(KEYRING_SERVICE, &key.to_string()).map_err(|err| {
+        keyring_error(err, |e| {
+            ProxyAuthK8sError::KeyringReadError(format!("{}: {}", key, e))
+        })
+    })
+}
+
+/// A missing keyring service is reported with the backends to use instead
+fn keyring_error(
+    err: keyring::Error,
+    other: impl FnOnce(keyring::Error) -> ProxyAuthK8sError,
+) -> ProxyAuthK8sError {
+    debug!("Keyring error: {:?}", err);
+    match err {
+        keyring::Error::PlatformFailure(e) | keyring::Error::NoStorageAccess(e) => {
+            ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+                "the OS keyring can't be used ({}), move the tokens to the encrypted file with `config migrate-credentials --to file` or set PROXYAUTH_CREDENTIAL_BACKEND=env",
+                e
+            ))
+        }
+        err => other(err),
+    }
+}
+
+/// Passphrase of the credential file, from the environment or asked on the terminal
+fn passphrase(path: &Path) -> Result<SecretString, ProxyAuthK8sError> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(SecretString::from(passphrase));
+        }
+    }
+    let prompt = if path.exists() {
+        format!("Passphrase of {}: ", path.display())
+    } else {
+        format!("New passphrase for {}: ", path.display())
+    };
+    match rpassword::prompt_password(prompt) {
+        Ok(passphrase) if !passphrase.is_empty() => Ok(SecretString::from(passphrase)),
+        Ok(_) => Err(ProxyAuthK8sError::CredentialDecryptError(
+            "The passphrase can't be empty".to_string(),
+        )),
+        Err(e) => Err(ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+            "no terminal to ask the passphrase of {} ({}), set {}",
+            path.display(),
+            e,
+            PASSPHRASE_ENV
+        ))),
+    }
+}
+
+/// Write the file through a private temporary file of the same directory renamed over it,
+/// a crash or a full disk never leaves a truncated credential file
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let tmp_path = path.with_extension(format!("age.{}.tmp", std::process::id()));
+    let result = (|| {
+        let mut file = create_private(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> std::io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+/// Keep the passphrase out of the debug output
+impl fmt::Debug for CredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredentialStore")
+            .field("backend", &self.backend)
+            .field("file_path", &self.file_path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var() {
+        let url = "http://localhost:5437";
+        assert_eq!(
+            CredentialKey::server(url).env_var(),
+            "PROXYAUTH_SERVER_TOKEN__LOCALHOST_3A5437"
+        );
+        assert_eq!(
+            CredentialKey::server(url)
+                .with_kind("refresh_token")
+                .env_var(),
+            "PROXYAUTH_SERVER_REFRESH_TOKEN__LOCALHOST_3A5437"
+        );
+        assert_eq!(
+            CredentialKey::cluster(url, "default", "local-sso").env_var(),
+            "PROXYAUTH_TOKEN__LOCALHOST_3A5437__DEFAULT__LOCAL_2DSSO"
+        );
+        assert_eq!(
+            CredentialKey::cluster(url, "default", "local-sso")
+                .with_kind("id_token")
+                .env_var(),
+            "PROXYAUTH_ID_TOKEN__LOCALHOST_3A5437__DEFAULT__LOCAL_2DSSO"
+        );
+
+        // Keys that only differ by their server or by a separator get their own variable
+        let keys = [
+            CredentialKey::cluster(url, "team", "b-c"),
+            CredentialKey::cluster(url, "team-b", "c"),
+            CredentialKey::cluster(url, "team", "b.c"),
+            CredentialKey::cluster(url, "team", "b_c"),
+            CredentialKey::cluster("https://proxy.example.com", "team", "b-c"),
+            CredentialKey::cluster("https://proxy-example.com", "team", "b-c"),
+        ];
+        let vars: std::collections::HashSet<String> =
+            keys.iter().map(CredentialKey::env_var).collect();
+        assert_eq!(vars.len(), keys.len());
+    }
+
+    /// Store of the file backend in a fresh directory, with the passphrase already known
+    fn file_store(dir: &Path, passphrase: &str) -> CredentialStore {
+        let store =
+            CredentialStore::new(CredentialBackend::File, &dir.join("proxyauth_config.yaml"));
+        *store.file_state.lock().unwrap() = Some(FileState {
+            passphrase: SecretString::from(passphrase.to_string()),
+            secrets: HashMap::new(),
+            modified: None,
+        });
+        store
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("proxyauth-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_backend() {
+        let dir = test_dir("credential-store");
+        let key = CredentialKey::cluster("http://localhost:5437", "dev", "c1");
+
+        let store = file_store(&dir, "correct horse");
+        store.set(&key, "cluster-token").unwrap();
+        assert_eq!(store.get(&key).unwrap().as_deref(), Some("cluster-token"));
+        let ciphertext = fs::read(store.file_path()).unwrap();
+        assert!(!String::from_utf8_lossy(&ciphertext).contains("cluster-token"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(store.file_path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Only the credential file and its lock are left, the temporary file was renamed
+        let mut files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "proxyauth_credentials.age",
+                "proxyauth_credentials.age.lock"
+            ]
+        );
+
+        // Another process reads the file written by the first one
+        let other = file_store(&dir, "correct horse");
+        assert_eq!(other.get(&key).unwrap().as_deref(), Some("cluster-token"));
+        other.delete(&key).unwrap();
+        assert_eq!(other.get(&key).unwrap(), None);
+        // The first store reloads the file changed by the other one
+        assert_eq!(store.get(&key).unwrap(), None);
+
+        store.set(&key, "new-token").unwrap();
+        let wrong = file_store(&dir, "wrong passphrase");
+        assert!(matches!(
+            wrong.get(&key),
+            Err(ProxyAuthK8sError::CredentialDecryptError(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
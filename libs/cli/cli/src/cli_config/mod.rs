@@ -2,14 +2,19 @@ use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 
+pub mod catalog;
 pub mod cli_cluster_config;
 pub mod cli_server_config;
+pub mod credential_store;
 pub mod error;
 
 use cli_server_config::CliServerConfig;
 
 use crate::{
-    cli_config::{cli_cluster_config::CliClusterConfig, error::CliConfigError},
+    cli_config::{
+        catalog::DEFAULT_CATALOG_TTL, cli_cluster_config::CliClusterConfig,
+        credential_store::CredentialBackend, error::CliConfigError,
+    },
     error::ProxyAuthK8sError,
 };
 
@@ -17,6 +22,16 @@ use crate::{
 pub struct CliConfig {
     pub default_server_name: String,
     pub servers: HashMap<String, CliServerConfig>,
+    /// Where the tokens are stored, the OS keyring by default
+    #[serde(default)]
+    pub credential_backend: CredentialBackend,
+    /// Seconds the cached cluster list of a server is used before asking it again
+    #[serde(default = "default_catalog_ttl")]
+    pub catalog_ttl: u64,
+}
+
+fn default_catalog_ttl() -> u64 {
+    DEFAULT_CATALOG_TTL
 }
 
 impl Default for CliConfig {
@@ -37,7 +52,21 @@ impl CliConfig {
         CliConfig {
             default_server_name: "".to_string(),
             servers: vec![].into_iter().collect(),
+            credential_backend: CredentialBackend::default(),
+            catalog_ttl: DEFAULT_CATALOG_TTL,
+        }
+    }
+
+    /// `$HOME/.kube/proxyauth_config.yaml`, None without HOME
+    pub fn default_path() -> Option<PathBuf> {
+        let home_env = std::env::var("HOME").unwrap_or_default();
+        if home_env.is_empty() {
+            return None;
         }
+        Some(PathBuf::from(format!(
+            "{}/.kube/proxyauth_config.yaml",
+            home_env
+        )))
     }
 
     pub fn clear(&mut self) -> &Self {
@@ -134,7 +163,11 @@ impl CliConfig {
                 ))
             }
         };
-        let server_name = host.replace(".", "-").replace(":", "-");
+        // Same name as the server configuration, the port is part of it
+        let server_name = match parsed_url.port() {
+            Some(port) => format!("{}-{}", host.replace(".", "-"), port),
+            None => host.replace(".", "-").replace(":", "-"),
+        };
         let ns = parsed_url
             .path_segments()
             .and_then(|mut segments| segments.nth(1))
@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use clap::CommandFactory;
+use clap_complete::{engine::CompletionCandidate, CompleteEnv};
+
+use crate::{
+    cli_config::{catalog::CatalogCache, cli_server_config::CliServerConfig, CliConfig},
+    Cli,
+};
+
+/// Prefix of the completion executable kubectl looks up for a plugin, `kubectl_complete-proxyauth`
+const KUBECTL_COMPLETE_PREFIX: &str = "kubectl_complete-";
+/// Cobra `ShellCompDirectiveNoFileComp`, the candidates are complete without file names
+const NO_FILE_COMP_DIRECTIVE: u8 = 4;
+
+/// Value of a flag in the arguments, `-n ns`, `--namespace ns` or `--namespace=ns`, the last one wins
+fn flag_value(args: &[String], short: &str, long: &str) -> Option<String> {
+    args.iter().enumerate().rev().find_map(|(i, arg)| {
+        if arg == short || arg == long {
+            args.get(i + 1).filter(|value| !value.is_empty()).cloned()
+        } else {
+            arg.strip_prefix(long)
+                .and_then(|value| value.strip_prefix('='))
+                .map(str::to_string)
+        }
+    })
+}
+
+/// Value of a flag already typed on the command line
+fn typed_flag(short: &str, long: &str) -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    flag_value(&args, short, long)
+}
+
+/// Configuration selected by the typed `--proxy-auth-config`, None if it can't be read
+fn typed_config() -> Option<(CliConfig, PathBuf)> {
+    let config_path = typed_flag("-p", "--proxy-auth-config")
+        .map(PathBuf::from)
+        .or_else(CliConfig::default_path)?;
+    let config = CliConfig::read_from_file(config_path.clone()).ok()?;
+    Some((config, config_path))
+}
+
+/// Servers matching `server_url`, all the configured ones by URL otherwise
+fn selected_servers(config: &CliConfig, server_url: Option<String>) -> Vec<&CliServerConfig> {
+    match server_url {
+        Some(url) => config
+            .get_server_config_by_url(Some(url))
+            .into_iter()
+            .collect(),
+        None => {
+            let mut servers: Vec<&CliServerConfig> = config.servers.values().collect();
+            servers.sort_by(|a, b| a.url.cmp(&b.url));
+            servers
+        }
+    }
+}
+
+/// `(namespace, cluster, logged in)` known for the server, from its cached list and its logged in clusters
+/// The cache is read whatever its age, completion never calls the server
+fn known_clusters(
+    server: &CliServerConfig,
+    config: &CliConfig,
+    config_path: &Path,
+) -> Vec<(String, String, bool)> {
+    let mut clusters: BTreeMap<(String, String), bool> = server
+        .clusters
+        .keys()
+        .filter_map(|key| key.split_once('/'))
+        .map(|(ns, cluster)| ((ns.to_string(), cluster.to_string()), true))
+        .collect();
+    if let Some(catalog) = CatalogCache::new(config_path, config.catalog_ttl).read(&server.url) {
+        for cluster in catalog.clusters.clusters {
+            clusters
+                .entry((cluster.namespace, cluster.name))
+                .or_insert(false);
+        }
+    }
+    clusters
+        .into_iter()
+        .map(|((ns, cluster), logged_in)| (ns, cluster, logged_in))
+        .collect()
+}
+
+/// URL of each configured server with its name
+fn server_values(config: &CliConfig) -> BTreeMap<String, String> {
+    config
+        .servers
+        .iter()
+        .map(|(name, server)| {
+            let help = if *name == config.default_server_name {
+                format!("{} (default)", name)
+            } else {
+                name.clone()
+            };
+            (server.url.clone(), help)
+        })
+        .collect()
+}
+
+/// Default namespace of each server and namespaces of their known clusters, with where they come from
+fn namespace_values(
+    config: &CliConfig,
+    config_path: &Path,
+    server_url: Option<String>,
+) -> BTreeMap<String, String> {
+    let mut namespaces: BTreeMap<String, String> = BTreeMap::new();
+    for server in selected_servers(config, server_url) {
+        let server_name = server.url_to_name();
+        namespaces
+            .entry(server.namespace.clone())
+            .or_insert_with(|| format!("default of {}", server_name));
+        for (ns, _, _) in known_clusters(server, config, config_path) {
+            namespaces.entry(ns).or_insert_with(|| server_name.clone());
+        }
+    }
+    namespaces
+}
+
+/// Known clusters of the servers in `namespace` if any, with their namespace and server
+fn cluster_values(
+    config: &CliConfig,
+    config_path: &Path,
+    server_url: Option<String>,
+    namespace: Option<String>,
+) -> BTreeMap<String, String> {
+    let mut clusters: BTreeMap<String, String> = BTreeMap::new();
+    for server in selected_servers(config, server_url) {
+        for (ns, cluster, logged_in) in known_clusters(server, config, config_path) {
+            if namespace.as_ref().is_some_and(|namespace| *namespace != ns) {
+                continue;
+            }
+            clusters.entry(cluster).or_insert_with(|| {
+                format!(
+                    "{} on {}{}",
+                    ns,
+                    server.url_to_name(),
+                    if logged_in { ", logged in" } else { "" }
+                )
+            });
+        }
+    }
+    clusters
+}
+
+fn to_candidates(values: BTreeMap<String, String>) -> Vec<CompletionCandidate> {
+    values
+        .into_iter()
+        .map(|(value, help)| CompletionCandidate::new(value).help(Some(help.into())))
+        .collect()
+}
+
+/// URLs of the configured servers, described by their name
+pub fn server_candidates() -> Vec<CompletionCandidate> {
+    let Some((config, _)) = typed_config() else {
+        return vec![];
+    };
+    to_candidates(server_values(&config))
+}
+
+/// Default namespaces of the typed `--server-url` servers and namespaces of their known clusters
+pub fn namespace_candidates() -> Vec<CompletionCandidate> {
+    let Some((config, config_path)) = typed_config() else {
+        return vec![];
+    };
+    to_candidates(namespace_values(
+        &config,
+        &config_path,
+        typed_flag("-s", "--server-url"),
+    ))
+}
+
+/// Known clusters of the typed `--server-url` servers, in the typed `--namespace` if any
+pub fn cluster_candidates() -> Vec<CompletionCandidate> {
+    let Some((config, config_path)) = typed_config() else {
+        return vec![];
+    };
+    to_candidates(cluster_values(
+        &config,
+        &config_path,
+        typed_flag("-s", "--server-url"),
+        typed_flag("-n", "--namespace"),
+    ))
+}
+
+/// Answer kubectl with the Cobra `__complete` protocol, one `value\tdescription` per line then the directive
+/// kubectl runs `kubectl_complete-proxyauth <args> <word>` when completing `kubectl proxyauth`
+fn kubectl_complete(bin: &str, words: Vec<OsString>) {
+    let mut args = vec![OsString::from(bin)];
+    args.extend(words);
+    if args.len() == 1 {
+        args.push(OsString::new());
+    }
+    let index = args.len() - 1;
+    let current_dir = env::current_dir().ok();
+    let candidates =
+        clap_complete::engine::complete(&mut Cli::command(), args, index, current_dir.as_deref())
+            .unwrap_or_default();
+    for candidate in candidates.iter().filter(|c| !c.is_hide_set()) {
+        let value = candidate.get_value().to_string_lossy();
+        match candidate
+            .get_help()
+            .and_then(|help| help.to_string().lines().next().map(str::to_string))
+        {
+            Some(help) if !help.is_empty() => println!("{}\t{}", value, help),
+            _ => println!("{}", value),
+        }
+    }
+    println!(":{}", NO_FILE_COMP_DIRECTIVE);
+}
+
+/// Handle a completion request and exit, return for a regular run
+/// `COMPLETE=<shell> kubectl-proxyauth` prints the bash, zsh, fish, elvish or powershell registration
+/// Invoked as `kubectl_complete-proxyauth`, or with `__complete`, kubectl plugin completion is answered
+pub fn complete() {
+    let args: Vec<OsString> = env::args_os().collect();
+    let bin = args
+        .first()
+        .and_then(|arg0| Path::new(arg0).file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "kubectl-proxyauth".to_string());
+    if bin.starts_with(KUBECTL_COMPLETE_PREFIX) {
+        kubectl_complete(&bin, args.into_iter().skip(1).collect());
+        std::process::exit(0);
+    }
+    if args.get(1).is_some_and(|arg| arg == "__complete") {
+        kubectl_complete(&bin, args.into_iter().skip(2).collect());
+        std::process::exit(0);
+    }
+    CompleteEnv::with_factory(Cli::command).bin(bin).complete();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use client_api::models::{GetAllVisibleClusterBody, VisibleCluster};
+
+    use crate::cli_config::{catalog::CachedCatalog, cli_cluster_config::CliClusterConfig};
+
+    use super::*;
+
+    const A: &str = "https://a.example.com";
+    const B: &str = "https://b.example.com";
+
+    /// Server A is logged in to dev/c1 and has prod/c2 in its cached list, server B is logged in to dev/c3
+    fn config(dir: &Path) -> (CliConfig, PathBuf) {
+        let config_path = dir.join("proxyauth_config.yaml");
+        let mut config = CliConfig::new();
+        for (url, namespace, cluster) in [(A, "team-a", "dev/c1"), (B, "team-b", "dev/c3")] {
+            let mut server = CliServerConfig::new(url.to_string());
+            server.namespace = namespace.to_string();
+            server
+                .clusters
+                .insert(cluster.to_string(), CliClusterConfig::new());
+            config.servers.insert(
+                CliServerConfig::url_to_name_from_string(url.to_string()),
+                server,
+            );
+        }
+        config.default_server_name = CliServerConfig::url_to_name_from_string(A.to_string());
+        let clusters = GetAllVisibleClusterBody {
+            clusters: vec![
+                VisibleCluster::new(true, "c1".to_string(), "dev".to_string(), true),
+                VisibleCluster::new(true, "c2".to_string(), "prod".to_string(), false),
+            ],
+        };
+        CatalogCache::new(&config_path, config.catalog_ttl).write(
+            A,
+            &CachedCatalog {
+                etag: None,
+                fetched_at: 0,
+                clusters,
+            },
+        );
+        (config, config_path)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("proxyauth-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn pairs(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(value, help)| (value.to_string(), help.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flag_value() {
+        let args: Vec<String> = [
+            "kubectl-proxyauth",
+            "-n",
+            "dev",
+            "login",
+            "--namespace=prod",
+            "-s",
+            "",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            flag_value(&args, "-n", "--namespace"),
+            Some("prod".to_string())
+        );
+        assert_eq!(
+            flag_value(&args[..3], "-n", "--namespace"),
+            Some("dev".to_string())
+        );
+        // A flag still being typed has no value
+        assert_eq!(flag_value(&args, "-s", "--server-url"), None);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let dir = temp_dir("completion");
+        let (config, config_path) = config(&dir);
+
+        assert_eq!(
+            server_values(&config),
+            pairs(&[(A, "a-example-com (default)"), (B, "b-example-com"),])
+        );
+        assert_eq!(
+            namespace_values(&config, &config_path, None),
+            pairs(&[
+                ("dev", "a-example-com"),
+                ("prod", "a-example-com"),
+                ("team-a", "default of a-example-com"),
+                ("team-b", "default of b-example-com"),
+            ])
+        );
+        assert_eq!(
+            namespace_values(&config, &config_path, Some(B.to_string())),
+            pairs(&[
+                ("dev", "b-example-com"),
+                ("team-b", "default of b-example-com")
+            ])
+        );
+        assert_eq!(
+            cluster_values(&config, &config_path, None, None),
+            pairs(&[
+                ("c1", "dev on a-example-com, logged in"),
+                ("c2", "prod on a-example-com"),
+                ("c3", "dev on b-example-com, logged in"),
+            ])
+        );
+        assert_eq!(
+            cluster_values(
+                &config,
+                &config_path,
+                Some(A.to_string()),
+                Some("dev".to_string())
+            ),
+            pairs(&[("c1", "dev on a-example-com, logged in")])
+        );
+        // An unknown server has no candidate
+        assert!(cluster_values(
+            &config,
+            &config_path,
+            Some("https://c.example.com".to_string()),
+            None
+        )
+        .is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,88 @@
+use tracing::{error, info, warn};
+
+use crate::{
+    cli_config::credential_store::{CredentialBackend, CredentialStore},
+    config::ConfigCommands,
+    ctx::CliCtx,
+    error::ProxyAuthK8sError,
+};
+
+impl ConfigCommands {
+    pub fn handle_migrate_credentials(&self, ctx: &mut CliCtx) {
+        if let ConfigCommands::MigrateCredentials { to } = self {
+            let source = CredentialStore::current();
+            if source.backend == *to {
+                info!("Credentials are already stored in the {} backend.", to);
+                return;
+            }
+            let target = CredentialStore::new(*to, &ctx.config_path);
+            let keys: Vec<_> = ctx
+                .config
+                .servers
+                .values()
+                .flat_map(|server| server.credential_keys())
+                .collect();
+
+            // Copy everything first, the source is only cleaned once the configuration points to the target
+            let mut migrated = Vec::new();
+            for key in keys {
+                let result: Result<(), ProxyAuthK8sError> = match source.get(&key) {
+                    Ok(Some(secret)) if *to == CredentialBackend::Env => {
+                        println!(
+                            "export {}='{}'",
+                            key.env_var(),
+                            secret.replace('\'', "'\\''")
+                        );
+                        Ok(())
+                    }
+                    Ok(Some(secret)) => target.set(&key, &secret),
+                    Ok(None) => continue,
+                    // An unusable OS keyring holds nothing to migrate
+                    Err(ProxyAuthK8sError::CredentialStoreUnavailable(e)) => {
+                        warn!("Nothing read from the {} backend: {}", source.backend, e);
+                        break;
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    error!(
+                        "Migration to the {} backend aborted on {}: {}. The configuration is unchanged.",
+                        to, key, e
+                    );
+                    return;
+                }
+                migrated.push(key);
+            }
+
+            ctx.config.credential_backend = *to;
+            if let Err(e) = ctx.config.write_to_file(ctx.config_path.clone()) {
+                error!("Failed to update config file: {}", e);
+                return;
+            }
+            if *to == CredentialBackend::Env {
+                warn!("The exported variables contain secrets, store them in the secrets of the CI. The {} backend is kept as is.", source.backend);
+            } else {
+                for key in &migrated {
+                    if let Err(e) = source.delete(key) {
+                        warn!(
+                            "Couldn't remove {} from the {} backend: {}",
+                            key, source.backend, e
+                        );
+                    }
+                }
+            }
+            if *to == CredentialBackend::File {
+                info!(
+                    "Credentials are stored in {}.",
+                    target.file_path().display()
+                );
+            }
+            info!(
+                "Migrated {} secret(s) from the {} backend to the {} backend.",
+                migrated.len(),
+                source.backend,
+                to
+            );
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use comfy_table::Table;
 use serde::{Deserialize, Serialize};
 use std::vec;
 
-use crate::{cli_config::cli_server_config::CliServerConfig, ctx::ContextFormat};
+use crate::{
+    cli_config::cli_server_config::CliServerConfig,
+    ctx::ContextFormat,
+    output::{to_output, OutputItem},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetOutput {
@@ -10,6 +13,8 @@ pub struct GetOutput {
     pub url: String,
     pub default_namespace: String,
     pub has_clusters: bool,
+    /// Logged in clusters, as `namespace/name`
+    pub clusters: Vec<String>,
 }
 
 impl GetOutput {
@@ -17,30 +22,49 @@ impl GetOutput {
         cli_server_config: CliServerConfig,
         default_server_name: String,
     ) -> GetOutput {
+        let mut clusters: Vec<String> = cli_server_config.clusters.keys().cloned().collect();
+        clusters.sort();
         GetOutput {
             is_default: cli_server_config.url_to_name() == default_server_name,
             url: cli_server_config.url,
             default_namespace: cli_server_config.namespace,
             has_clusters: !cli_server_config.clusters.is_empty(),
+            clusters,
         }
     }
+}
+
+impl OutputItem for GetOutput {
+    const KIND: &'static str = "server";
 
-    pub fn to_row(&self) -> Vec<String> {
-        vec![
+    fn to_row_headers(wide: bool) -> Vec<String> {
+        let mut headers = vec![
+            "Is Default".to_string(),
+            "Server URL".to_string(),
+            "Default Namespace".to_string(),
+            "Has Clusters".to_string(),
+        ];
+        if wide {
+            headers.push("Clusters".to_string());
+        }
+        headers
+    }
+
+    fn to_row(&self, wide: bool) -> Vec<String> {
+        let mut row = vec![
             self.is_default.to_string(),
             self.url.clone(),
             self.default_namespace.clone(),
             self.has_clusters.to_string(),
-        ]
+        ];
+        if wide {
+            row.push(self.clusters.join(","));
+        }
+        row
     }
 
-    pub fn to_row_headers() -> Vec<String> {
-        vec![
-            "Is Default".to_string(),
-            "Server URL".to_string(),
-            "Default Namespace".to_string(),
-            "Has Clusters".to_string(),
-        ]
+    fn name(&self) -> String {
+        CliServerConfig::url_to_name_from_string(self.url.clone())
     }
 }
 
@@ -62,29 +86,7 @@ impl VecGetOutput {
         }
     }
 
-    pub fn to_json(&self) -> String {
-        serde_json::to_string_pretty(self).unwrap_or_default()
-    }
-
-    pub fn to_yaml(&self) -> String {
-        serde_yaml::to_string(self).unwrap_or_default()
-    }
-
-    pub fn to_table(&self) -> String {
-        let mut table = Table::new();
-        table.load_preset(comfy_table::presets::NOTHING);
-        table.set_header(GetOutput::to_row_headers());
-        for item in &self.items {
-            table.add_row(item.to_row());
-        }
-        table.to_string()
-    }
-
     pub fn to_output(&self, format: ContextFormat) -> String {
-        match format {
-            ContextFormat::Json => self.to_json(),
-            ContextFormat::Yaml => self.to_yaml(),
-            ContextFormat::Table => self.to_table(),
-        }
+        to_output(self, &self.items, &format)
     }
 }
@@ -1,6 +1,10 @@
 use clap::Subcommand;
+use clap_complete::ArgValueCandidates;
+
+use crate::{cli_config::credential_store::CredentialBackend, completion};
 
 pub mod clear;
+pub mod credentials;
 pub mod get;
 pub mod get_output;
 pub mod set_def;
@@ -12,13 +16,13 @@ pub enum ConfigCommands {
     /// The two other flags need to be provided together
     SetDef {
         /// Set the default server URL
-        #[arg(short, long, value_name = "URL")]
+        #[arg(short, long, value_name = "URL", add = ArgValueCandidates::new(completion::server_candidates))]
         server_url: Option<String>,
         /// Set the default namespace for the selected server
-        #[arg(short, long, value_name = "NAMESPACE")]
+        #[arg(short, long, value_name = "NAMESPACE", add = ArgValueCandidates::new(completion::namespace_candidates))]
         namespace: Option<String>,
         /// Set the default server namespace
-        #[arg(short, long, value_name = "SERVER_NAME")]
+        #[arg(short, long, value_name = "SERVER_NAME", add = ArgValueCandidates::new(completion::server_candidates))]
         default_server: Option<String>,
     },
     /// Clear configuration options
@@ -27,22 +31,29 @@ pub enum ConfigCommands {
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         all: bool,
         /// Clear configuration for a specific server URL
-        #[arg(short, long, value_name = "URL")]
+        #[arg(short, long, value_name = "URL", add = ArgValueCandidates::new(completion::server_candidates))]
         server_url: Option<String>,
     },
     /// Get configuration
     /// If no flags are provided, shows the current configuration
     Get {
         /// Filter by server URL
-        #[arg(short, long, value_name = "URL")]
+        #[arg(short, long, value_name = "URL", add = ArgValueCandidates::new(completion::server_candidates))]
         server_url: Option<String>,
         /// Filter by namespace
-        #[arg(short, long, value_name = "NAMESPACE")]
+        #[arg(short, long, value_name = "NAMESPACE", add = ArgValueCandidates::new(completion::namespace_candidates))]
         namespace: Option<String>,
         /// list all configurations
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         list: bool,
     },
+    /// Move the stored tokens to another credential backend and use it from now on
+    /// Migrating to `env` prints the variables to export and keeps the current store
+    MigrateCredentials {
+        /// Backend to move the tokens to
+        #[arg(long, value_name = "BACKEND")]
+        to: CredentialBackend,
+    },
 }
 
 impl ConfigCommands {
@@ -57,6 +68,9 @@ impl ConfigCommands {
             ConfigCommands::Get { .. } => {
                 self.handle_get(ctx);
             }
+            ConfigCommands::MigrateCredentials { .. } => {
+                self.handle_migrate_credentials(ctx);
+            }
         }
     }
 }
@@ -6,6 +6,7 @@ use crate::{
 };
 
 pub mod output;
+pub mod sync;
 
 impl CliCtx {
     pub fn handle_context(&mut self, context_name: Option<String>, list: bool, set: bool) {
@@ -1,12 +1,12 @@
 use std::vec;
 
-use comfy_table::Table;
 use kube::config::NamedContext;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     cli_config::CliConfig,
     ctx::{CliCtx, ContextFormat},
+    output::{to_output, OutputItem},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -57,9 +57,32 @@ impl GetContextOutput {
             proxy_name: Some(url_info.cluster_name),
         })
     }
+}
+
+impl OutputItem for GetContextOutput {
+    const KIND: &'static str = "context";
 
-    pub fn to_row(&self) -> Vec<String> {
-        vec![
+    fn to_row_headers(wide: bool) -> Vec<String> {
+        let mut headers = vec![
+            "CURRENT".to_string(),
+            "NAME".to_string(),
+            "CLUSTER".to_string(),
+            "AUTHINFO".to_string(),
+            "NAMESPACE".to_string(),
+            "IS PROXY AUTH".to_string(),
+        ];
+        if wide {
+            headers.extend([
+                "PROXY SERVER URL".to_string(),
+                "PROXY NAMESPACE".to_string(),
+                "PROXY NAME".to_string(),
+            ]);
+        }
+        headers
+    }
+
+    fn to_row(&self, wide: bool) -> Vec<String> {
+        let mut row = vec![
             if self.current_context {
                 "*".to_string()
             } else {
@@ -74,24 +97,19 @@ impl GetContextOutput {
             } else {
                 "No".to_string()
             },
-            self.proxy_server_url.clone().unwrap_or_default(),
-            self.proxy_namespace.clone().unwrap_or_default(),
-            self.proxy_name.clone().unwrap_or_default(),
-        ]
+        ];
+        if wide {
+            row.extend([
+                self.proxy_server_url.clone().unwrap_or_default(),
+                self.proxy_namespace.clone().unwrap_or_default(),
+                self.proxy_name.clone().unwrap_or_default(),
+            ]);
+        }
+        row
     }
 
-    pub fn to_row_header() -> Vec<String> {
-        vec![
-            "CURRENT".to_string(),
-            "NAME".to_string(),
-            "CLUSTER".to_string(),
-            "AUTHINFO".to_string(),
-            "NAMESPACE".to_string(),
-            "IS PROXY AUTH".to_string(),
-            "PROXY SERVER URL".to_string(),
-            "PROXY NAMESPACE".to_string(),
-            "PROXY NAME".to_string(),
-        ]
+    fn name(&self) -> String {
+        self.name.clone()
     }
 }
 
@@ -113,30 +131,7 @@ impl VecGetContextOutput {
         }
     }
 
-    pub fn to_json(&self) -> String {
-        serde_json::to_string_pretty(self).unwrap_or_default()
-    }
-
-    pub fn to_yaml(&self) -> String {
-        serde_yaml::to_string(self).unwrap_or_default()
-    }
-
-    pub fn to_table(&self) -> String {
-        let mut table = Table::new();
-        // remove tables borders
-        table.load_preset(comfy_table::presets::NOTHING);
-        table.set_header(GetContextOutput::to_row_header());
-        for item in &self.items {
-            table.add_row(item.to_row());
-        }
-        table.to_string()
-    }
-
     pub fn to_output(&self, format: ContextFormat) -> String {
-        match format {
-            ContextFormat::Json => self.to_json(),
-            ContextFormat::Yaml => self.to_yaml(),
-            ContextFormat::Table => self.to_table(),
-        }
+        to_output(self, &self.items, &format)
     }
 }
@@ -0,0 +1,388 @@
+use kube::config::{
+    AuthInfo, Cluster, Context, ExecConfig, ExecInteractiveMode, NamedAuthInfo, NamedCluster,
+    NamedContext,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    cli_config::{cli_server_config::CliServerConfig, CliConfig},
+    ctx::{CliCtx, ContextFormat},
+    output::{to_output, OutputItem},
+};
+
+/// Binary called by kubectl to get the cluster tokens
+pub const EXEC_COMMAND: &str = "kubectl-proxyauth";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    Added,
+    Updated,
+    Unchanged,
+    Removed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContextSyncOutput {
+    pub action: SyncAction,
+    pub context: String,
+    pub server: String,
+}
+
+impl OutputItem for ContextSyncOutput {
+    const KIND: &'static str = "context";
+
+    fn to_row_headers(_wide: bool) -> Vec<String> {
+        vec![
+            "ACTION".to_string(),
+            "CONTEXT".to_string(),
+            "SERVER".to_string(),
+        ]
+    }
+
+    fn to_row(&self, _wide: bool) -> Vec<String> {
+        vec![
+            match self.action {
+                SyncAction::Added => "+",
+                SyncAction::Updated => "~",
+                SyncAction::Unchanged => "",
+                SyncAction::Removed => "-",
+            }
+            .to_string(),
+            self.context.clone(),
+            self.server.clone(),
+        ]
+    }
+
+    fn name(&self) -> String {
+        self.context.clone()
+    }
+}
+
+impl ContextSyncOutput {
+    pub fn to_output(items: &[ContextSyncOutput], format: ContextFormat) -> String {
+        to_output(&items, items, &format)
+    }
+}
+
+/// Name shared by the cluster, user and context entries of a proxied cluster
+pub fn sync_entry_name(server_name: &str, ns: &str, cluster: &str) -> String {
+    format!("proxyauth-{}-{}-{}", server_name, ns, cluster)
+}
+
+/// URL of a cluster behind the server, as built by the server for its ProxyKubeApi
+pub fn proxy_cluster_url(server_url: &str, ns: &str, cluster: &str) -> String {
+    format!(
+        "{}/clusters/{}/{}",
+        server_url.trim_end_matches('/'),
+        ns,
+        cluster
+    )
+}
+
+/// Exec credential plugin calling `kubectl-proxyauth get-token` for the cluster
+pub fn exec_config(server_url: &str, ns: &str, cluster: &str) -> ExecConfig {
+    ExecConfig {
+        api_version: Some("client.authentication.k8s.io/v1".to_string()),
+        command: Some(EXEC_COMMAND.to_string()),
+        args: Some(vec![
+            "get-token".to_string(),
+            "--server-url".to_string(),
+            server_url.to_string(),
+            "--namespace".to_string(),
+            ns.to_string(),
+            cluster.to_string(),
+        ]),
+        env: None,
+        drop_env: None,
+        interactive_mode: Some(ExecInteractiveMode::IfAvailable),
+        provide_cluster_info: true,
+        cluster: None,
+    }
+}
+
+/// Name and server of the cluster entries written by sync for this server, and namespace if filtered, that were not synced
+/// The namespace is matched on the cluster URL, as the entry name can't tell `team` from `team-b`
+fn stale_sync_entries(
+    clusters: &[NamedCluster],
+    server_name: &str,
+    server_url: &str,
+    namespace: Option<&str>,
+    synced: &[String],
+) -> Vec<(String, String)> {
+    let prefix = format!("proxyauth-{}-", server_name);
+    let cluster_prefix = format!("{}/clusters/", server_url.trim_end_matches('/'));
+    clusters
+        .iter()
+        .filter(|c| c.name.starts_with(&prefix) && !synced.contains(&c.name))
+        .filter_map(|c| {
+            let server = c.cluster.as_ref()?.server.clone()?;
+            if !server.starts_with(&cluster_prefix) {
+                return None;
+            }
+            let url_info = CliConfig::proxy_url_to_tuple(&server).ok()?;
+            namespace
+                .is_none_or(|namespace| url_info.namespace == namespace)
+                .then(|| (c.name.clone(), server))
+        })
+        .collect()
+}
+
+impl CliCtx {
+    /// Write a cluster, user and context entry for each cluster visible on the server
+    /// Entries of the server whose cluster is no longer visible are removed, unless `no_prune`
+    pub async fn handle_context_sync(&mut self, dry_run: bool, no_prune: bool) {
+        let server_config =
+            match self
+                .config
+                .get_server_config_by_url(if self.server_url.is_empty() {
+                    None
+                } else {
+                    Some(self.server_url.clone())
+                }) {
+                Ok(config) => config.clone(),
+                Err(e) => {
+                    error!(
+                        "Error retrieving server configuration, please login to server first: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+        let clusters = match server_config.get_clusters_from_remote().await {
+            Ok(clusters) => clusters.clusters,
+            Err(e) => {
+                error!("Failed to retrieve clusters: {}", e);
+                return;
+            }
+        };
+        let server_name = CliServerConfig::url_to_name_from_string(server_config.url.clone());
+        let namespace_filter = (!self.namespace.is_empty()).then(|| self.namespace.clone());
+
+        let mut changes = Vec::new();
+        let mut synced = Vec::new();
+        for cluster in clusters.iter().filter(|cluster| {
+            cluster.enabled
+                && namespace_filter
+                    .as_ref()
+                    .is_none_or(|namespace| cluster.namespace == *namespace)
+        }) {
+            let name = sync_entry_name(&server_name, &cluster.namespace, &cluster.name);
+            let server = proxy_cluster_url(&server_config.url, &cluster.namespace, &cluster.name);
+            let action = self.upsert_sync_entry(
+                &name,
+                &server,
+                exec_config(&server_config.url, &cluster.namespace, &cluster.name),
+            );
+            synced.push(name.clone());
+            changes.push(ContextSyncOutput {
+                action,
+                context: name,
+                server,
+            });
+        }
+
+        if !no_prune {
+            let stale = stale_sync_entries(
+                &self.kubeconfig.clusters,
+                &server_name,
+                &server_config.url,
+                namespace_filter.as_deref(),
+                &synced,
+            );
+            for (name, server) in stale {
+                self.remove_sync_entry(&name);
+                changes.push(ContextSyncOutput {
+                    action: SyncAction::Removed,
+                    context: name,
+                    server,
+                });
+            }
+        }
+
+        let changed = changes
+            .iter()
+            .filter(|change| change.action != SyncAction::Unchanged)
+            .count();
+        if dry_run {
+            info!("Dry run, {} context(s) would change.", changed);
+        } else if changed > 0 {
+            if let Err(e) = self.write_kubeconfig() {
+                error!("Failed to write kubeconfig: {}", e);
+                return;
+            }
+            info!("{} context(s) synced from {}.", changed, server_config.url);
+        } else {
+            info!("Kubeconfig already in sync with {}.", server_config.url);
+        }
+        println!(
+            "{}",
+            ContextSyncOutput::to_output(&changes, self.format.clone())
+        );
+    }
+
+    pub(crate) fn upsert_sync_entry(
+        &mut self,
+        name: &str,
+        server: &str,
+        exec: ExecConfig,
+    ) -> SyncAction {
+        let mut action = SyncAction::Unchanged;
+        let kubeconfig = &mut self.kubeconfig;
+
+        match kubeconfig.clusters.iter_mut().find(|c| c.name == name) {
+            Some(existing) => {
+                let cluster = existing.cluster.get_or_insert_with(Cluster::default);
+                if cluster.server.as_deref() != Some(server) {
+                    cluster.server = Some(server.to_string());
+                    action = SyncAction::Updated;
+                }
+            }
+            None => {
+                kubeconfig.clusters.push(NamedCluster {
+                    name: name.to_string(),
+                    cluster: Some(Cluster {
+                        server: Some(server.to_string()),
+                        ..Default::default()
+                    }),
+                });
+                action = SyncAction::Added;
+            }
+        }
+
+        match kubeconfig.auth_infos.iter_mut().find(|u| u.name == name) {
+            Some(existing) => {
+                let auth_info = existing.auth_info.get_or_insert_with(AuthInfo::default);
+                let same_exec = auth_info.exec.as_ref().is_some_and(|current| {
+                    current.command == exec.command && current.args == exec.args
+                });
+                if !same_exec {
+                    auth_info.exec = Some(exec);
+                    if action == SyncAction::Unchanged {
+                        action = SyncAction::Updated;
+                    }
+                }
+            }
+            None => {
+                kubeconfig.auth_infos.push(NamedAuthInfo {
+                    name: name.to_string(),
+                    auth_info: Some(AuthInfo {
+                        exec: Some(exec),
+                        ..Default::default()
+                    }),
+                });
+                if action == SyncAction::Unchanged {
+                    action = SyncAction::Updated;
+                }
+            }
+        }
+
+        match kubeconfig.contexts.iter_mut().find(|c| c.name == name) {
+            Some(existing) => {
+                let context = existing.context.get_or_insert_with(Context::default);
+                if context.cluster != name || context.user.as_deref() != Some(name) {
+                    context.cluster = name.to_string();
+                    context.user = Some(name.to_string());
+                    if action == SyncAction::Unchanged {
+                        action = SyncAction::Updated;
+                    }
+                }
+            }
+            None => {
+                kubeconfig.contexts.push(NamedContext {
+                    name: name.to_string(),
+                    context: Some(Context {
+                        cluster: name.to_string(),
+                        user: Some(name.to_string()),
+                        ..Default::default()
+                    }),
+                });
+                if action == SyncAction::Unchanged {
+                    action = SyncAction::Updated;
+                }
+            }
+        }
+        action
+    }
+
+    fn remove_sync_entry(&mut self, name: &str) {
+        self.kubeconfig.clusters.retain(|c| c.name != name);
+        self.kubeconfig.auth_infos.retain(|u| u.name != name);
+        self.kubeconfig.contexts.retain(|c| c.name != name);
+        if self.kubeconfig.current_context.as_deref() == Some(name) {
+            warn!(
+                "Current context {} was removed, please select another one.",
+                name
+            );
+            self.kubeconfig.current_context = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named_cluster(name: &str, server: &str) -> NamedCluster {
+        NamedCluster {
+            name: name.to_string(),
+            cluster: Some(Cluster {
+                server: Some(server.to_string()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_stale_sync_entries() {
+        let url = "https://proxy.example.com";
+        let clusters = vec![
+            named_cluster(
+                "proxyauth-proxy-example-com-team-c1",
+                "https://proxy.example.com/clusters/team/c1",
+            ),
+            named_cluster(
+                "proxyauth-proxy-example-com-team-c2",
+                "https://proxy.example.com/clusters/team/c2",
+            ),
+            named_cluster(
+                "proxyauth-proxy-example-com-team-b-c1",
+                "https://proxy.example.com/clusters/team-b/c1",
+            ),
+            named_cluster(
+                "proxyauth-proxy-example-com-other",
+                "https://other.example.com/clusters/team/other",
+            ),
+            named_cluster("kind", "https://127.0.0.1:6443"),
+        ];
+        let synced = vec!["proxyauth-proxy-example-com-team-c1".to_string()];
+
+        let names = |stale: Vec<(String, String)>| {
+            stale.into_iter().map(|(name, _)| name).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(stale_sync_entries(
+                &clusters,
+                "proxy-example-com",
+                url,
+                Some("team"),
+                &synced
+            )),
+            vec!["proxyauth-proxy-example-com-team-c2"]
+        );
+        assert_eq!(
+            names(stale_sync_entries(
+                &clusters,
+                "proxy-example-com",
+                url,
+                None,
+                &synced
+            )),
+            vec![
+                "proxyauth-proxy-example-com-team-c2",
+                "proxyauth-proxy-example-com-team-b-c1"
+            ]
+        );
+    }
+}
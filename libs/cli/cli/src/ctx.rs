@@ -2,16 +2,59 @@ use clap::ValueEnum;
 use cli_trace::level::VerboseLevel;
 use kube::config::Kubeconfig;
 use serde::{Deserialize, Serialize};
-use std::{env, fs, path::PathBuf};
+use std::{env, fs, path::PathBuf, str::FromStr};
 
-use crate::{cli_config::CliConfig, error::ProxyAuthK8sError};
+use crate::{
+    cli_config::{
+        catalog::CatalogCache,
+        credential_store::{CredentialBackend, CredentialStore, BACKEND_ENV},
+        CliConfig,
+    },
+    error::ProxyAuthK8sError,
+    output::{jsonpath::JsonPath, parse_custom_columns, CustomColumn},
+};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, ValueEnum)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub enum ContextFormat {
     #[default]
     Table,
+    /// Table with the extra columns
+    Wide,
     Json,
     Yaml,
+    /// `kind/name` of each item
+    Name,
+    /// `jsonpath=<template>`, applied to the whole output
+    JsonPath(String),
+    /// `custom-columns=HEADER:.path,...`, applied to each item
+    CustomColumns(Vec<CustomColumn>),
+}
+
+impl FromStr for ContextFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(ContextFormat::Table),
+            "wide" => Ok(ContextFormat::Wide),
+            "json" => Ok(ContextFormat::Json),
+            "yaml" => Ok(ContextFormat::Yaml),
+            "name" => Ok(ContextFormat::Name),
+            _ => {
+                if let Some(template) = format.strip_prefix("jsonpath=") {
+                    JsonPath::parse(template)?;
+                    Ok(ContextFormat::JsonPath(template.to_string()))
+                } else if let Some(spec) = format.strip_prefix("custom-columns=") {
+                    Ok(ContextFormat::CustomColumns(parse_custom_columns(spec)?))
+                } else {
+                    Err(format!(
+                        "unknown output format '{}', expected one of: table, wide, json, yaml, name, jsonpath=<template>, custom-columns=<spec>",
+                        format
+                    ))
+                }
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -79,15 +122,10 @@ impl From<super::Cli> for CliCtx {
                 .is_some_and(|stem| stem == "kubectl")
         });
         // Load CLI configuration
-        let config_path = if let Some(path) = cli.proxy_auth_config {
-            path
-        } else {
-            let home_env = env::var("HOME").unwrap_or_default();
-            if home_env.is_empty() {
-                panic!("{}", ProxyAuthK8sError::ConfigPathCouldNotBeCalculated);
-            }
-            PathBuf::from(format!("{}/.kube/proxyauth_config.yaml", home_env))
-        };
+        let config_path = cli
+            .proxy_auth_config
+            .or_else(CliConfig::default_path)
+            .unwrap_or_else(|| panic!("{}", ProxyAuthK8sError::ConfigPathCouldNotBeCalculated));
         let config = if !config_path.exists() {
             match CliConfig::default().write_to_file(config_path.clone()) {
                 Ok(config) => config.clone(),
@@ -115,6 +153,22 @@ impl From<super::Cli> for CliCtx {
             })
         };
 
+        // The environment wins over the configuration, CI jobs can select the env backend without a config file
+        let credential_backend = match env::var(BACKEND_ENV) {
+            Ok(backend) => CredentialBackend::from_str(&backend, true).unwrap_or_else(|e| {
+                panic!(
+                    "{}",
+                    ProxyAuthK8sError::CredentialStoreUnavailable(format!(
+                        "invalid {}: {}",
+                        BACKEND_ENV, e
+                    ))
+                )
+            }),
+            Err(_) => config.credential_backend,
+        };
+        CredentialStore::init(credential_backend, &config_path);
+        CatalogCache::init(&config_path, config.catalog_ttl);
+
         CliCtx {
             namespace: cli.namespace,
             kubeconfig,
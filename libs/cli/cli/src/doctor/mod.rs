@@ -0,0 +1,525 @@
+use client_api::apis::{configuration::Configuration, health_api::health};
+use k8s_openapi::jiff::Timestamp;
+use kube::config::{ExecConfig, Kubeconfig};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tracing::{error, info, warn};
+
+use crate::{
+    cli_config::{
+        cli_cluster_config::CliClusterConfig,
+        cli_server_config::CliServerConfig,
+        credential_store::{CredentialBackend, CredentialKey, CredentialStore},
+        CliConfig,
+    },
+    ctx::{CliCtx, ContextFormat},
+    output::{to_output, OutputItem},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DoctorCheck {
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl OutputItem for DoctorCheck {
+    const KIND: &'static str = "check";
+
+    fn to_row_headers(_wide: bool) -> Vec<String> {
+        vec![
+            "CHECK".to_string(),
+            "STATUS".to_string(),
+            "DETAIL".to_string(),
+        ]
+    }
+
+    fn to_row(&self, _wide: bool) -> Vec<String> {
+        vec![
+            self.check.clone(),
+            match self.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            }
+            .to_string(),
+            self.detail.clone(),
+        ]
+    }
+
+    fn name(&self) -> String {
+        self.check.clone()
+    }
+}
+
+impl DoctorCheck {
+    fn new(check: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        DoctorCheck {
+            check: check.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DoctorReport {
+    pub api_version: String,
+    pub kind: String,
+    pub metadata: Option<serde_json::Value>,
+    pub items: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    pub fn new(items: Vec<DoctorCheck>) -> Self {
+        DoctorReport {
+            api_version: "v1".to_string(),
+            kind: "List".to_string(),
+            metadata: None,
+            items,
+        }
+    }
+
+    pub fn to_output(&self, format: ContextFormat) -> String {
+        to_output(self, &self.items, &format)
+    }
+}
+
+/// Cluster targeted by the positional argument and `--namespace` of a `get-token` exec entry
+fn exec_target(exec: &ExecConfig) -> Option<(Option<String>, Option<String>)> {
+    let args = exec.args.as_ref()?;
+    let command = exec.command.as_deref().unwrap_or_default();
+    if !command.contains("proxyauth") && !args.iter().any(|arg| arg == "proxyauth") {
+        return None;
+    }
+    let mut args = args.iter().skip_while(|arg| *arg != "get-token").skip(1);
+    let (mut namespace, mut cluster) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" | "--namespace" => namespace = args.next().cloned(),
+            "-s"
+            | "--server-url"
+            | "-k"
+            | "--kubeconfig"
+            | "-p"
+            | "--proxy-auth-config"
+            | "-c"
+            | "--context"
+            | "-f"
+            | "--format" => {
+                args.next();
+            }
+            arg if arg.starts_with('-') => {}
+            arg => cluster = Some(arg.to_string()),
+        }
+    }
+    Some((namespace, cluster))
+}
+
+/// Check the kubeconfig contexts pointing at the server against the clusters known to the CLI
+fn check_kubeconfig(kubeconfig: &Kubeconfig, server: &CliServerConfig) -> Vec<DoctorCheck> {
+    let server_name = server.url_to_name();
+    let mut checks = Vec::new();
+    for context in &kubeconfig.contexts {
+        let Some(ctx) = &context.context else {
+            continue;
+        };
+        let Some(url) = kubeconfig
+            .clusters
+            .iter()
+            .find(|c| c.name == ctx.cluster)
+            .and_then(|c| c.cluster.as_ref())
+            .and_then(|c| c.server.clone())
+        else {
+            continue;
+        };
+        let Ok(url_info) = CliConfig::proxy_url_to_tuple(&url) else {
+            continue;
+        };
+        if url_info.server_name != server_name {
+            continue;
+        }
+        let name = format!("kubeconfig {}", context.name);
+        let exec = ctx
+            .user
+            .as_ref()
+            .and_then(|user| kubeconfig.auth_infos.iter().find(|u| &u.name == user))
+            .and_then(|user| user.auth_info.as_ref())
+            .and_then(|auth_info| auth_info.exec.as_ref());
+        let check = match exec.and_then(exec_target) {
+            None => DoctorCheck::new(
+                name,
+                CheckStatus::Fail,
+                "no `kubectl-proxyauth get-token` exec entry, run `context sync`",
+            ),
+            Some((namespace, cluster))
+                if namespace
+                    .as_ref()
+                    .is_some_and(|ns| *ns != url_info.namespace)
+                    || cluster
+                        .as_ref()
+                        .is_some_and(|name| *name != url_info.cluster_name) =>
+            {
+                DoctorCheck::new(
+                    name,
+                    CheckStatus::Fail,
+                    format!(
+                        "exec entry targets {}/{} but the cluster URL is {}/{}",
+                        namespace.unwrap_or_else(|| url_info.namespace.clone()),
+                        cluster.unwrap_or_else(|| url_info.cluster_name.clone()),
+                        url_info.namespace,
+                        url_info.cluster_name
+                    ),
+                )
+            }
+            Some(_) => {
+                let key = format!("{}/{}", url_info.namespace, url_info.cluster_name);
+                if server.clusters.contains_key(&key) {
+                    DoctorCheck::new(name, CheckStatus::Pass, format!("exec entry for {}", key))
+                } else {
+                    DoctorCheck::new(name, CheckStatus::Warn, format!("not logged in to {}", key))
+                }
+            }
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+/// Presence and expiry of a cluster token
+fn check_cluster_token(
+    server: &CliServerConfig,
+    key: &str,
+    cluster_config: &CliClusterConfig,
+    visible: Option<bool>,
+) -> DoctorCheck {
+    let name = format!("cluster {}", key);
+    let (ns, cluster) = key.split_once('/').unwrap_or(("", key));
+    if let Err(e) = server.get_cluster_token(ns.to_string(), cluster.to_string()) {
+        return DoctorCheck::new(name, CheckStatus::Fail, e.to_string());
+    }
+    if visible == Some(false) {
+        return DoctorCheck::new(
+            name,
+            CheckStatus::Warn,
+            "token stored but the cluster is no longer visible on the server",
+        );
+    }
+    match cluster_config.expires_at {
+        Some(expires_at) if expires_at <= Timestamp::now().as_second() => {
+            if server
+                .get_cluster_refresh_token(ns.to_string(), cluster.to_string())
+                .is_ok()
+            {
+                DoctorCheck::new(
+                    name,
+                    CheckStatus::Warn,
+                    "token expired, get-token will refresh it",
+                )
+            } else {
+                DoctorCheck::new(
+                    name,
+                    CheckStatus::Fail,
+                    format!("token expired, run `login {}`", cluster),
+                )
+            }
+        }
+        Some(expires_at) => DoctorCheck::new(
+            name,
+            CheckStatus::Pass,
+            format!(
+                "token valid until {}",
+                Timestamp::from_second(expires_at)
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|_| expires_at.to_string())
+            ),
+        ),
+        None => DoctorCheck::new(name, CheckStatus::Pass, "token present, no known expiry"),
+    }
+}
+
+impl CliCtx {
+    /// Check the configuration, the credential store, the server and the clusters
+    pub async fn handle_doctor(&mut self) {
+        let mut checks = Vec::new();
+
+        checks.push(match CliConfig::read_from_file(self.config_path.clone()) {
+            Ok(_) => DoctorCheck::new(
+                "config file",
+                CheckStatus::Pass,
+                self.config_path.to_string_lossy(),
+            ),
+            Err(e) => DoctorCheck::new("config file", CheckStatus::Fail, e.to_string()),
+        });
+
+        let store = CredentialStore::current();
+        let store_check = format!("credential store ({})", store.backend);
+        let probe = CredentialKey::server("proxyauthk8s-doctor");
+        checks.push(match store.backend {
+            // Read-only, the variables are checked with the tokens
+            CredentialBackend::Env => {
+                DoctorCheck::new(store_check, CheckStatus::Pass, "environment variables")
+            }
+            // Writing would create the file, reading is enough to check the passphrase
+            CredentialBackend::File if !store.file_path().exists() => DoctorCheck::new(
+                store_check,
+                CheckStatus::Pass,
+                format!("{} not created yet", store.file_path().display()),
+            ),
+            CredentialBackend::File => match store.get(&probe) {
+                Ok(_) => DoctorCheck::new(
+                    store_check,
+                    CheckStatus::Pass,
+                    format!("{} decrypted", store.file_path().display()),
+                ),
+                Err(e) => DoctorCheck::new(store_check, CheckStatus::Fail, e.to_string()),
+            },
+            _ => match store
+                .set(&probe, "probe")
+                .and_then(|_| store.get(&probe))
+                .and_then(|value| store.delete(&probe).map(|_| value))
+            {
+                Ok(Some(value)) if value == "probe" => {
+                    DoctorCheck::new(store_check, CheckStatus::Pass, "read and write work")
+                }
+                Ok(_) => DoctorCheck::new(
+                    store_check,
+                    CheckStatus::Fail,
+                    "a written secret couldn't be read back",
+                ),
+                Err(e) => DoctorCheck::new(store_check, CheckStatus::Fail, e.to_string()),
+            },
+        });
+
+        let server = match self
+            .config
+            .get_server_config_by_url(if self.server_url.is_empty() {
+                None
+            } else {
+                Some(self.server_url.clone())
+            }) {
+            Ok(server) => server.clone(),
+            Err(e) => {
+                checks.push(DoctorCheck::new(
+                    "server",
+                    CheckStatus::Fail,
+                    format!("{}, run `login --server-url <URL>`", e),
+                ));
+                self.print_doctor_report(checks);
+                return;
+            }
+        };
+
+        checks.push(
+            match health(&Configuration {
+                base_path: server.url.clone(),
+                ..Default::default()
+            })
+            .await
+            {
+                Ok(_) => DoctorCheck::new("server health", CheckStatus::Pass, server.url.clone()),
+                Err(e) => DoctorCheck::new(
+                    "server health",
+                    CheckStatus::Fail,
+                    format!("{}: {}", server.url, e),
+                ),
+            },
+        );
+
+        let visible = match server.get_server_token() {
+            Err(e) => {
+                checks.push(DoctorCheck::new(
+                    "server token",
+                    CheckStatus::Fail,
+                    e.to_string(),
+                ));
+                None
+            }
+            Ok(_) => match server.get_clusters_from_remote().await {
+                Ok(clusters) => {
+                    checks.push(DoctorCheck::new(
+                        "server token",
+                        CheckStatus::Pass,
+                        format!("{} visible cluster(s)", clusters.clusters.len()),
+                    ));
+                    Some(clusters.clusters)
+                }
+                Err(e) => {
+                    checks.push(DoctorCheck::new(
+                        "server token",
+                        CheckStatus::Fail,
+                        e.to_string(),
+                    ));
+                    None
+                }
+            },
+        };
+
+        let mut clusters: Vec<_> = server.clusters.iter().collect();
+        clusters.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, cluster_config) in clusters {
+            let is_visible = visible.as_ref().map(|visible| {
+                visible
+                    .iter()
+                    .any(|c| format!("{}/{}", c.namespace, c.name) == *key)
+            });
+            checks.push(check_cluster_token(
+                &server,
+                key,
+                cluster_config,
+                is_visible,
+            ));
+        }
+
+        checks.extend(check_kubeconfig(&self.kubeconfig, &server));
+        self.print_doctor_report(checks);
+    }
+
+    /// Print the report, exit with 1 if a check failed
+    fn print_doctor_report(&self, checks: Vec<DoctorCheck>) {
+        let failed = checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count();
+        let warned = checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Warn)
+            .count();
+        println!(
+            "{}",
+            DoctorReport::new(checks).to_output(self.format.clone())
+        );
+        if failed > 0 {
+            error!("{} check(s) failed, {} warning(s).", failed, warned);
+            // Scripts and CI rely on the exit code, the report is already written
+            let _ = std::io::stdout().flush();
+            std::process::exit(1);
+        } else if warned > 0 {
+            warn!("All checks passed with {} warning(s).", warned);
+        } else {
+            info!("All checks passed.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kubeconfig(server: &str) -> Kubeconfig {
+        let entry = |name: &str, cluster_url: &str, args: Option<&str>| {
+            let user = match args {
+                Some(args) => format!(
+                    "- name: {name}\n  user:\n    exec:\n      apiVersion: client.authentication.k8s.io/v1\n      command: kubectl-proxyauth\n      args: {args}\n"
+                ),
+                None => format!("- name: {name}\n  user:\n    token: static\n"),
+            };
+            (
+                format!("- name: {name}\n  cluster:\n    server: {cluster_url}\n"),
+                user,
+                format!("- name: {name}\n  context:\n    cluster: {name}\n    user: {name}\n"),
+            )
+        };
+        let entries = [
+            entry(
+                "match",
+                &format!("{server}/clusters/dev/c1"),
+                Some(r#"["get-token", "c1", "-n", "dev"]"#),
+            ),
+            entry(
+                "from-url",
+                &format!("{server}/clusters/dev/c1"),
+                Some(r#"["get-token"]"#),
+            ),
+            entry(
+                "wrong-namespace",
+                &format!("{server}/clusters/dev/c1"),
+                Some(r#"["get-token", "c1", "--namespace", "prod", "-s", "https://other"]"#),
+            ),
+            entry(
+                "not-logged-in",
+                &format!("{server}/clusters/prod/c2"),
+                Some(r#"["get-token", "c2", "-n", "prod"]"#),
+            ),
+            entry("no-exec", &format!("{server}/clusters/dev/c1"), None),
+            entry(
+                "other-server",
+                "https://other.example.com/clusters/dev/c1",
+                Some(r#"["get-token", "c1", "-n", "dev"]"#),
+            ),
+        ];
+        let section = |select: fn(&(String, String, String)) -> &String| {
+            entries.iter().map(select).cloned().collect::<String>()
+        };
+        serde_yaml::from_str(&format!(
+            "apiVersion: v1\nkind: Config\nclusters:\n{}users:\n{}contexts:\n{}",
+            section(|e| &e.0),
+            section(|e| &e.1),
+            section(|e| &e.2)
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_kubeconfig() {
+        for url in ["https://proxyauth.example.com", "https://localhost:5437"] {
+            let mut server = CliServerConfig::new(url.to_string());
+            server
+                .clusters
+                .insert("dev/c1".to_string(), CliClusterConfig::new());
+            let checks: Vec<(String, CheckStatus)> = check_kubeconfig(&kubeconfig(url), &server)
+                .into_iter()
+                .map(|check| (check.check, check.status))
+                .collect();
+            assert_eq!(
+                checks,
+                vec![
+                    ("kubeconfig match".to_string(), CheckStatus::Pass),
+                    ("kubeconfig from-url".to_string(), CheckStatus::Pass),
+                    ("kubeconfig wrong-namespace".to_string(), CheckStatus::Fail),
+                    ("kubeconfig not-logged-in".to_string(), CheckStatus::Warn),
+                    ("kubeconfig no-exec".to_string(), CheckStatus::Fail),
+                ],
+                "server {}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_exec_target() {
+        let exec = |command: &str, args: &[&str]| ExecConfig {
+            api_version: None,
+            command: Some(command.to_string()),
+            args: Some(args.iter().map(|arg| arg.to_string()).collect()),
+            env: None,
+            drop_env: None,
+            interactive_mode: None,
+            provide_cluster_info: false,
+            cluster: None,
+        };
+        assert_eq!(
+            exec_target(&exec(
+                "kubectl",
+                &["proxyauth", "get-token", "-n", "dev", "c1"]
+            )),
+            Some((Some("dev".to_string()), Some("c1".to_string())))
+        );
+        assert_eq!(
+            exec_target(&exec(
+                "kubectl-proxyauth",
+                &["get-token", "-f", "json", "c1"]
+            )),
+            Some((None, Some("c1".to_string())))
+        );
+        assert_eq!(exec_target(&exec("kubelogin", &["get-token", "c1"])), None);
+    }
+}
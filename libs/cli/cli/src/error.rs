@@ -33,6 +33,20 @@ pub enum ProxyAuthK8sError {
     RemoteServerError(String),
     #[error("ERR000014: Unauthenticated: {0}")]
     Unauthenticated(String),
+    #[error("ERR000015: SSO login failed: {0}")]
+    SsoLoginError(String),
+    #[error("ERR000016: Credential not found: {0}")]
+    CredentialNotFound(String),
+    #[error("ERR000017: Credential store unavailable: {0}")]
+    CredentialStoreUnavailable(String),
+    #[error("ERR000018: Credential store is read-only: {0}")]
+    CredentialStoreReadOnly(String),
+    #[error("ERR000019: Failed to decrypt the credential file: {0}")]
+    CredentialDecryptError(String),
+    #[error("ERR000020: Cluster token rejected: {0}")]
+    InvalidClusterToken(String),
+    #[error("ERR000021: Failed to write the credential file: {0}")]
+    CredentialWriteError(String),
 }
 
 impl From<CliConfigError> for ProxyAuthK8sError {
@@ -1,10 +1,13 @@
-use comfy_table::Table;
+use k8s_openapi::jiff::Timestamp;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
 use crate::{
+    cli_config::catalog::CatalogCache,
+    context::sync::proxy_cluster_url,
     ctx::{CliCtx, ContextFormat},
     error::ProxyAuthK8sError,
+    output::{to_output, OutputItem},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -14,28 +17,46 @@ pub struct GetClusterOutput {
     pub enabled: bool,
     pub is_reachable: Option<bool>,
     pub sso_enabled: bool,
+    /// URL of the cluster through the proxy
+    pub url: String,
+    /// A cluster token is stored for the cluster
+    pub logged_in: bool,
 }
 
-impl GetClusterOutput {
-    pub fn to_row(&self) -> Vec<String> {
-        vec![
+impl OutputItem for GetClusterOutput {
+    const KIND: &'static str = "cluster";
+
+    fn to_row_headers(wide: bool) -> Vec<String> {
+        let mut headers = vec![
+            "NAME".to_string(),
+            "NAMESPACE".to_string(),
+            "ENABLED".to_string(),
+            "REACHABLE".to_string(),
+            "SSO".to_string(),
+        ];
+        if wide {
+            headers.extend(["LOGGED IN".to_string(), "URL".to_string()]);
+        }
+        headers
+    }
+
+    fn to_row(&self, wide: bool) -> Vec<String> {
+        let mut row = vec![
             self.name.clone(),
             self.namespace.clone(),
             self.enabled.to_string(),
             self.is_reachable
                 .map_or_else(|| "unknown".to_string(), |value| value.to_string()),
             self.sso_enabled.to_string(),
-        ]
+        ];
+        if wide {
+            row.extend([self.logged_in.to_string(), self.url.clone()]);
+        }
+        row
     }
 
-    pub fn to_row_headers() -> Vec<String> {
-        vec![
-            "NAME".to_string(),
-            "NAMESPACE".to_string(),
-            "ENABLED".to_string(),
-            "REACHABLE".to_string(),
-            "SSO".to_string(),
-        ]
+    fn name(&self) -> String {
+        self.name.clone()
     }
 }
 
@@ -57,30 +78,8 @@ impl VecGetClusterOutput {
         }
     }
 
-    pub fn to_json(&self) -> String {
-        serde_json::to_string_pretty(self).unwrap_or_default()
-    }
-
-    pub fn to_yaml(&self) -> String {
-        serde_yaml::to_string(self).unwrap_or_default()
-    }
-
-    pub fn to_table(&self) -> String {
-        let mut table = Table::new();
-        table.load_preset(comfy_table::presets::NOTHING);
-        table.set_header(GetClusterOutput::to_row_headers());
-        for item in &self.items {
-            table.add_row(item.to_row());
-        }
-        table.to_string()
-    }
-
     pub fn to_output(&self, format: ContextFormat) -> String {
-        match format {
-            ContextFormat::Json => self.to_json(),
-            ContextFormat::Yaml => self.to_yaml(),
-            ContextFormat::Table => self.to_table(),
-        }
+        to_output(self, &self.items, &format)
     }
 }
 
@@ -130,6 +129,14 @@ impl CliCtx {
                         enabled: cluster.enabled,
                         is_reachable: cluster.is_reachable.flatten(),
                         sso_enabled: cluster.sso_enabled,
+                        url: proxy_cluster_url(
+                            &server_config.url,
+                            &cluster.namespace,
+                            &cluster.name,
+                        ),
+                        logged_in: server_config
+                            .clusters
+                            .contains_key(&format!("{}/{}", cluster.namespace, cluster.name)),
                     })
                     .collect();
 
@@ -139,7 +146,16 @@ impl CliCtx {
                         .then_with(|| a.name.cmp(&b.name))
                 });
 
-                let output = VecGetClusterOutput::new(outputs);
+                let mut output = VecGetClusterOutput::new(outputs);
+                // Tell the scripts reading json or yaml that the server wasn't reached
+                if let Some(fetched_at) = CatalogCache::current().stale_since(&server_config.url) {
+                    output.metadata = Some(serde_json::json!({
+                        "stale": true,
+                        "cachedAt": Timestamp::from_second(fetched_at)
+                            .map(|t| t.to_string())
+                            .unwrap_or_default(),
+                    }));
+                }
                 println!("{}", output.to_output(self.format.clone()));
             }
             Err(e) => {
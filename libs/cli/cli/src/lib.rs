@@ -1,20 +1,30 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
+use clap_complete::ArgValueCandidates;
 use tracing::{debug, warn};
 
 use crate::{
+    cli_config::cli_cluster_config::{ClientCredentialsConfig, StepUp},
     config::ConfigCommands,
     ctx::{CliCtx, ContextFormat},
 };
 
+pub mod access;
+pub mod cache;
 pub mod cli_config;
+pub mod completion;
 pub mod config;
 pub mod context;
 pub mod ctx;
+pub mod doctor;
 pub mod error;
 pub mod get;
 pub mod login;
+pub mod logout;
+pub mod output;
+pub mod pick;
+pub mod proxy;
 
 /// Kubectl ProxyAuth CLI
 #[derive(Parser, Debug, Clone)]
@@ -35,7 +45,8 @@ pub struct Cli {
         long,
         global = true,
         value_name = "NAMESPACE",
-        default_value = ""
+        default_value = "",
+        add = ArgValueCandidates::new(completion::namespace_candidates)
     )]
     pub namespace: String,
 
@@ -64,16 +75,25 @@ pub struct Cli {
     pub verbose: Option<u8>,
 
     /// ProxyAuthK8S server URL
-    #[arg(short, long, global = true, value_name = "URL", default_value = "")]
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_name = "URL",
+        default_value = "",
+        add = ArgValueCandidates::new(completion::server_candidates)
+    )]
     pub server_url: String,
 
     /// Output format
-    /// Specify the output format (e.g., json, yaml, table)
+    /// One of table, wide, json, yaml, name, jsonpath=<template> or custom-columns=<HEADER:.path,...>
     /// Default is `table`
     #[arg(
         short,
         global = true,
         long,
+        visible_short_alias = 'o',
+        visible_alias = "output",
         value_name = "FORMAT",
         default_value = "table"
     )]
@@ -89,29 +109,75 @@ pub enum Commands {
     /// If no flags are provided, lists all clusters
     Get {
         /// Get a specific cluster by name
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
         cluster_name: Option<String>,
     },
     /// Login either to ProxyAuthK8S server or to a specific cluster
     Login {
         /// Cluster name to login to
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
         cluster_name: Option<String>,
         /// Optional token for authentication
         #[arg(short, long, value_name = "TOKEN")]
         token: Option<String>,
+        /// Use the device authorization grant instead of the browser redirect
+        /// For sessions without browser, such as SSH sessions or jump hosts
+        /// Applies to the login to the server and to the SSO enabled clusters
+        #[arg(long)]
+        device: bool,
+        /// Get the cluster token with the OAuth client credentials grant, without any interactive step
+        /// For CI runners, get-token renews the token with the same client before it expires
+        #[arg(
+            long,
+            requires_all = ["cluster_name", "client_id", "client_secret_env"],
+            conflicts_with_all = ["token", "device"]
+        )]
+        client_credentials: bool,
+        /// Client ID registered on the OIDC provider of the cluster
+        #[arg(long, value_name = "ID", requires = "client_credentials")]
+        client_id: Option<String>,
+        /// Environment variable holding the client secret, the secret itself is never stored
+        #[arg(long, value_name = "VAR", requires = "client_credentials")]
+        client_secret_env: Option<String>,
+        /// Space separated ACR values to request to the OIDC provider of the cluster, for step-up authentication
+        /// Kept for the next browser logins to the cluster
+        #[arg(
+            long,
+            value_name = "VALUES",
+            requires = "cluster_name",
+            conflicts_with_all = ["token", "device", "client_credentials"]
+        )]
+        acr_values: Option<String>,
+        /// Maximum seconds since the last authentication on the OIDC provider, for step-up authentication
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            requires = "cluster_name",
+            conflicts_with_all = ["token", "device", "client_credentials"]
+        )]
+        max_age: Option<i64>,
     },
     /// Logout either from ProxyAuthK8S server or from a specific cluster
     Logout {
         /// Cluster name to logout from
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
         cluster_name: Option<String>,
     },
-    /// Clear cached authentication tokens
+    /// Clear the data cached from the tokens and the servers
+    /// The tokens are kept, use logout to remove them
     Cache {
-        /// Clear all cached tokens
+        /// Clear the recorded token expirations and the cached cluster lists
+        /// Use --namespace to only clear the expirations of the clusters of a namespace
+        #[arg(long)]
         clear: bool,
+        /// Clear the cache of all servers instead of the default one
+        #[arg(long)]
+        all_servers: bool,
     },
     /// Retrieve the current authentication token for a specific cluster
     GetToken {
         /// Cluster name to retrieve the token for
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
         cluster_name: Option<String>,
     },
     /// Handle Kubectl contexts
@@ -122,9 +188,67 @@ pub enum Commands {
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         list: bool,
         /// Set the current context to the specified cluster
-        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        #[arg(long, action = clap::ArgAction::SetTrue)]
         set: bool,
+        #[command(subcommand)]
+        command: Option<ContextCommands>,
+    },
+    /// Show the identity of the cluster tokens, from a SelfSubjectReview on each logged in cluster
+    Whoami {
+        /// Only review this cluster
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
+        cluster_name: Option<String>,
     },
+    /// Show what the cluster tokens allow, as a verb/resource matrix per cluster
+    /// Each cell is a SelfSubjectAccessReview made through the proxy
+    CanI {
+        /// Only review this cluster
+        #[arg(add = ArgValueCandidates::new(completion::cluster_candidates))]
+        cluster_name: Option<String>,
+        /// Verbs to review, comma separated
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "get,list,watch,create,update,delete"
+        )]
+        verbs: Vec<String>,
+        /// Resources to review, comma separated, `resource[.group][/subresource]`
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "pods,deployments.apps,services,configmaps,secrets"
+        )]
+        resources: Vec<String>,
+        /// Kubernetes namespace to review the access in
+        #[arg(long, value_name = "NAMESPACE", default_value = "default")]
+        target_namespace: String,
+        /// List the rules of a SelfSubjectRulesReview instead of the matrix
+        #[arg(long)]
+        rules: bool,
+    },
+    /// Pick a cluster in a fuzzy searchable list, log in if needed and switch the kubeconfig context
+    /// Use --namespace to only list the clusters of a namespace
+    Pick {
+        /// Initial search
+        query: Option<String>,
+    },
+    /// Serve a cluster on localhost, injecting the cluster token in each request
+    /// For the clients that can't run the exec credential plugin, prints the matching kubeconfig
+    Proxy {
+        /// Cluster to serve
+        #[arg(
+            long,
+            value_name = "CLUSTER",
+            add = ArgValueCandidates::new(completion::cluster_candidates)
+        )]
+        cluster: String,
+        /// Local port to listen on, bound to 127.0.0.1
+        #[arg(long, default_value_t = 8001)]
+        port: u16,
+    },
+    /// Check the configuration, the credential store, the server and the cluster tokens
+    /// Also checks the kubeconfig exec entries of the server clusters, exits with 1 if a check fails
+    Doctor,
     /// Configuration management
     Config {
         #[command(subcommand)]
@@ -132,6 +256,21 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum ContextCommands {
+    /// Write a kubeconfig context for each cluster visible on the server
+    /// The users get their token through `kubectl-proxyauth get-token`
+    /// Use --namespace to only sync the clusters of a namespace
+    Sync {
+        /// Show the changes without writing the kubeconfig
+        #[arg(long)]
+        dry_run: bool,
+        /// Keep the contexts of the clusters that are no longer visible
+        #[arg(long)]
+        no_prune: bool,
+    },
+}
+
 impl Cli {
     pub async fn run_cli(&mut self, mut ctx: CliCtx) {
         // Match and execute the appropriate command
@@ -143,20 +282,48 @@ impl Cli {
             Some(Commands::Login {
                 cluster_name,
                 token,
+                device,
+                client_credentials,
+                client_id,
+                client_secret_env,
+                acr_values,
+                max_age,
             }) => {
                 debug!(
                     "Logging in to cluster: {:?} with token: {:?}",
                     cluster_name, token
                 );
-                ctx.handle_login(cluster_name.clone(), token.clone()).await;
+                let client_credentials = client_id
+                    .clone()
+                    .zip(client_secret_env.clone())
+                    .filter(|_| *client_credentials)
+                    .map(|(client_id, client_secret_env)| ClientCredentialsConfig {
+                        client_id,
+                        client_secret_env,
+                    });
+                let step_up = (acr_values.is_some() || max_age.is_some()).then(|| StepUp {
+                    acr_values: acr_values.clone(),
+                    max_age: *max_age,
+                });
+                ctx.handle_login(
+                    cluster_name.clone(),
+                    token.clone(),
+                    *device,
+                    client_credentials,
+                    step_up,
+                )
+                .await;
             }
             Some(Commands::Logout { cluster_name }) => {
-                //ctx.handle_logout(cluster_name.clone());
                 debug!("Logging out from cluster: {:?}", cluster_name);
+                ctx.handle_logout(cluster_name.clone()).await;
             }
-            Some(Commands::Cache { clear }) => {
-                //ctx.handle_cache(*clear);
-                debug!("Handling cache clear: {}", clear);
+            Some(Commands::Cache { clear, all_servers }) => {
+                debug!(
+                    "Handling cache clear: {}, all servers: {}",
+                    clear, all_servers
+                );
+                ctx.handle_cache(*clear, *all_servers);
             }
             Some(Commands::GetToken { cluster_name }) => {
                 //ctx.handle_get_token(cluster_name.clone());
@@ -164,10 +331,21 @@ impl Cli {
                 debug!("Getting token for cluster: {:?}", cluster_name);
                 ctx.handle_get_token(cluster_name.clone()).await;
             }
+            Some(Commands::Context {
+                command: Some(ContextCommands::Sync { dry_run, no_prune }),
+                ..
+            }) => {
+                debug!(
+                    "Syncing contexts, dry run: {}, no prune: {}",
+                    dry_run, no_prune
+                );
+                ctx.handle_context_sync(*dry_run, *no_prune).await;
+            }
             Some(Commands::Context {
                 context_name,
                 list,
                 set,
+                command: None,
             }) => {
                 debug!(
                     "Handling context for cluster: {:?}, list: {}, set: {}",
@@ -175,6 +353,42 @@ impl Cli {
                 );
                 ctx.handle_context(context_name.clone(), *list, *set);
             }
+            Some(Commands::Whoami { cluster_name }) => {
+                debug!("Reviewing identity for cluster: {:?}", cluster_name);
+                ctx.handle_whoami(cluster_name.clone()).await;
+            }
+            Some(Commands::CanI {
+                cluster_name,
+                verbs,
+                resources,
+                target_namespace,
+                rules,
+            }) => {
+                debug!(
+                    "Reviewing access for cluster: {:?} in namespace {}",
+                    cluster_name, target_namespace
+                );
+                ctx.handle_can_i(
+                    cluster_name.clone(),
+                    verbs.clone(),
+                    resources.clone(),
+                    target_namespace.clone(),
+                    *rules,
+                )
+                .await;
+            }
+            Some(Commands::Pick { query }) => {
+                debug!("Picking a cluster with query: {:?}", query);
+                ctx.handle_pick(query.clone()).await;
+            }
+            Some(Commands::Proxy { cluster, port }) => {
+                debug!("Serving cluster {} on port {}", cluster, port);
+                ctx.handle_proxy(cluster.clone(), *port).await;
+            }
+            Some(Commands::Doctor) => {
+                debug!("Running diagnostics");
+                ctx.handle_doctor().await;
+            }
             Some(Commands::Config { command }) => {
                 //ctx.handle_config(server_url.clone(), namespace.clone(), *clear);
                 debug!("Handling config command: {:?}", command);
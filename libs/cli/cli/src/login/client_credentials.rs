@@ -0,0 +1,280 @@
+use client_api::{apis::auth_clusters_api::cluster_oidc_configuration, models::CallbackModel};
+use oauth2_reqwest::ReqwestClient;
+use openidconnect::{
+    core::{CoreClient, CoreProviderMetadata},
+    ClientId, ClientSecret, IssuerUrl, OAuth2TokenResponse, Scope,
+};
+use tracing::debug;
+
+use crate::{
+    cli_config::{cli_cluster_config::ClientCredentialsConfig, cli_server_config::CliServerConfig},
+    error::ProxyAuthK8sError,
+    login::oidc::{http_client, oidc_error},
+};
+
+/// Scopes of the client credentials grant, openid then the scopes of the cluster
+fn client_credentials_scopes(cluster_scopes: &str) -> Vec<Scope> {
+    let mut scopes = vec!["openid"];
+    for scope in cluster_scopes.split_whitespace() {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes
+        .into_iter()
+        .map(|scope| Scope::new(scope.to_string()))
+        .collect()
+}
+
+impl CliServerConfig {
+    /// Get a cluster token with the client credentials grant, for non interactive clients such as CI runners
+    /// The OIDC provider of the cluster is discovered through the server, the secret is read from the environment
+    pub async fn client_credentials_login_cluster(
+        &self,
+        ns: &str,
+        cluster: &str,
+        client: &ClientCredentialsConfig,
+    ) -> Result<CallbackModel, ProxyAuthK8sError> {
+        let client_secret = std::env::var(&client.client_secret_env).map_err(|_| {
+            ProxyAuthK8sError::SsoLoginError(format!(
+                "The client secret environment variable {} is not set",
+                client.client_secret_env
+            ))
+        })?;
+        let conf = cluster_oidc_configuration(&self.get_base_configuration()?, ns, cluster)
+            .await
+            .map_err(|e| oidc_error("Couldn't get the OIDC provider of the cluster", e))?;
+        debug!(
+            "Requesting a token of {}/{} from {} with client {}",
+            ns, cluster, conf.issuer_url, client.client_id
+        );
+        let http = ReqwestClient::from(http_client()?);
+        let metadata = CoreProviderMetadata::discover_async(
+            IssuerUrl::new(conf.issuer_url).map_err(|e| oidc_error("Invalid issuer", e))?,
+            &http,
+        )
+        .await
+        .map_err(|e| oidc_error("OIDC discovery failed", e))?;
+        // openid is needed by the OIDC validator of the proxy, which calls the userinfo endpoint with the token
+        let token = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(client.client_id.clone()),
+            Some(ClientSecret::new(client_secret)),
+        )
+        .exchange_client_credentials()
+        .map_err(|e| oidc_error("The OIDC provider has no token endpoint", e))?
+        .add_scopes(client_credentials_scopes(&conf.scopes))
+        .request_async(&http)
+        .await
+        .map_err(|e| oidc_error("The client credentials were rejected", e))?;
+
+        let mut credentials = CallbackModel::new(
+            token.access_token().secret().to_string(),
+            self.get_cluster_url_from_ns_name(Some(ns.to_string()), cluster.to_string())
+                .unwrap_or_default(),
+            String::new(),
+            String::new(),
+            client.client_id.clone(),
+        );
+        credentials.expires_in = Some(
+            token
+                .expires_in()
+                .map(|expires_in| expires_in.as_secs() as i64),
+        );
+        Ok(credentials)
+    }
+
+    /// Client credentials the cluster was logged in with, if any
+    pub fn get_cluster_client_credentials(
+        &self,
+        ns: &str,
+        cluster: &str,
+    ) -> Option<ClientCredentialsConfig> {
+        self.get_clusters_from_name_ns(cluster.to_string(), Some(ns.to_string()))
+            .and_then(|cluster_config| cluster_config.client_credentials.clone())
+    }
+
+    /// Remember the client credentials of the cluster, None for the other login methods
+    pub fn set_cluster_client_credentials(
+        &mut self,
+        ns: &str,
+        cluster: &str,
+        client: Option<ClientCredentialsConfig>,
+    ) {
+        if let Some(cluster_config) = self.clusters.get_mut(&format!("{}/{}", ns, cluster)) {
+            cluster_config.client_credentials = client;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::cli_config::credential_store::{CredentialBackend, CredentialKey, CredentialStore};
+
+    const SERVER_TOKEN: &str = "server-token";
+    const CLIENT_SECRET_ENV: &str = "PROXYAUTH_TEST_CC_CLIENT_SECRET";
+
+    /// Method, path, headers and body of a request read from the connection
+    async fn read_request(
+        stream: &mut TcpStream,
+    ) -> Option<(String, String, HashMap<String, String>, String)> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+            let read = stream.read(&mut chunk).await.ok()?;
+            if read == 0 {
+                return None;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        };
+        let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let length: usize = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or_default();
+        while buffer.len() < header_end + length {
+            let read = stream.read(&mut chunk).await.ok()?;
+            if read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        let body = String::from_utf8_lossy(&buffer[header_end..]).to_string();
+        Some((method, path, headers, body))
+    }
+
+    /// ProxyAuthK8S server and OIDC provider, the token endpoint only accepts the expected client credentials grant
+    async fn serve(listener: TcpListener, base_url: String) {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let base_url = base_url.clone();
+            tokio::spawn(async move {
+                let Some((method, path, headers, body)) = read_request(&mut stream).await else {
+                    return;
+                };
+                let (status, response) = match (method.as_str(), path.as_str()) {
+                    ("GET", "/clusters/dev/c1/auth/oidc")
+                        if headers.get("authorization").map(String::as_str)
+                            == Some(&format!("Bearer {}", SERVER_TOKEN)) =>
+                    {
+                        (
+                            "200 OK",
+                            serde_json::json!({
+                                "issuer_url": base_url,
+                                "client_id": "cluster-client",
+                                "scopes": "groups openid",
+                            }),
+                        )
+                    }
+                    ("GET", "/.well-known/openid-configuration") => (
+                        "200 OK",
+                        serde_json::json!({
+                            "issuer": base_url,
+                            "authorization_endpoint": format!("{}/authorize", base_url),
+                            "token_endpoint": format!("{}/token", base_url),
+                            "jwks_uri": format!("{}/jwks", base_url),
+                            "response_types_supported": ["code"],
+                            "subject_types_supported": ["public"],
+                            "id_token_signing_alg_values_supported": ["RS256"],
+                        }),
+                    ),
+                    ("GET", "/jwks") => ("200 OK", serde_json::json!({ "keys": [] })),
+                    ("POST", "/token") => {
+                        let basic = format!("Basic {}", STANDARD.encode("ci-runner:ci-secret"));
+                        let form: HashMap<String, String> = body
+                            .split('&')
+                            .filter_map(|pair| pair.split_once('='))
+                            .map(|(key, value)| {
+                                (key.to_string(), value.replace("+", " ").replace("%20", " "))
+                            })
+                            .collect();
+                        if headers.get("authorization") == Some(&basic)
+                            && form.get("grant_type").map(String::as_str)
+                                == Some("client_credentials")
+                            && form.get("scope").map(String::as_str) == Some("openid groups")
+                        {
+                            (
+                                "200 OK",
+                                serde_json::json!({
+                                    "access_token": "ci-access-token",
+                                    "token_type": "Bearer",
+                                    "expires_in": 300,
+                                }),
+                            )
+                        } else {
+                            (
+                                "400 Bad Request",
+                                serde_json::json!({ "error": "invalid_request" }),
+                            )
+                        }
+                    }
+                    _ => ("404 Not Found", serde_json::json!({})),
+                };
+                let body = response.to_string();
+                let answer = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(answer.as_bytes()).await;
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_login_cluster() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, base_url.clone()));
+
+        let server_config = CliServerConfig::new(base_url.clone());
+        CredentialStore::init(CredentialBackend::Env, Path::new("proxyauth_config.yaml"));
+        std::env::set_var(CredentialKey::server(&base_url).env_var(), SERVER_TOKEN);
+        std::env::set_var(CLIENT_SECRET_ENV, "ci-secret");
+        let client = ClientCredentialsConfig {
+            client_id: "ci-runner".to_string(),
+            client_secret_env: CLIENT_SECRET_ENV.to_string(),
+        };
+
+        let credentials = server_config
+            .client_credentials_login_cluster("dev", "c1", &client)
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_token, "ci-access-token");
+        assert_eq!(credentials.expires_in, Some(Some(300)));
+        assert_eq!(credentials.subject, "ci-runner");
+        assert!(credentials.refresh_token.is_empty());
+
+        let wrong_client = ClientCredentialsConfig {
+            client_id: "other".to_string(),
+            ..client
+        };
+        assert!(server_config
+            .client_credentials_login_cluster("dev", "c1", &wrong_client)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use client_api::{
+    apis::{
+        auth_clusters_api::{device_authorization, device_token, DeviceTokenError},
+        Error,
+    },
+    models::{CallbackModel, DeviceTokenBody, DeviceTokenErrorModel},
+};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+use crate::{cli_config::cli_server_config::CliServerConfig, error::ProxyAuthK8sError};
+
+/// Lowest polling interval, whatever the server answers
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+/// Added to the interval on slow_down when the server doesn't raise it (RFC 8628 section 3.5)
+const SLOW_DOWN_INCREMENT: Duration = Duration::from_secs(5);
+
+fn seconds(interval: i64) -> Duration {
+    Duration::from_secs(interval.max(0) as u64).max(MIN_INTERVAL)
+}
+
+/// Interval to wait before the next poll, None if the device login ended
+fn next_interval(current: Duration, pending: &DeviceTokenErrorModel) -> Option<Duration> {
+    match pending.error.as_str() {
+        "authorization_pending" => Some(seconds(pending.interval)),
+        "slow_down" => Some(seconds(pending.interval).max(current + SLOW_DOWN_INCREMENT)),
+        _ => None,
+    }
+}
+
+impl CliServerConfig {
+    /// Log in to an SSO enabled cluster with the device authorization grant (RFC 8628)
+    /// The user approves the login from any browser, the CLI polls the server until it is done
+    pub async fn device_login_cluster(
+        &self,
+        ns: &str,
+        cluster: &str,
+    ) -> Result<CallbackModel, ProxyAuthK8sError> {
+        let configuration = self.get_base_configuration()?;
+        let authorization = device_authorization(&configuration, ns, cluster)
+            .await
+            .map_err(|e| {
+                ProxyAuthK8sError::SsoLoginError(format!("Couldn't start the device login: {}", e))
+            })?;
+
+        match authorization.verification_uri_complete.flatten() {
+            Some(uri) => info!(
+                "To log in to {}/{}, visit {} and check the code {}",
+                ns, cluster, uri, authorization.user_code
+            ),
+            None => info!(
+                "To log in to {}/{}, visit {} and enter the code {}",
+                ns, cluster, authorization.verification_uri, authorization.user_code
+            ),
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(authorization.expires_in.max(0) as u64);
+        let mut interval = seconds(authorization.interval);
+        let body = DeviceTokenBody::new(authorization.device_id);
+        loop {
+            sleep(interval).await;
+            if Instant::now() >= deadline {
+                return Err(ProxyAuthK8sError::SsoLoginError(
+                    "The device code expired before the login was approved".to_string(),
+                ));
+            }
+            match device_token(&configuration, ns, cluster, body.clone()).await {
+                Ok(credentials) => return Ok(credentials),
+                Err(Error::ResponseError(content)) => match content.entity {
+                    Some(DeviceTokenError::Status400(pending)) => {
+                        match next_interval(interval, &pending) {
+                            Some(next) => {
+                                debug!("Device login still pending: {}", pending.error);
+                                interval = next;
+                            }
+                            None => {
+                                return Err(ProxyAuthK8sError::SsoLoginError(format!(
+                                    "The device login ended: {}",
+                                    pending.error
+                                )));
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(ProxyAuthK8sError::SsoLoginError(format!(
+                            "Couldn't complete the device login, status {}: {}",
+                            content.status, content.content
+                        )));
+                    }
+                },
+                Err(e) => {
+                    return Err(ProxyAuthK8sError::SsoLoginError(format!(
+                        "Couldn't complete the device login: {}",
+                        e
+                    )));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(error: &str, interval: i64) -> DeviceTokenErrorModel {
+        DeviceTokenErrorModel::new(error.to_string(), interval)
+    }
+
+    #[test]
+    fn test_next_interval() {
+        let current = Duration::from_secs(5);
+        assert_eq!(
+            next_interval(current, &pending("authorization_pending", 5)),
+            Some(Duration::from_secs(5))
+        );
+        // The server raised the interval
+        assert_eq!(
+            next_interval(current, &pending("slow_down", 10)),
+            Some(Duration::from_secs(10))
+        );
+        // The server kept the interval, slow down anyway
+        assert_eq!(
+            next_interval(current, &pending("slow_down", 5)),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            next_interval(current, &pending("authorization_pending", 0)),
+            Some(MIN_INTERVAL)
+        );
+        assert_eq!(
+            next_interval(current, &pending("authorization_pending", -1)),
+            Some(MIN_INTERVAL)
+        );
+        assert_eq!(next_interval(current, &pending("access_denied", 5)), None);
+        assert_eq!(next_interval(current, &pending("expired_token", 5)), None);
+    }
+}
@@ -1,5 +1,53 @@
-use crate::ctx::CliCtx;
-use tracing::{debug, error};
+use crate::{
+    cli_config::cli_server_config::CliServerConfig, ctx::CliCtx, error::ProxyAuthK8sError,
+};
+use client_api::{apis::auth_clusters_api::refresh_login, models::RefreshBody};
+use k8s_openapi::jiff::Timestamp;
+use tracing::{debug, error, info, warn};
+
+/// Seconds before the expiration from which a cluster token is refreshed
+pub(crate) const REFRESH_MARGIN: i64 = 60;
+
+impl CliServerConfig {
+    /// Whether the cluster token expires within the refresh margin
+    pub fn cluster_token_expiring(&self, ns: &str, cluster: &str, now: i64) -> bool {
+        self.get_cluster_expiry(ns, cluster)
+            .is_some_and(|expires_at| expires_at - REFRESH_MARGIN <= now)
+    }
+
+    /// Refresh the token of an SSO enabled cluster through the server
+    /// A cluster logged in with client credentials requests a new token with them instead
+    pub async fn refresh_cluster_token(
+        &mut self,
+        ns: &str,
+        cluster: &str,
+    ) -> Result<(), ProxyAuthK8sError> {
+        if let Some(client) = self.get_cluster_client_credentials(ns, cluster) {
+            let credentials = self
+                .client_credentials_login_cluster(ns, cluster, &client)
+                .await?;
+            self.set_cluster_credentials(ns.to_string(), cluster.to_string(), &credentials)?;
+            return Ok(());
+        }
+        let refresh_token = self.get_cluster_refresh_token(ns.to_string(), cluster.to_string())?;
+        let credentials = refresh_login(
+            &self.get_base_configuration()?,
+            ns,
+            cluster,
+            RefreshBody::new(refresh_token),
+        )
+        .await
+        .map_err(|e| {
+            debug!("Cluster token refresh failed: {:?}", e);
+            ProxyAuthK8sError::Unauthenticated(format!(
+                "Couldn't refresh the token of cluster {}/{}",
+                ns, cluster
+            ))
+        })?;
+        self.set_cluster_credentials(ns.to_string(), cluster.to_string(), &credentials)
+    }
+}
+
 //https://kubernetes.io/docs/reference/access-authn-authz/authentication/#input-and-output-formats
 impl CliCtx {
     pub async fn handle_get_token(&mut self, cluster_name: Option<String>) {
@@ -120,6 +168,91 @@ impl CliCtx {
             namespace, cluster_name, server_config.url
         );
 
+        // Refresh the token before kubectl gets an expired one
+        let mut server_config = server_config.clone();
+        let now = Timestamp::now().as_second();
+        // kubectl passes the terminal to the plugin only when it is interactive
+        let interactive = exec_info
+            .get("spec")
+            .and_then(|s| s.get("interactive"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        // A browser login would replace the client credentials of a CI runner
+        let client_credentials = server_config
+            .get_cluster_client_credentials(&namespace, &cluster_name)
+            .is_some();
+        let step_up = server_config.get_cluster_step_up(&namespace, &cluster_name);
+        // The cluster refused the current token asking for a step-up authentication it doesn't carry,
+        // without such a challenge the token is refreshed normally
+        let step_up_missing = !client_credentials
+            && server_config.cluster_step_up_missing(&namespace, &cluster_name, now);
+        if step_up_missing && !interactive {
+            warn!(
+                "Cluster '{}/{}' asks for a step-up authentication, please login using 'login {}'.",
+                namespace, cluster_name, cluster_name
+            );
+        }
+        let mut relogin = step_up_missing && interactive;
+        let mut renewed = false;
+        if relogin {
+            warn!(
+                "Cluster '{}/{}' asks for a step-up authentication, starting an interactive login.",
+                namespace, cluster_name
+            );
+        } else if server_config.cluster_token_expiring(&namespace, &cluster_name, now) {
+            debug!(
+                "Token of cluster '{}/{}' is about to expire, refreshing it.",
+                namespace, cluster_name
+            );
+            match server_config
+                .refresh_cluster_token(&namespace, &cluster_name)
+                .await
+            {
+                Ok(()) => renewed = true,
+                Err(e) if !interactive || client_credentials => {
+                    error!("{}. Please login using 'login {}'.", e, cluster_name);
+                    return;
+                }
+                Err(e) => {
+                    warn!("{}, starting an interactive login.", e);
+                    relogin = true;
+                }
+            }
+        }
+        if relogin {
+            let credentials = match server_config
+                .sso_login_cluster(&namespace, &cluster_name, step_up.as_ref())
+                .await
+            {
+                Ok(credentials) => credentials,
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            };
+            if let Err(e) = server_config.set_cluster_credentials(
+                namespace.clone(),
+                cluster_name.clone(),
+                &credentials,
+            ) {
+                error!("Failed to save the cluster tokens: {}", e);
+                return;
+            }
+            server_config.set_cluster_step_up(&namespace, &cluster_name, step_up);
+            renewed = true;
+        }
+        if renewed {
+            info!("Token of cluster '{}/{}' renewed.", namespace, cluster_name);
+            self.config.servers.insert(
+                CliServerConfig::url_to_name_from_string(server_config.url.clone()),
+                server_config.clone(),
+            );
+            if let Err(e) = self.config.write_to_file(self.config_path.clone()) {
+                error!("Failed to update config file: {}", e);
+            }
+        }
+        let expires_at = server_config.get_cluster_expiry(&namespace, &cluster_name);
+
         // Retrieve the cluster token from keyring
         let token = match server_config.get_cluster_token(namespace.clone(), cluster_name.clone()) {
             Ok(token) => token,
@@ -144,12 +277,15 @@ impl CliCtx {
         };
 
         // Output ExecCredential JSON to stdout (kubectl exec plugin protocol)
+        let mut status = serde_json::json!({ "token": token });
+        // kubectl calls the plugin again once the token expires instead of caching it
+        if let Some(expiration) = expires_at.and_then(|exp| Timestamp::from_second(exp).ok()) {
+            status["expirationTimestamp"] = serde_json::Value::String(expiration.to_string());
+        }
         let exec_credential = serde_json::json!({
             "apiVersion": api_version,
             "kind": "ExecCredential",
-            "status": {
-                "token": token
-            }
+            "status": status
         });
 
         match serde_json::to_string(&exec_credential) {
@@ -160,3 +296,24 @@ impl CliCtx {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cli_config::cli_cluster_config::CliClusterConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_cluster_token_expiring() {
+        let mut server_config = CliServerConfig::new("https://proxyauth.example.com".to_string());
+        let mut cluster = CliClusterConfig::new();
+        cluster.expires_at = Some(1_000);
+        server_config.clusters.insert("dev/c1".to_string(), cluster);
+
+        assert!(!server_config.cluster_token_expiring("dev", "c1", 1_000 - REFRESH_MARGIN - 1));
+        // Refreshed from the start of the margin, before the token is rejected
+        assert!(server_config.cluster_token_expiring("dev", "c1", 1_000 - REFRESH_MARGIN));
+        assert!(server_config.cluster_token_expiring("dev", "c1", 1_000));
+        assert!(server_config.cluster_token_expiring("dev", "c1", 2_000));
+    }
+}
@@ -1,11 +1,22 @@
 use client_api::apis::{api_clusters_api::get_all_visible_cluster, configuration::Configuration};
 use std::io::{self, Write};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+pub mod client_credentials;
+pub mod device;
 pub mod get_token;
+pub mod oidc;
+pub mod sso;
+pub mod validate;
 
 use crate::{
-    cli_config::cli_server_config::CliServerConfig, ctx::CliCtx, error::ProxyAuthK8sError,
+    cli_config::{
+        cli_cluster_config::{ClientCredentialsConfig, StepUp},
+        cli_server_config::CliServerConfig,
+    },
+    ctx::CliCtx,
+    error::ProxyAuthK8sError,
+    login::oidc::oidc_login_server,
 };
 
 impl CliCtx {
@@ -26,20 +37,35 @@ impl CliCtx {
         }
     }
 
-    pub async fn handle_login(&mut self, cluster_name: Option<String>, token: Option<String>) {
+    pub async fn handle_login(
+        &mut self,
+        cluster_name: Option<String>,
+        token: Option<String>,
+        device: bool,
+        client_credentials: Option<ClientCredentialsConfig>,
+        step_up: Option<StepUp>,
+    ) {
         // if server_url is not provided and none exist in config, return error
         if self.server_url.is_empty() && self.config.default_server_name.is_empty() {
             error!("Error: No ProxyAuthK8S server URL provided and no existing configuration found. Please provide a server URL using the --server-url option or login to server first.");
             return;
         }
         if let Some(cluster) = cluster_name {
-            self.handle_login_clusters(cluster, token).await;
+            self.handle_login_clusters(cluster, token, device, client_credentials, step_up)
+                .await;
         } else {
-            self.handle_login_servers(token).await;
+            self.handle_login_servers(token, device).await;
         }
     }
 
-    pub async fn handle_login_clusters(&mut self, cluster: String, token: Option<String>) {
+    pub async fn handle_login_clusters(
+        &mut self,
+        cluster: String,
+        token: Option<String>,
+        device: bool,
+        client_credentials: Option<ClientCredentialsConfig>,
+        step_up: Option<StepUp>,
+    ) {
         debug!("Logging in to cluster: {}", cluster);
         // if server url is provided but not in config, return error
         let server_config = match self.config.get_server_config_by_url(
@@ -71,7 +97,6 @@ impl CliCtx {
                 match e {
                     ProxyAuthK8sError::Unauthenticated(_) => {
                         error!("Authentication failed: Invalid server token, please re-login to the server.");
-                        // TODO: Trigger re-login flow when implemented
                     }
                     ProxyAuthK8sError::RemoteServerError(_) => {
                         error!("Server error occurred while retrieving clusters.");
@@ -97,6 +122,12 @@ impl CliCtx {
             }
         };
 
+        if let Some(client) = client_credentials {
+            self.client_credentials_login_cluster(&server_name, namespace, cluster, client)
+                .await;
+            return;
+        }
+
         let token = match token {
             Some(token) => Some(token),
             None if is_sso_enabled => None,
@@ -105,57 +136,188 @@ impl CliCtx {
 
         if let Some(tok) = token {
             info!("Using token for cluster authentication.");
-            //TODO: Validate token against cluster here
-            // Validation has to be done by calling the /api?timeout=32s used by kubectl to ensure token is valid for the cluster
+            let identity = match server_config
+                .validate_cluster_token(&namespace, &cluster, &tok)
+                .await
+            {
+                Ok(identity) => identity,
+                Err(e) => {
+                    error!("Token not saved. {}", e);
+                    return;
+                }
+            };
 
             // Insert credentials into config
-            let _ = &self
-                .config
-                .servers
-                .get_mut(&server_name)
-                .unwrap()
-                .set_cluster_token(namespace, cluster.clone(), tok.clone());
+            let server_config = self.config.servers.get_mut(&server_name).unwrap();
+            let _ =
+                server_config.set_cluster_token(namespace.clone(), cluster.clone(), tok.clone());
+            server_config.set_cluster_client_credentials(&namespace, &cluster, None);
             match self.config.write_to_file(self.config_path.clone()) {
                 Ok(_) => info!("Config file updated successfully."),
                 Err(e) => error!("Failed to update config file: {}", e),
             };
-            info!("Login to cluster {} successful.", cluster);
+            match identity {
+                Some((username, groups)) if groups.is_empty() => {
+                    info!("Login to cluster {} successful as {}.", cluster, username)
+                }
+                Some((username, groups)) => info!(
+                    "Login to cluster {} successful as {} ({}).",
+                    cluster,
+                    username,
+                    groups.join(", ")
+                ),
+                None => info!("Login to cluster {} successful.", cluster),
+            }
         } else if is_sso_enabled {
+            // The requirement given on the command line replaces the one recorded for the cluster
+            let step_up =
+                step_up.or_else(|| server_config.get_cluster_step_up(&namespace, &cluster));
+            let login = if device {
+                if step_up.is_some() {
+                    warn!(
+                        "The device login can't request the step-up authentication of the cluster."
+                    );
+                }
+                info!(
+                    "Cluster '{}' has SSO enabled, starting the device login.",
+                    cluster
+                );
+                server_config
+                    .device_login_cluster(&namespace, &cluster)
+                    .await
+            } else {
+                info!(
+                    "Cluster '{}' has SSO enabled, starting the browser login.",
+                    cluster
+                );
+                server_config
+                    .sso_login_cluster(&namespace, &cluster, step_up.as_ref())
+                    .await
+            };
+            let credentials = match login {
+                Ok(credentials) => credentials,
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            };
+            let server_config = self.config.servers.get_mut(&server_name).unwrap();
+            if let Err(e) = server_config.set_cluster_credentials(
+                namespace.clone(),
+                cluster.clone(),
+                &credentials,
+            ) {
+                error!("Failed to save the cluster tokens: {}", e);
+                return;
+            }
+            server_config.set_cluster_client_credentials(&namespace, &cluster, None);
+            server_config.set_cluster_step_up(&namespace, &cluster, step_up);
+            match self.config.write_to_file(self.config_path.clone()) {
+                Ok(_) => info!("Config file updated successfully."),
+                Err(e) => error!("Failed to update config file: {}", e),
+            };
             info!(
-                "Cluster '{}' has SSO enabled. Token prompt is skipped for SSO clusters.",
-                cluster
-            );
-            info!(
-                "Interactive SSO login is not implemented yet. Please use --token if your flow requires a cluster token."
+                "Login to cluster {} successful as {}.",
+                cluster, credentials.subject
             );
         } else {
             error!("No token provided. Cluster login requires a token.");
         }
     }
 
-    pub async fn handle_login_servers(&mut self, token: Option<String>) {
+    /// Log in to the cluster with the client credentials grant, the client is kept to renew the token
+    async fn client_credentials_login_cluster(
+        &mut self,
+        server_name: &str,
+        namespace: String,
+        cluster: String,
+        client: ClientCredentialsConfig,
+    ) {
+        info!(
+            "Requesting a token for cluster '{}' with client {}.",
+            cluster, client.client_id
+        );
+        let server_config = self.config.servers.get_mut(server_name).unwrap();
+        let credentials = match server_config
+            .client_credentials_login_cluster(&namespace, &cluster, &client)
+            .await
+        {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+        let identity = match server_config
+            .validate_cluster_token(&namespace, &cluster, &credentials.access_token)
+            .await
+        {
+            Ok(identity) => identity,
+            Err(e) => {
+                error!("Token not saved. {}", e);
+                return;
+            }
+        };
+        if let Err(e) =
+            server_config.set_cluster_credentials(namespace.clone(), cluster.clone(), &credentials)
+        {
+            error!("Failed to save the cluster tokens: {}", e);
+            return;
+        }
+        server_config.set_cluster_client_credentials(&namespace, &cluster, Some(client));
+        match self.config.write_to_file(self.config_path.clone()) {
+            Ok(_) => info!("Config file updated successfully."),
+            Err(e) => error!("Failed to update config file: {}", e),
+        };
+        match identity {
+            Some((username, _)) => {
+                info!("Login to cluster {} successful as {}.", cluster, username)
+            }
+            None => info!(
+                "Login to cluster {} successful as {}.",
+                cluster, credentials.subject
+            ),
+        }
+    }
+
+    pub async fn handle_login_servers(&mut self, token: Option<String>, device: bool) {
         debug!("Logging in to ProxyAuthK8S server.");
-        let token = token
-            .or_else(|| Self::prompt_for_token("Server token not provided. Enter server token: "));
+        let server_url = if !self.server_url.is_empty() {
+            self.server_url.clone()
+        } else {
+            let def_server = self
+                .config
+                .servers
+                .get(&self.config.default_server_name)
+                .unwrap();
+            def_server.url.clone()
+        };
+        let (token, refresh_token) = match token {
+            Some(token) => (Some(token), None),
+            None => match oidc_login_server(&server_url, device).await {
+                Ok(credentials) => (Some(credentials.access_token), credentials.refresh_token),
+                Err(e) => {
+                    warn!("OIDC login to the server failed: {}", e);
+                    (
+                        Self::prompt_for_token("Server token not provided. Enter server token: "),
+                        None,
+                    )
+                }
+            },
+        };
 
         if let Some(tok) = token {
             info!("Using token for server authentication.");
             // Use the token for authentication
             // Try to get cluster info from server using the token
-            let output = get_all_visible_cluster(&Configuration {
-                bearer_access_token: Some(tok.clone()),
-                base_path: if !self.server_url.is_empty() {
-                    self.server_url.clone()
-                } else {
-                    let def_server = self
-                        .config
-                        .servers
-                        .get(&self.config.default_server_name)
-                        .unwrap();
-                    def_server.url.clone()
+            let output = get_all_visible_cluster(
+                &Configuration {
+                    bearer_access_token: Some(tok.clone()),
+                    base_path: server_url.clone(),
+                    ..Default::default()
                 },
-                ..Default::default()
-            })
+                None,
+            )
             .await;
 
             match output {
@@ -193,8 +355,15 @@ impl CliCtx {
                         Err(e) => error!("Failed to update config file: {}", e),
                     };
                     match server_config_clone.set_server_token(tok.clone()) {
-                        Ok(_) => info!("Token saved to keyring successfully."),
-                        Err(e) => error!("Failed to save token to keyring: {}", e),
+                        Ok(_) => info!("Token saved to the credential store successfully."),
+                        Err(e) => error!("Failed to save token to the credential store: {}", e),
+                    }
+                    // A token given by hand replaces the OIDC session, its refresh token is dropped
+                    if let Err(e) = server_config_clone.set_server_refresh_token(refresh_token) {
+                        error!(
+                            "Failed to save refresh token to the credential store: {}",
+                            e
+                        );
                     }
                 }
                 Err(e) => {
@@ -0,0 +1,321 @@
+use client_api::{
+    apis::{auth_server_api::cli_oidc_configuration, configuration::Configuration},
+    models::CliOidcConfiguration,
+};
+use oauth2_reqwest::ReqwestClient;
+use openidconnect::{
+    core::{
+        CoreAuthenticationFlow, CoreClient, CoreDeviceAuthorizationResponse, CoreProviderMetadata,
+        CoreTokenResponse,
+    },
+    AuthorizationCode, ClientId, CsrfToken, DeviceAuthorizationUrl, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, IssuerUrl, Nonce, OAuth2TokenResponse, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, Scope,
+};
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+use crate::{
+    cli_config::cli_server_config::CliServerConfig,
+    error::ProxyAuthK8sError,
+    login::sso::{wait_for_callback, SSO_LOGIN_TIMEOUT},
+};
+
+type ServerOidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// Tokens obtained by the OIDC login to the server
+pub struct ServerCredentials {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+}
+
+impl From<CoreTokenResponse> for ServerCredentials {
+    fn from(token: CoreTokenResponse) -> Self {
+        ServerCredentials {
+            access_token: token.access_token().secret().to_string(),
+            refresh_token: token
+                .refresh_token()
+                .map(|refresh_token| refresh_token.secret().to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DeviceProviderMetadata {
+    device_authorization_endpoint: Option<String>,
+}
+
+pub(crate) fn oidc_error(message: &str, err: impl std::fmt::Display) -> ProxyAuthK8sError {
+    ProxyAuthK8sError::SsoLoginError(format!("{}: {}", message, err))
+}
+
+pub(crate) fn http_client() -> Result<reqwest::Client, ProxyAuthK8sError> {
+    // The OIDC provider must not be able to redirect the token requests
+    reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| oidc_error("Couldn't build the HTTP client", e))
+}
+
+/// OIDC configuration published by the server and the matching client
+async fn server_oidc_client(
+    server_url: &str,
+) -> Result<(CliOidcConfiguration, ServerOidcClient), ProxyAuthK8sError> {
+    let conf = cli_oidc_configuration(&Configuration {
+        base_path: server_url.to_string(),
+        ..Default::default()
+    })
+    .await
+    .map_err(|e| oidc_error("Couldn't get the OIDC configuration of the server", e))?;
+    let metadata = CoreProviderMetadata::discover_async(
+        IssuerUrl::new(conf.issuer_url.clone()).map_err(|e| oidc_error("Invalid issuer", e))?,
+        &ReqwestClient::from(http_client()?),
+    )
+    .await
+    .map_err(|e| oidc_error("OIDC discovery failed", e))?;
+    let client =
+        CoreClient::from_provider_metadata(metadata, ClientId::new(conf.client_id.clone()), None);
+    Ok((conf, client))
+}
+
+fn scopes(conf: &CliOidcConfiguration) -> Vec<Scope> {
+    conf.scopes
+        .split_whitespace()
+        .map(|scope| Scope::new(scope.to_string()))
+        .collect()
+}
+
+/// Authorization URL with a random state and a S256 PKCE challenge
+/// The state is checked on the callback, the verifier is sent with the code
+fn authorization_url(
+    conf: &CliOidcConfiguration,
+    client: &ServerOidcClient,
+) -> (Url, CsrfToken, PkceCodeVerifier) {
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (auth_url, csrf_token, _) = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .set_pkce_challenge(pkce_challenge)
+        .add_scopes(scopes(conf))
+        .url();
+    (auth_url, csrf_token, pkce_verifier)
+}
+
+/// Authorization code flow with PKCE, the OIDC provider redirects the browser to a loopback listener
+async fn loopback_login(
+    conf: &CliOidcConfiguration,
+    client: ServerOidcClient,
+) -> Result<CoreTokenResponse, ProxyAuthK8sError> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|e| oidc_error("Couldn't start the callback listener", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| oidc_error("Couldn't start the callback listener", e))?
+        .port();
+    let redirect_url = format!("http://localhost:{}/auth/callback/server", port);
+    let client = client.set_redirect_uri(
+        RedirectUrl::new(redirect_url).map_err(|e| oidc_error("Invalid redirect URL", e))?,
+    );
+    let (auth_url, csrf_token, pkce_verifier) = authorization_url(conf, &client);
+
+    info!(
+        "Opening the browser to log in to the server. If it doesn't open, visit: {}",
+        auth_url
+    );
+    if let Err(e) = open::that(auth_url.as_str()) {
+        warn!("Couldn't open the browser: {}", e);
+    }
+    let authorization = tokio::time::timeout(
+        SSO_LOGIN_TIMEOUT,
+        wait_for_callback(&listener, csrf_token.secret()),
+    )
+    .await
+    .map_err(|_| {
+        ProxyAuthK8sError::SsoLoginError(
+            "Timed out waiting for the login in the browser".to_string(),
+        )
+    })??;
+    client
+        .exchange_code(AuthorizationCode::new(authorization.code))
+        .map_err(|e| oidc_error("The OIDC provider has no token endpoint", e))?
+        .set_pkce_verifier(pkce_verifier)
+        .request_async(&ReqwestClient::from(http_client()?))
+        .await
+        .map_err(|e| oidc_error("Couldn't exchange the authorization code", e))
+}
+
+/// Device authorization grant, for sessions without browser
+async fn device_login(
+    conf: &CliOidcConfiguration,
+    client: ServerOidcClient,
+) -> Result<CoreTokenResponse, ProxyAuthK8sError> {
+    let discovery_url = format!(
+        "{}/.well-known/openid-configuration",
+        conf.issuer_url.trim_end_matches('/')
+    );
+    let metadata: DeviceProviderMetadata = http_client()?
+        .get(&discovery_url)
+        .send()
+        .await
+        .map_err(|e| oidc_error("OIDC discovery failed", e))?
+        .json()
+        .await
+        .map_err(|e| oidc_error("OIDC discovery failed", e))?;
+    let device_endpoint =
+        metadata
+            .device_authorization_endpoint
+            .ok_or(ProxyAuthK8sError::SsoLoginError(
+                "The OIDC provider doesn't support the device authorization grant".to_string(),
+            ))?;
+    let client = client.set_device_authorization_url(
+        DeviceAuthorizationUrl::new(device_endpoint)
+            .map_err(|e| oidc_error("Invalid device authorization endpoint", e))?,
+    );
+    let http = ReqwestClient::from(http_client()?);
+    let details: CoreDeviceAuthorizationResponse = client
+        .exchange_device_code()
+        .add_scopes(scopes(conf))
+        .request_async(&http)
+        .await
+        .map_err(|e| oidc_error("Couldn't start the device login", e))?;
+    match details.verification_uri_complete() {
+        Some(uri) => info!(
+            "To log in to the server, visit {} and check the code {}",
+            uri.secret(),
+            details.user_code().secret()
+        ),
+        None => info!(
+            "To log in to the server, visit {} and enter the code {}",
+            details.verification_uri().as_str(),
+            details.user_code().secret()
+        ),
+    }
+    client
+        .exchange_device_access_token(&details)
+        .map_err(|e| oidc_error("The OIDC provider has no token endpoint", e))?
+        .request_async(&http, tokio::time::sleep, None)
+        .await
+        .map_err(|e| oidc_error("Couldn't complete the device login", e))
+}
+
+/// Log in to the server with its OIDC provider, through the browser or the device authorization grant
+pub async fn oidc_login_server(
+    server_url: &str,
+    device: bool,
+) -> Result<ServerCredentials, ProxyAuthK8sError> {
+    let (conf, client) = server_oidc_client(server_url).await?;
+    debug!(
+        "Logging in to {} with client {}",
+        conf.issuer_url, conf.client_id
+    );
+    let token = if device {
+        device_login(&conf, client).await?
+    } else {
+        loopback_login(&conf, client).await?
+    };
+    Ok(token.into())
+}
+
+impl CliServerConfig {
+    /// Get a new server token with the refresh token of the OIDC login
+    pub async fn refresh_server_token(&self) -> Result<(), ProxyAuthK8sError> {
+        let refresh_token =
+            self.get_server_refresh_token()
+                .ok_or(ProxyAuthK8sError::Unauthenticated(
+                    "No refresh token, please re-login to the server.".to_owned(),
+                ))?;
+        let (_, client) = server_oidc_client(&self.url).await?;
+        let token = client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .map_err(|e| oidc_error("The OIDC provider has no token endpoint", e))?
+            .request_async(&ReqwestClient::from(http_client()?))
+            .await
+            .map_err(|e| {
+                debug!("Refresh token rejected: {}", e);
+                ProxyAuthK8sError::Unauthenticated(
+                    "The server session expired, please re-login to the server.".to_owned(),
+                )
+            })?;
+        let credentials = ServerCredentials::from(token);
+        self.set_server_token(credentials.access_token)?;
+        // Keep the previous refresh token when the OIDC provider doesn't rotate it
+        if credentials.refresh_token.is_some() {
+            self.set_server_refresh_token(credentials.refresh_token)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::login::sso::parse_callback_request;
+
+    use super::*;
+
+    fn client() -> ServerOidcClient {
+        let metadata: CoreProviderMetadata = serde_json::from_value(serde_json::json!({
+            "issuer": "https://idp.example.com",
+            "authorization_endpoint": "https://idp.example.com/authorize",
+            "token_endpoint": "https://idp.example.com/token",
+            "jwks_uri": "https://idp.example.com/jwks",
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["RS256"],
+        }))
+        .unwrap();
+        CoreClient::from_provider_metadata(metadata, ClientId::new("cli".to_string()), None)
+            .set_redirect_uri(
+                RedirectUrl::new("http://localhost:8000/auth/callback/server".to_string()).unwrap(),
+            )
+    }
+
+    #[test]
+    fn test_authorization_url() {
+        let conf = CliOidcConfiguration::new(
+            "cli".to_string(),
+            "https://idp.example.com".to_string(),
+            "openid email".to_string(),
+        );
+        let (auth_url, csrf_token, pkce_verifier) = authorization_url(&conf, &client());
+        let query: std::collections::HashMap<String, String> =
+            auth_url.query_pairs().into_owned().collect();
+        assert_eq!(query["state"], *csrf_token.secret());
+        assert_eq!(query["code_challenge_method"], "S256");
+        assert_eq!(
+            query["code_challenge"],
+            PkceCodeChallenge::from_code_verifier_sha256(&pkce_verifier).as_str()
+        );
+        assert!(query["scope"].contains("email"));
+
+        // Each login gets its own state
+        let (_, other_token, _) = authorization_url(&conf, &client());
+        assert_ne!(other_token.secret(), csrf_token.secret());
+
+        // Only the callback carrying the state of this login is accepted
+        let callback = |state: &str| {
+            format!(
+                "GET /auth/callback/server?code=abc&state={} HTTP/1.1\r\n\r\n",
+                state
+            )
+        };
+        assert!(
+            parse_callback_request(&callback(csrf_token.secret()), csrf_token.secret()).is_ok()
+        );
+        assert!(
+            parse_callback_request(&callback(other_token.secret()), csrf_token.secret()).is_err()
+        );
+    }
+}
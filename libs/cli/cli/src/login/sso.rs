@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use client_api::{
+    apis::auth_clusters_api::{callback_login, cluster_login},
+    models::CallbackModel,
+};
+use reqwest::Url;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    cli_config::{cli_cluster_config::StepUp, cli_server_config::CliServerConfig},
+    error::ProxyAuthK8sError,
+};
+
+/// Time given to the user to complete the login in the browser
+pub(crate) const SSO_LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+/// Path prefix of the redirect built by the server for the kubectl callback
+const CALLBACK_PATH: &str = "/auth/callback/";
+
+const SUCCESS_PAGE: &str = "<html><body><h1>Login successful</h1><p>You can close this window and go back to your terminal.</p></body></html>";
+const FAILURE_PAGE: &str = "<html><body><h1>Login failed</h1><p>Go back to your terminal for more details.</p></body></html>";
+
+/// Code and state given by the OIDC provider to the loopback listener
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationResponse {
+    pub code: String,
+    pub state: String,
+}
+
+/// Parse the HTTP request received by the loopback listener
+/// The state must be the one sent in the authorization request, otherwise the callback wasn't started by this login
+pub fn parse_callback_request(
+    request: &str,
+    expected_state: &str,
+) -> Result<AuthorizationResponse, ProxyAuthK8sError> {
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or(ProxyAuthK8sError::SsoLoginError(
+            "Invalid request received on the callback".to_string(),
+        ))?;
+    let url = Url::parse("http://localhost")
+        .and_then(|base| base.join(target))
+        .map_err(|e| ProxyAuthK8sError::SsoLoginError(format!("Invalid callback URL: {}", e)))?;
+    let (mut code, mut state, mut error) = (None, None, None);
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => code = Some(value.to_string()),
+            "state" => state = Some(value.to_string()),
+            "error_description" => error = Some(value.to_string()),
+            "error" if error.is_none() => error = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if let Some(error) = error {
+        return Err(ProxyAuthK8sError::SsoLoginError(format!(
+            "The OIDC provider refused the login: {}",
+            error
+        )));
+    }
+    match (code, state) {
+        (Some(_), Some(state)) if state != expected_state => Err(ProxyAuthK8sError::SsoLoginError(
+            "The state returned by the OIDC provider doesn't match".to_string(),
+        )),
+        (Some(code), Some(state)) => Ok(AuthorizationResponse { code, state }),
+        _ => Err(ProxyAuthK8sError::SsoLoginError(
+            "The callback doesn't contain the code and the state".to_string(),
+        )),
+    }
+}
+
+/// State sent to the OIDC provider in the authorization URL built by the server
+fn authorization_state(auth_url: &str) -> Result<String, ProxyAuthK8sError> {
+    Url::parse(auth_url)
+        .ok()
+        .and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "state")
+                .map(|(_, state)| state.to_string())
+        })
+        .ok_or(ProxyAuthK8sError::SsoLoginError(
+            "The authorization URL returned by the server has no state".to_string(),
+        ))
+}
+
+/// Wait for the browser to be redirected to the loopback listener and answer it
+pub(crate) async fn wait_for_callback(
+    listener: &TcpListener,
+    expected_state: &str,
+) -> Result<AuthorizationResponse, ProxyAuthK8sError> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| {
+            ProxyAuthK8sError::SsoLoginError(format!("Callback listener failed: {}", e))
+        })?;
+        let mut buffer = vec![0u8; 16 * 1024];
+        let read = match stream.read(&mut buffer).await {
+            Ok(read) => read,
+            Err(e) => {
+                debug!("Couldn't read the callback request: {}", e);
+                continue;
+            }
+        };
+        let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+        // Browsers also ask for the favicon, only the callback carries the code
+        if !request.starts_with(&format!("GET {}", CALLBACK_PATH)) {
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            continue;
+        }
+        let response = parse_callback_request(&request, expected_state);
+        let (status, page) = match &response {
+            Ok(_) => ("200 OK", SUCCESS_PAGE),
+            Err(_) => ("400 Bad Request", FAILURE_PAGE),
+        };
+        let answer = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            page.len(),
+            page
+        );
+        if let Err(e) = stream.write_all(answer.as_bytes()).await {
+            debug!("Couldn't answer the browser: {}", e);
+        }
+        return response;
+    }
+}
+
+impl CliServerConfig {
+    /// Log in to an SSO enabled cluster through the browser
+    /// The OIDC provider redirects the browser to a loopback listener, the code is then exchanged by the server
+    /// The step-up requirement, if any, is requested to the OIDC provider with acr_values and max_age
+    pub async fn sso_login_cluster(
+        &self,
+        ns: &str,
+        cluster: &str,
+        step_up: Option<&StepUp>,
+    ) -> Result<CallbackModel, ProxyAuthK8sError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(|e| {
+            ProxyAuthK8sError::SsoLoginError(format!("Couldn't start the callback listener: {}", e))
+        })?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| ProxyAuthK8sError::SsoLoginError(e.to_string()))?
+            .port();
+        // The server only accepts a loopback callback without path
+        // Use the address the listener is bound to, localhost may resolve to ::1 first
+        let callback_url = format!("http://127.0.0.1:{}", port);
+        debug!("Callback listener started on {}", callback_url);
+
+        let configuration = self.get_base_configuration()?;
+        let auth_url = cluster_login(
+            &configuration,
+            ns,
+            cluster,
+            None,
+            Some(&callback_url),
+            step_up.and_then(|step_up| step_up.acr_values.as_deref()),
+            step_up.and_then(|step_up| step_up.max_age),
+        )
+        .await
+        .map_err(|e| {
+            ProxyAuthK8sError::SsoLoginError(format!("Couldn't start the login: {}", e))
+        })?;
+        let state = authorization_state(&auth_url)?;
+
+        info!(
+            "Opening the browser to log in to {}/{}. If it doesn't open, visit: {}",
+            ns, cluster, auth_url
+        );
+        if let Err(e) = open::that(&auth_url) {
+            warn!("Couldn't open the browser: {}", e);
+        }
+
+        let authorization =
+            tokio::time::timeout(SSO_LOGIN_TIMEOUT, wait_for_callback(&listener, &state))
+                .await
+                .map_err(|_| {
+                    ProxyAuthK8sError::SsoLoginError(
+                        "Timed out waiting for the login in the browser".to_string(),
+                    )
+                })??;
+        callback_login(
+            &configuration,
+            ns,
+            cluster,
+            None,
+            Some(&callback_url),
+            &authorization.code,
+            &authorization.state,
+        )
+        .await
+        .map_err(|e| {
+            ProxyAuthK8sError::SsoLoginError(format!("Couldn't complete the login: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(query: &str) -> String {
+        format!(
+            "GET {}dev/c1?{} HTTP/1.1\r\nHost: 127.0.0.1:8000\r\n\r\n",
+            CALLBACK_PATH, query
+        )
+    }
+
+    #[test]
+    fn test_parse_callback_request() {
+        assert_eq!(
+            parse_callback_request(&request("code=abc&state=s1"), "s1").unwrap(),
+            AuthorizationResponse {
+                code: "abc".to_string(),
+                state: "s1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_callback_request_refused() {
+        let error = |query: &str| match parse_callback_request(&request(query), "s1") {
+            Err(ProxyAuthK8sError::SsoLoginError(message)) => message,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert!(error("code=abc&state=s2").contains("doesn't match"));
+        assert!(error("state=s1").contains("doesn't contain the code"));
+        assert!(error("code=abc").contains("doesn't contain the code"));
+        assert_eq!(
+            error("error=access_denied&state=s1"),
+            "The OIDC provider refused the login: access_denied"
+        );
+        assert_eq!(
+            error("error=access_denied&error_description=User+cancelled&state=s1"),
+            "The OIDC provider refused the login: User cancelled"
+        );
+        assert!(parse_callback_request("", "s1").is_err());
+    }
+
+    #[test]
+    fn test_authorization_state() {
+        assert_eq!(
+            authorization_state("https://idp.example.com/auth?client_id=c1&state=s%201").unwrap(),
+            "s 1"
+        );
+        assert!(authorization_state("https://idp.example.com/auth?client_id=c1").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use client_api::apis::auth_clusters_api::cluster_oidc_configuration;
+use k8s_openapi::jiff::Timestamp;
+use reqwest::{header, StatusCode};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    access::review_identity,
+    cli_config::{
+        cli_cluster_config::{CliClusterConfig, StepUp},
+        cli_server_config::CliServerConfig,
+    },
+    context::sync::proxy_cluster_url,
+    error::ProxyAuthK8sError,
+};
+
+/// Same discovery call as kubectl, the first one made with a new token
+const DISCOVERY_PATH: &str = "/api?timeout=32s";
+
+fn format_timestamp(seconds: i64) -> String {
+    Timestamp::from_second(seconds)
+        .map(|t| t.to_string())
+        .unwrap_or_else(|_| seconds.to_string())
+}
+
+/// Why the cluster most likely rejected the token, from its claims
+/// `issuer` and `audience` are the ones of the OIDC provider of the cluster, when it has one
+pub fn token_rejection_reason(
+    claims: Option<&Value>,
+    now: i64,
+    expected_issuer: Option<&str>,
+    expected_audience: Option<&str>,
+) -> String {
+    let Some(claims) = claims else {
+        return "the cluster rejected the token, it isn't a JWT so no more detail is available"
+            .to_string();
+    };
+    if let Some(exp) = claims.get("exp").and_then(Value::as_i64) {
+        if exp <= now {
+            return format!("expired at {}", format_timestamp(exp));
+        }
+    }
+    if let Some(nbf) = claims.get("nbf").and_then(Value::as_i64) {
+        if nbf > now {
+            return format!("not valid before {}", format_timestamp(nbf));
+        }
+    }
+    let issuer = claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let audiences: Vec<String> = match claims.get("aud") {
+        Some(Value::String(aud)) => vec![aud.clone()],
+        Some(Value::Array(auds)) => auds
+            .iter()
+            .filter_map(|aud| aud.as_str().map(str::to_string))
+            .collect(),
+        _ => vec![],
+    };
+    if let Some(expected_issuer) = expected_issuer {
+        if issuer.trim_end_matches('/') != expected_issuer.trim_end_matches('/') {
+            return format!(
+                "unknown issuer '{}', expected '{}'",
+                issuer, expected_issuer
+            );
+        }
+    }
+    if let Some(expected_audience) = expected_audience {
+        if !audiences.iter().any(|aud| aud == expected_audience) {
+            return format!(
+                "wrong audience [{}], expected '{}'",
+                audiences.join(", "),
+                expected_audience
+            );
+        }
+    }
+    format!(
+        "the cluster rejected the token issued by '{}' for [{}]",
+        issuer,
+        audiences.join(", ")
+    )
+}
+
+impl CliServerConfig {
+    /// Issuer and client of the OIDC provider of the cluster, None if it has none
+    async fn cluster_oidc_provider(&self, ns: &str, cluster: &str) -> Option<(String, String)> {
+        let configuration = self.get_base_configuration().ok()?;
+        match cluster_oidc_configuration(&configuration, ns, cluster).await {
+            Ok(conf) => Some((conf.issuer_url, conf.client_id)),
+            Err(e) => {
+                debug!("The OIDC provider of the cluster is not available: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Check the token against the cluster before it is stored, with the call kubectl makes first
+    /// Return the username and groups of the token when the cluster supports SelfSubjectReview
+    pub async fn validate_cluster_token(
+        &self,
+        ns: &str,
+        cluster: &str,
+        token: &str,
+    ) -> Result<Option<(String, Vec<String>)>, ProxyAuthK8sError> {
+        let url = format!(
+            "{}{}",
+            proxy_cluster_url(&self.url, ns, cluster),
+            DISCOVERY_PATH
+        );
+        let response = Self::http_client()?
+            .get(&url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| {
+                ProxyAuthK8sError::RemoteServerError(format!("Couldn't reach {}: {}", url, e))
+            })?;
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::UNAUTHORIZED
+                if let Some(step_up) = response
+                    .headers()
+                    .get(header::WWW_AUTHENTICATE)
+                    .and_then(|challenge| challenge.to_str().ok())
+                    .and_then(StepUp::from_challenge) =>
+            {
+                return Err(ProxyAuthK8sError::InvalidClusterToken(format!(
+                    "the cluster asks for a step-up authentication, login using 'login {}{}'",
+                    cluster,
+                    step_up.login_flags()
+                )));
+            }
+            StatusCode::UNAUTHORIZED => {
+                let claims = CliClusterConfig::jwt_claims(token);
+                let provider = self.cluster_oidc_provider(ns, cluster).await;
+                return Err(ProxyAuthK8sError::InvalidClusterToken(
+                    token_rejection_reason(
+                        claims.as_ref(),
+                        Timestamp::now().as_second(),
+                        provider.as_ref().map(|(issuer, _)| issuer.as_str()),
+                        provider.as_ref().map(|(_, client_id)| client_id.as_str()),
+                    ),
+                ));
+            }
+            StatusCode::FORBIDDEN => {
+                return Err(ProxyAuthK8sError::InvalidClusterToken(
+                    "the token is valid but not allowed to read the API discovery".to_string(),
+                ));
+            }
+            status => {
+                return Err(ProxyAuthK8sError::RemoteServerError(format!(
+                    "Unexpected status {} from {}",
+                    status, url
+                )));
+            }
+        }
+
+        // SelfSubjectReview is only served from Kubernetes 1.28
+        match review_identity(self.cluster_client_with_token(ns, cluster, token.to_string())?).await
+        {
+            Ok(identity) => Ok(Some(identity)),
+            Err(e) => {
+                warn!("Couldn't resolve the identity of the token: {}", e);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const ISSUER: &str = "https://idp.example.com/realms/main";
+
+    fn reason(claims: Value) -> String {
+        token_rejection_reason(Some(&claims), NOW, Some(ISSUER), Some("cluster-c1"))
+    }
+
+    #[test]
+    fn test_token_rejection_reason() {
+        assert!(
+            reason(json!({"exp": NOW - 60, "iss": ISSUER, "aud": "cluster-c1"}))
+                .starts_with("expired at")
+        );
+        assert!(reason(json!({"exp": NOW, "iss": ISSUER})).starts_with("expired at"));
+        assert!(
+            reason(json!({"exp": NOW + 60, "nbf": NOW + 30, "iss": ISSUER}))
+                .starts_with("not valid before")
+        );
+        assert_eq!(
+            reason(
+                json!({"exp": NOW + 60, "iss": "https://other.example.com", "aud": "cluster-c1"})
+            ),
+            format!(
+                "unknown issuer 'https://other.example.com', expected '{}'",
+                ISSUER
+            )
+        );
+        assert_eq!(
+            reason(
+                json!({"exp": NOW + 60, "iss": format!("{}/", ISSUER), "aud": ["account", "kube"]})
+            ),
+            "wrong audience [account, kube], expected 'cluster-c1'"
+        );
+        assert!(
+            reason(json!({"iss": ISSUER, "aud": ["account", "cluster-c1"]}))
+                .starts_with("the cluster rejected the token issued by")
+        );
+        assert_eq!(
+            token_rejection_reason(None, NOW, Some(ISSUER), Some("cluster-c1")),
+            "the cluster rejected the token, it isn't a JWT so no more detail is available"
+        );
+        // Without OIDC provider only the dates can be checked
+        assert_eq!(
+            token_rejection_reason(Some(&json!({"iss": "a", "aud": "b"})), NOW, None, None),
+            "the cluster rejected the token issued by 'a' for [b]"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use client_api::{
+    apis::{
+        auth_clusters_api::{logout, LogoutError},
+        Error,
+    },
+    models::LogoutBody,
+};
+use reqwest::StatusCode;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    cli_config::cli_server_config::CliServerConfig, ctx::CliCtx, error::ProxyAuthK8sError,
+};
+
+impl CliServerConfig {
+    /// Revoke the token of a cluster with its OIDC provider, through the server
+    /// The refresh token is revoked when there is one, the provider then drops the access tokens issued from it
+    pub async fn revoke_cluster_token(
+        &self,
+        ns: &str,
+        cluster: &str,
+    ) -> Result<(), ProxyAuthK8sError> {
+        let (token, kind) =
+            match self.get_cluster_refresh_token(ns.to_string(), cluster.to_string()) {
+                Ok(refresh_token) => (refresh_token, "refresh_token"),
+                Err(_) => (
+                    self.get_cluster_token(ns.to_string(), cluster.to_string())?,
+                    "access_token",
+                ),
+            };
+        let mut body = LogoutBody::new(token);
+        body.token_type_hint = Some(Some(kind.to_string()));
+        match logout(&self.get_base_configuration()?, ns, cluster, body).await {
+            Ok(()) => Ok(()),
+            Err(Error::ResponseError(content)) => Err(match content.entity {
+                Some(LogoutError::Status401()) => ProxyAuthK8sError::Unauthenticated(
+                    "Authentification failed, please re-login to the server.".to_owned(),
+                ),
+                _ if content.status == StatusCode::NOT_IMPLEMENTED => {
+                    ProxyAuthK8sError::RemoteServerError(
+                        "The OIDC provider of the cluster doesn't support token revocation"
+                            .to_owned(),
+                    )
+                }
+                _ => ProxyAuthK8sError::RemoteServerError(format!(
+                    "Couldn't revoke the token, status {}: {}",
+                    content.status, content.content
+                )),
+            }),
+            Err(e) => Err(ProxyAuthK8sError::RemoteServerError(format!(
+                "Couldn't revoke the token: {}",
+                e
+            ))),
+        }
+    }
+}
+
+impl CliCtx {
+    pub async fn handle_logout(&mut self, cluster_name: Option<String>) {
+        let server_name =
+            match self
+                .config
+                .get_server_config_by_url(if self.server_url.is_empty() {
+                    None
+                } else {
+                    Some(self.server_url.clone())
+                }) {
+                Ok(config) => CliServerConfig::url_to_name_from_string(config.url.clone()),
+                Err(e) => {
+                    error!("Error retrieving server configuration: {}", e);
+                    return;
+                }
+            };
+        match cluster_name {
+            Some(cluster) => self.handle_logout_cluster(server_name, cluster).await,
+            None => self.handle_logout_server(server_name).await,
+        }
+        match self.config.write_to_file(self.config_path.clone()) {
+            Ok(_) => info!("Config file updated successfully."),
+            Err(e) => error!("Failed to update config file: {}", e),
+        };
+    }
+
+    async fn handle_logout_cluster(&mut self, server_name: String, cluster: String) {
+        let Some(server_config) = self.config.servers.get_mut(&server_name) else {
+            return;
+        };
+        let namespace = if self.namespace.is_empty() {
+            server_config.namespace.clone()
+        } else {
+            self.namespace.clone()
+        };
+        let key = format!("{}/{}", namespace, cluster);
+        if !server_config.clusters.contains_key(&key) {
+            warn!("Not logged in to cluster {}.", key);
+            return;
+        }
+        // The local token is removed even if the revocation fails, it then stays valid until it expires
+        if let Err(e) = server_config
+            .revoke_cluster_token(&namespace, &cluster)
+            .await
+        {
+            warn!("Token of cluster {} not revoked: {}", key, e);
+        }
+        if let Err(e) = server_config.clear_cluster_token(namespace, cluster) {
+            debug!("Error clearing token for cluster {}: {}", key, e);
+        }
+        server_config.clusters.remove(&key);
+        info!("Logout from cluster {} successful.", key);
+    }
+
+    async fn handle_logout_server(&mut self, server_name: String) {
+        let Some(server_config) = self.config.servers.get_mut(&server_name) else {
+            return;
+        };
+        for key in server_config.clusters.keys() {
+            let Some((ns, cluster)) = key.split_once('/') else {
+                continue;
+            };
+            if let Err(e) = server_config.revoke_cluster_token(ns, cluster).await {
+                warn!("Token of cluster {} not revoked: {}", key, e);
+            }
+        }
+        server_config.clear_all_tokens();
+        server_config.clusters.clear();
+        info!("Logout from server {} successful.", server_config.url);
+    }
+}
@@ -0,0 +1,507 @@
+use serde_json::Value;
+
+/// Step of a path, such as `.items`, `[*]`, `[0]`, `[1:3]`, `..name` or `[?(@.enabled==true)]`
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Recursive(String),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+}
+
+/// `[?(@.path)]` keeps the elements having the path, `[?(@.path==value)]` compares it
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    path: Path,
+    comparison: Option<(Operator, Value)>,
+}
+
+/// Path starting from the root with `$`, or from the current element otherwise
+#[derive(Debug, Clone, PartialEq)]
+struct Path {
+    from_root: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Path(Path),
+    Range(Path, Vec<Node>),
+}
+
+/// Template of `-o jsonpath=...`, the subset of the kubectl JSONPath syntax
+/// Supports fields, `[*]`, indexes, slices, `..`, filters, `range`/`end` and `"literals"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    nodes: Vec<Node>,
+}
+
+fn parse_literal(literal: &str) -> Result<Value, String> {
+    let literal = literal.trim();
+    if let Some(string) = literal
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .or_else(|| {
+            literal
+                .strip_prefix('\'')
+                .and_then(|l| l.strip_suffix('\''))
+        })
+    {
+        return Ok(Value::String(unescape(string)));
+    }
+    serde_json::from_str(literal).map_err(|_| format!("invalid literal '{}'", literal))
+}
+
+fn unescape(string: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Index of the delimiter closing the one at `start`, quotes are skipped
+fn closing(chars: &[char], start: usize, open: char, close: char) -> Result<usize, String> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut i = start;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(_) if c == '\\' => i += 1,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == open => depth += 1,
+            None if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            None => {}
+        }
+        i += 1;
+    }
+    Err(format!("unclosed '{}'", open))
+}
+
+fn parse_filter(filter: &str) -> Result<Filter, String> {
+    let (path, comparison) = match filter.find("==").or_else(|| filter.find("!=")) {
+        Some(index) => {
+            let operator = if &filter[index..index + 2] == "==" {
+                Operator::Eq
+            } else {
+                Operator::Ne
+            };
+            (
+                &filter[..index],
+                Some((operator, parse_literal(&filter[index + 2..])?)),
+            )
+        }
+        None => (filter, None),
+    };
+    let path = parse_path(path.trim())?;
+    Ok(Filter { path, comparison })
+}
+
+fn parse_bracket(content: &str) -> Result<Segment, String> {
+    let content = content.trim();
+    if content == "*" {
+        return Ok(Segment::Wildcard);
+    }
+    if let Some(filter) = content
+        .strip_prefix("?(")
+        .and_then(|filter| filter.strip_suffix(')'))
+    {
+        return Ok(Segment::Filter(parse_filter(filter)?));
+    }
+    if let Value::String(field) = parse_literal(content).unwrap_or(Value::Null) {
+        return Ok(Segment::Field(field));
+    }
+    let index = |bound: &str| -> Result<Option<i64>, String> {
+        let bound = bound.trim();
+        if bound.is_empty() {
+            return Ok(None);
+        }
+        bound
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid index '{}'", bound))
+    };
+    match content.split_once(':') {
+        Some((start, end)) => Ok(Segment::Slice(index(start)?, index(end)?)),
+        None => index(content)?
+            .map(Segment::Index)
+            .ok_or_else(|| "empty index".to_string()),
+    }
+}
+
+fn parse_path(path: &str) -> Result<Path, String> {
+    let path = path.trim();
+    let (from_root, path) = match path.strip_prefix('$') {
+        Some(path) => (true, path),
+        None => (false, path.strip_prefix('@').unwrap_or(path)),
+    };
+    let chars: Vec<char> = path.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+    let ident = |start: usize| -> (String, usize) {
+        let end = chars[start..]
+            .iter()
+            .position(|c| *c == '.' || *c == '[')
+            .map_or(chars.len(), |end| start + end);
+        (chars[start..end].iter().collect(), end)
+    };
+    while i < chars.len() {
+        match chars[i] {
+            '.' if chars.get(i + 1) == Some(&'.') => {
+                let (name, end) = ident(i + 2);
+                if name.is_empty() {
+                    return Err(format!("missing field after '..' in '{}'", path));
+                }
+                segments.push(Segment::Recursive(name));
+                i = end;
+            }
+            '.' => {
+                let (name, end) = ident(i + 1);
+                match name.as_str() {
+                    "" => {}
+                    "*" => segments.push(Segment::Wildcard),
+                    _ => segments.push(Segment::Field(name)),
+                }
+                i = end;
+            }
+            '[' => {
+                let end = closing(&chars, i, '[', ']')?;
+                let content: String = chars[i + 1..end].iter().collect();
+                segments.push(parse_bracket(&content)?);
+                i = end + 1;
+            }
+            _ => {
+                // A field without the leading dot, such as `items[0]`
+                let (name, end) = ident(i);
+                segments.push(Segment::Field(name));
+                i = end;
+            }
+        }
+    }
+    Ok(Path {
+        from_root,
+        segments,
+    })
+}
+
+impl JsonPath {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let chars: Vec<char> = template.chars().collect();
+        // Stack of the open ranges, the last one receives the nodes
+        let mut stack: Vec<(Option<Path>, Vec<Node>)> = vec![(None, Vec::new())];
+        let mut text = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] != '{' {
+                text.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            let end = closing(&chars, i, '{', '}')?;
+            let expression: String = chars[i + 1..end].iter().collect();
+            let expression = expression.trim();
+            i = end + 1;
+            let nodes = &mut stack.last_mut().expect("root is never popped").1;
+            if !text.is_empty() {
+                nodes.push(Node::Text(std::mem::take(&mut text)));
+            }
+            if let Some(path) = expression.strip_prefix("range ") {
+                stack.push((Some(parse_path(path)?), Vec::new()));
+            } else if expression == "end" {
+                match stack.pop() {
+                    Some((Some(path), body)) => stack
+                        .last_mut()
+                        .ok_or_else(|| "unexpected {end}".to_string())?
+                        .1
+                        .push(Node::Range(path, body)),
+                    _ => return Err("{end} without {range}".to_string()),
+                }
+            } else if expression.starts_with('"') || expression.starts_with('\'') {
+                match parse_literal(expression)? {
+                    Value::String(literal) => nodes.push(Node::Text(literal)),
+                    _ => return Err(format!("invalid literal {}", expression)),
+                }
+            } else {
+                nodes.push(Node::Path(parse_path(expression)?));
+            }
+        }
+        if stack.len() != 1 {
+            return Err("{range} without {end}".to_string());
+        }
+        let mut nodes = stack.pop().map(|(_, nodes)| nodes).unwrap_or_default();
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(JsonPath { nodes })
+    }
+
+    /// Render the template, missing keys print nothing as with kubectl
+    pub fn render(&self, root: &Value) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, root, root, &mut output);
+        output
+    }
+
+    /// Values of a single path template, as used by the custom columns
+    pub fn values<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        self.nodes
+            .iter()
+            .flat_map(|node| match node {
+                Node::Path(path) => eval(path, root, root),
+                _ => vec![],
+            })
+            .collect()
+    }
+}
+
+fn render_nodes(nodes: &[Node], root: &Value, current: &Value, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Path(path) => {
+                let values: Vec<String> = eval(path, root, current)
+                    .into_iter()
+                    .map(value_to_string)
+                    .collect();
+                output.push_str(&values.join(" "));
+            }
+            Node::Range(path, body) => {
+                for value in eval(path, root, current) {
+                    render_nodes(body, root, value, output);
+                }
+            }
+        }
+    }
+}
+
+/// Strings are printed as is, the other values as compact JSON
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn eval<'a>(path: &Path, root: &'a Value, current: &'a Value) -> Vec<&'a Value> {
+    let mut values = vec![if path.from_root { root } else { current }];
+    for segment in &path.segments {
+        values = values
+            .into_iter()
+            .flat_map(|value| step(segment, root, value))
+            .collect();
+    }
+    values
+}
+
+fn step<'a>(segment: &Segment, root: &'a Value, value: &'a Value) -> Vec<&'a Value> {
+    match segment {
+        Segment::Field(field) => value.get(field).into_iter().collect(),
+        Segment::Wildcard => match value {
+            Value::Array(array) => array.iter().collect(),
+            Value::Object(object) => object.values().collect(),
+            _ => vec![],
+        },
+        Segment::Index(index) => value
+            .as_array()
+            .and_then(|array| {
+                let index = if *index < 0 {
+                    array.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| array.get(index))
+            })
+            .into_iter()
+            .collect(),
+        Segment::Slice(start, end) => {
+            let Some(array) = value.as_array() else {
+                return vec![];
+            };
+            let len = array.len() as i64;
+            let bound = |bound: i64| (if bound < 0 { len + bound } else { bound }).clamp(0, len);
+            let start = bound(start.unwrap_or(0)) as usize;
+            let end = bound(end.unwrap_or(len)) as usize;
+            array
+                .get(start..end.max(start))
+                .unwrap_or_default()
+                .iter()
+                .collect()
+        }
+        Segment::Recursive(field) => {
+            let mut found = Vec::new();
+            collect_recursive(field, value, &mut found);
+            found
+        }
+        Segment::Filter(filter) => {
+            let candidates: Vec<&Value> = match value {
+                Value::Array(array) => array.iter().collect(),
+                other => vec![other],
+            };
+            candidates
+                .into_iter()
+                .filter(|candidate| {
+                    let matched = eval(&filter.path, root, candidate);
+                    match &filter.comparison {
+                        None => !matched.is_empty(),
+                        Some((Operator::Eq, expected)) => matched.contains(&expected),
+                        Some((Operator::Ne, expected)) => !matched.contains(&expected),
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+fn collect_recursive<'a>(field: &str, value: &'a Value, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(matched) = object.get(field) {
+                found.push(matched);
+            }
+            for child in object.values() {
+                collect_recursive(field, child, found);
+            }
+        }
+        Value::Array(array) => {
+            for child in array {
+                collect_recursive(field, child, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn clusters() -> Value {
+        json!({
+            "kind": "List",
+            "items": [
+                {"name": "c1", "namespace": "dev", "enabled": true, "spec": {"url": "https://c1"}},
+                {"name": "c2", "namespace": "dev", "enabled": false, "spec": {"url": "https://c2"}},
+                {"name": "c3", "namespace": "prod", "enabled": true},
+            ]
+        })
+    }
+
+    fn render(template: &str) -> String {
+        JsonPath::parse(template).unwrap().render(&clusters())
+    }
+
+    #[test]
+    fn test_fields_and_indexes() {
+        assert_eq!(render("{.kind}"), "List");
+        assert_eq!(render("{$.items[0].name}"), "c1");
+        assert_eq!(render("{.items[-1].name}"), "c3");
+        assert_eq!(render("{.items[*].name}"), "c1 c2 c3");
+        assert_eq!(render("{.items[0]['name']}"), "c1");
+        assert_eq!(render("{.items[0].spec}"), r#"{"url":"https://c1"}"#);
+        // Missing keys and out of range indexes print nothing
+        assert_eq!(render("{.items[5].name}{.missing}"), "");
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(render("{.items[0:2].name}"), "c1 c2");
+        assert_eq!(render("{.items[1:].name}"), "c2 c3");
+        assert_eq!(render("{.items[:1].name}"), "c1");
+        assert_eq!(render("{.items[-2:].name}"), "c2 c3");
+        assert_eq!(render("{.items[2:1].name}"), "");
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(render("{.items[?(@.enabled==true)].name}"), "c1 c3");
+        assert_eq!(render("{.items[?(@.namespace!='dev')].name}"), "c3");
+        assert_eq!(render("{.items[?(@.spec)].name}"), "c1 c2");
+        assert_eq!(
+            render(r#"{.items[?(@.namespace=="dev")].spec.url}"#),
+            "https://c1 https://c2"
+        );
+    }
+
+    #[test]
+    fn test_recursive_descent() {
+        assert_eq!(render("{..url}"), "https://c1 https://c2");
+        assert_eq!(render("{.items[2]..name}"), "c3");
+    }
+
+    #[test]
+    fn test_range_and_literals() {
+        assert_eq!(
+            render(r#"{range .items[*]}{.namespace}/{.name}{"\n"}{end}"#),
+            "dev/c1\ndev/c2\nprod/c3\n"
+        );
+        assert_eq!(
+            render("{range .items[?(@.enabled==true)]}[{.name}]{end} done"),
+            "[c1][c3] done"
+        );
+        assert_eq!(
+            JsonPath::parse("{.name}")
+                .unwrap()
+                .values(&json!({"name": "c1"})),
+            vec![&json!("c1")]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            JsonPath::parse("{range .items[*]}{.name}").unwrap_err(),
+            "{range} without {end}"
+        );
+        assert_eq!(
+            JsonPath::parse("{.name}{end}").unwrap_err(),
+            "{end} without {range}"
+        );
+        assert_eq!(JsonPath::parse("{.name").unwrap_err(), "unclosed '{'");
+        assert_eq!(JsonPath::parse("{.items[0}").unwrap_err(), "unclosed '['");
+        assert_eq!(
+            JsonPath::parse("{.items[x]}").unwrap_err(),
+            "invalid index 'x'"
+        );
+        assert_eq!(JsonPath::parse("{.items[]}").unwrap_err(), "empty index");
+        assert_eq!(
+            JsonPath::parse("{..}").unwrap_err(),
+            "missing field after '..' in '..'"
+        );
+        assert_eq!(
+            JsonPath::parse("{.items[?(@.name==dev)]}").unwrap_err(),
+            "invalid literal 'dev'"
+        );
+    }
+}
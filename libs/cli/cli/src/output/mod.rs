@@ -0,0 +1,259 @@
+use comfy_table::Table;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    ctx::ContextFormat,
+    output::jsonpath::{value_to_string, JsonPath},
+};
+
+pub mod jsonpath;
+
+/// Column of `-o custom-columns=HEADER:.path,...`
+pub type CustomColumn = (String, String);
+
+/// Item of a command output, printed in every format of `--format`
+pub trait OutputItem: Serialize {
+    /// Kind printed by `-o name`, as `kind/name`
+    const KIND: &'static str;
+
+    /// Columns of the table, `wide` adds the extra ones
+    fn to_row_headers(wide: bool) -> Vec<String>;
+
+    fn to_row(&self, wide: bool) -> Vec<String>;
+
+    fn name(&self) -> String;
+}
+
+/// Parse `HEADER:.path,HEADER:{.path}` as kubectl does
+pub fn parse_custom_columns(spec: &str) -> Result<Vec<CustomColumn>, String> {
+    spec.split(',')
+        .map(|column| {
+            let (header, path) = column
+                .split_once(':')
+                .ok_or_else(|| format!("expected HEADER:PATH, got '{}'", column))?;
+            let path = path.trim();
+            let template = if path.starts_with('{') {
+                path.to_string()
+            } else {
+                format!("{{{}}}", path)
+            };
+            JsonPath::parse(&template)?;
+            Ok((header.to_string(), template))
+        })
+        .collect()
+}
+
+fn table(headers: Vec<String>, rows: Vec<Vec<String>>) -> String {
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    table.set_header(headers);
+    for row in rows {
+        table.add_row(row);
+    }
+    table.to_string()
+}
+
+/// Render `list`, the serialized output, or its `items` as tables and names
+/// The jsonpath template applies to `list`, the custom columns to each item, as with kubectl
+pub fn render<T: Serialize>(
+    list: &impl Serialize,
+    items: &[T],
+    format: &ContextFormat,
+    headers: impl Fn(bool) -> Vec<String>,
+    row: impl Fn(&T, bool) -> Vec<String>,
+    name: impl Fn(&T) -> String,
+) -> String {
+    match format {
+        ContextFormat::Json => serde_json::to_string_pretty(list).unwrap_or_default(),
+        ContextFormat::Yaml => serde_yaml::to_string(list).unwrap_or_default(),
+        ContextFormat::Table => table(
+            headers(false),
+            items.iter().map(|i| row(i, false)).collect(),
+        ),
+        ContextFormat::Wide => table(headers(true), items.iter().map(|i| row(i, true)).collect()),
+        ContextFormat::Name => items.iter().map(name).collect::<Vec<String>>().join("\n"),
+        ContextFormat::JsonPath(template) => match JsonPath::parse(template) {
+            Ok(jsonpath) => jsonpath.render(&serde_json::to_value(list).unwrap_or_default()),
+            Err(e) => format!("error: {}", e),
+        },
+        ContextFormat::CustomColumns(columns) => {
+            let paths: Vec<Option<JsonPath>> = columns
+                .iter()
+                .map(|(_, template)| JsonPath::parse(template).ok())
+                .collect();
+            let rows = items
+                .iter()
+                .map(|item| {
+                    let item = serde_json::to_value(item).unwrap_or_default();
+                    paths
+                        .iter()
+                        .map(|path| {
+                            let values: Vec<String> = path
+                                .as_ref()
+                                .map(|path| path.values(&item))
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|value| !value.is_null())
+                                .map(value_to_string)
+                                .collect();
+                            if values.is_empty() {
+                                "<none>".to_string()
+                            } else {
+                                values.join(",")
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            table(
+                columns.iter().map(|(header, _)| header.clone()).collect(),
+                rows,
+            )
+        }
+    }
+}
+
+/// Render the items of an output list, see [`render`]
+pub fn to_output<T: OutputItem>(
+    list: &impl Serialize,
+    items: &[T],
+    format: &ContextFormat,
+) -> String {
+    render(
+        list,
+        items,
+        format,
+        T::to_row_headers,
+        |item, wide| item.to_row(wide),
+        |item| format!("{}/{}", T::KIND, item.name()),
+    )
+}
+
+/// Value of a single jsonpath template, used when the output isn't a list
+pub fn render_value(value: &impl Serialize, format: &ContextFormat) -> String {
+    match format {
+        ContextFormat::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+        ContextFormat::JsonPath(template) => JsonPath::parse(template)
+            .map(|jsonpath| jsonpath.render(&serde_json::to_value(value).unwrap_or(Value::Null)))
+            .unwrap_or_else(|e| format!("error: {}", e)),
+        _ => serde_yaml::to_string(value).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Cluster {
+        name: String,
+        namespace: String,
+        url: Option<String>,
+    }
+
+    impl OutputItem for Cluster {
+        const KIND: &'static str = "cluster";
+
+        fn to_row_headers(_wide: bool) -> Vec<String> {
+            vec!["NAME".to_string()]
+        }
+
+        fn to_row(&self, _wide: bool) -> Vec<String> {
+            vec![self.name.clone()]
+        }
+
+        fn name(&self) -> String {
+            format!("{}/{}", self.namespace, self.name)
+        }
+    }
+
+    fn clusters() -> Vec<Cluster> {
+        vec![
+            Cluster {
+                name: "c1".to_string(),
+                namespace: "dev".to_string(),
+                url: Some("https://c1".to_string()),
+            },
+            Cluster {
+                name: "c2".to_string(),
+                namespace: "prod".to_string(),
+                url: None,
+            },
+        ]
+    }
+
+    /// Cells of each line of a table, the padding depends on the widths
+    fn cells(output: &str) -> Vec<Vec<&str>> {
+        output
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_custom_columns() {
+        assert_eq!(
+            parse_custom_columns("NAME:.name,URL:{.spec.url}").unwrap(),
+            vec![
+                ("NAME".to_string(), "{.name}".to_string()),
+                ("URL".to_string(), "{.spec.url}".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_custom_columns("NAME").unwrap_err(),
+            "expected HEADER:PATH, got 'NAME'"
+        );
+        assert_eq!(
+            parse_custom_columns("NAME:.items[x]").unwrap_err(),
+            "invalid index 'x'"
+        );
+    }
+
+    #[test]
+    fn test_name_output() {
+        let items = clusters();
+        assert_eq!(
+            to_output(&items, &items, &ContextFormat::Name),
+            "cluster/dev/c1\ncluster/prod/c2"
+        );
+    }
+
+    #[test]
+    fn test_custom_columns_output() {
+        let items = clusters();
+        let columns = parse_custom_columns("NS:.namespace,NAME:.name,URL:.url").unwrap();
+        let output = to_output(&items, &items, &ContextFormat::CustomColumns(columns));
+        // A missing or null value prints <none> as with kubectl
+        assert_eq!(
+            cells(&output),
+            vec![
+                vec!["NS", "NAME", "URL"],
+                vec!["dev", "c1", "https://c1"],
+                vec!["prod", "c2", "<none>"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jsonpath_output() {
+        let items = clusters();
+        // The template applies to the whole list, not to each item
+        let format = ContextFormat::JsonPath("{[*].name}".to_string());
+        assert_eq!(to_output(&items, &items, &format), "c1 c2");
+        let format = ContextFormat::JsonPath(
+            r#"{range [*]}{.namespace}/{.name}={.url}{"\n"}{end}"#.to_string(),
+        );
+        assert_eq!(
+            to_output(&items, &items, &format),
+            "dev/c1=https://c1\nprod/c2=null\n"
+        );
+        let format = ContextFormat::JsonPath("{range [*]}{.name}".to_string());
+        assert_eq!(
+            to_output(&items, &items, &format),
+            "error: {range} without {end}"
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::io::{self, IsTerminal, Stderr, Write};
+
+use client_api::models::VisibleCluster;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tracing::{error, info};
+
+use crate::{
+    cli_config::cli_server_config::CliServerConfig,
+    context::sync::{exec_config, proxy_cluster_url, sync_entry_name},
+    ctx::CliCtx,
+};
+
+/// Score of `text` for a fuzzy `query`, None when the query isn't a subsequence of the text
+/// Consecutive characters and characters starting a word, after `/`, `-`, `_` or `.`, score higher
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|c| *c == query_char)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(text[found - 1], '/' | '-' | '_' | '.') {
+            score += 3;
+        }
+        score -= previous.map_or(found, |previous| found - previous - 1) as i64 / 4;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+struct PickerEntry {
+    cluster: VisibleCluster,
+    logged_in: bool,
+}
+
+impl PickerEntry {
+    fn key(&self) -> String {
+        format!("{}/{}", self.cluster.namespace, self.cluster.name)
+    }
+
+    fn to_line(&self, width: usize) -> String {
+        let reachable = match self.cluster.is_reachable.flatten() {
+            Some(true) => "reachable",
+            Some(false) => "unreachable",
+            None => "unknown",
+        };
+        format!(
+            "{:<width$} {:<11} {:<3} {}",
+            self.key(),
+            reachable,
+            if self.cluster.sso_enabled { "SSO" } else { "" },
+            if self.logged_in { "logged in" } else { "" },
+        )
+    }
+}
+
+/// Restore the terminal even when the picker returns early
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(out: &mut Stderr) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Entries matching the query, best score first
+fn filter_entries<'a>(entries: &'a [PickerEntry], query: &str) -> Vec<&'a PickerEntry> {
+    let mut matches: Vec<(i64, &PickerEntry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.key()).map(|score| (score, entry)))
+        .collect();
+    matches.sort_by(|(a, entry_a), (b, entry_b)| b.cmp(a).then(entry_a.key().cmp(&entry_b.key())));
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn render(
+    out: &mut Stderr,
+    query: &str,
+    matches: &[&PickerEntry],
+    total: usize,
+    selected: usize,
+) -> io::Result<()> {
+    let (_, rows) = terminal::size()?;
+    let visible = (rows as usize).saturating_sub(2).max(1);
+    let offset = selected.saturating_sub(visible - 1);
+    let width = matches
+        .iter()
+        .map(|entry| entry.key().len())
+        .max()
+        .unwrap_or(0);
+    queue!(
+        out,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(format!("> {}", query)),
+        MoveTo(0, 1),
+        SetAttribute(Attribute::Dim),
+        Print(format!(
+            "  {}/{}  ↑/↓ select, Enter pick, Esc cancel",
+            matches.len(),
+            total
+        )),
+        SetAttribute(Attribute::Reset),
+    )?;
+    for (row, entry) in matches.iter().skip(offset).take(visible).enumerate() {
+        queue!(out, MoveTo(0, row as u16 + 2))?;
+        if offset + row == selected {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(format!("> {}", entry.to_line(width))),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(format!("  {}", entry.to_line(width))))?;
+        }
+    }
+    out.flush()
+}
+
+/// Run the picker, return the key of the selected entry or None if cancelled
+fn run_picker(entries: &[PickerEntry], initial_query: String) -> io::Result<Option<String>> {
+    let mut out = io::stderr();
+    let _guard = TerminalGuard::enter(&mut out)?;
+    let mut query = initial_query;
+    let mut selected = 0;
+    loop {
+        let matches = filter_entries(entries, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        render(&mut out, &query, &matches, entries.len(), selected)?;
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+        match (code, modifiers) {
+            (KeyCode::Esc, _) => return Ok(None),
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => return Ok(None),
+            (KeyCode::Enter, _) => return Ok(matches.get(selected).map(|entry| entry.key())),
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                selected = selected.saturating_sub(1)
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => selected += 1,
+            (KeyCode::Backspace, _) => {
+                query.pop();
+                selected = 0;
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl CliCtx {
+    /// Pick a visible cluster with a fuzzy search, log in to it if needed and switch to its context
+    /// An existing context pointing at the cluster is reused, otherwise one is written as `context sync` would
+    pub async fn handle_pick(&mut self, query: Option<String>) {
+        if !io::stderr().is_terminal() {
+            error!(
+                "The picker needs a terminal, use 'login <cluster>' and 'context --set' instead."
+            );
+            return;
+        }
+        let server_config =
+            match self
+                .config
+                .get_server_config_by_url(if self.server_url.is_empty() {
+                    None
+                } else {
+                    Some(self.server_url.clone())
+                }) {
+                Ok(config) => config.clone(),
+                Err(e) => {
+                    error!(
+                        "Error retrieving server configuration, please login to server first: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+        let clusters = match server_config.get_clusters_from_remote().await {
+            Ok(clusters) => clusters.clusters,
+            Err(e) => {
+                error!("Failed to retrieve clusters: {}", e);
+                return;
+            }
+        };
+        let entries: Vec<PickerEntry> = clusters
+            .into_iter()
+            .filter(|cluster| {
+                cluster.enabled
+                    && (self.namespace.is_empty() || cluster.namespace == self.namespace)
+            })
+            .map(|cluster| PickerEntry {
+                logged_in: server_config
+                    .clusters
+                    .contains_key(&format!("{}/{}", cluster.namespace, cluster.name)),
+                cluster,
+            })
+            .collect();
+        if entries.is_empty() {
+            error!("No cluster visible on {}.", server_config.url);
+            return;
+        }
+
+        let selected = match run_picker(&entries, query.unwrap_or_default()) {
+            Ok(Some(selected)) => selected,
+            Ok(None) => {
+                info!("No cluster picked.");
+                return;
+            }
+            Err(e) => {
+                error!("Failed to run the picker: {}", e);
+                return;
+            }
+        };
+        let Some(entry) = entries.iter().find(|entry| entry.key() == selected) else {
+            return;
+        };
+        let (ns, cluster) = (entry.cluster.namespace.clone(), entry.cluster.name.clone());
+
+        if !entry.logged_in {
+            info!("Not logged in to cluster {}/{}, logging in.", ns, cluster);
+            self.namespace = ns.clone();
+            self.server_url = server_config.url.clone();
+            self.handle_login_clusters(cluster.clone(), None, false, None, None)
+                .await;
+            let logged_in = self
+                .config
+                .get_server_config_by_url(Some(server_config.url.clone()))
+                .is_ok_and(|config| config.clusters.contains_key(&selected));
+            if !logged_in {
+                error!(
+                    "Login to cluster {} failed, context not switched.",
+                    selected
+                );
+                return;
+            }
+        }
+
+        let server = proxy_cluster_url(&server_config.url, &ns, &cluster);
+        let existing = self.kubeconfig.contexts.iter().find(|context| {
+            context.context.as_ref().is_some_and(|context| {
+                self.kubeconfig.clusters.iter().any(|c| {
+                    c.name == context.cluster
+                        && c.cluster
+                            .as_ref()
+                            .is_some_and(|c| c.server.as_deref() == Some(server.as_str()))
+                })
+            })
+        });
+        let context_name = match existing {
+            Some(context) => context.name.clone(),
+            None => {
+                let name = sync_entry_name(
+                    &CliServerConfig::url_to_name_from_string(server_config.url.clone()),
+                    &ns,
+                    &cluster,
+                );
+                self.upsert_sync_entry(
+                    &name,
+                    &server,
+                    exec_config(&server_config.url, &ns, &cluster),
+                );
+                name
+            }
+        };
+        self.kubeconfig.current_context = Some(context_name.clone());
+        if let Err(e) = self.write_kubeconfig() {
+            error!("Failed to write kubeconfig: {}", e);
+            return;
+        }
+        info!("Switched to context {}.", context_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(keys: &[&str]) -> Vec<PickerEntry> {
+        keys.iter()
+            .map(|key| {
+                let (ns, name) = key.split_once('/').unwrap();
+                PickerEntry {
+                    cluster: VisibleCluster::new(true, name.to_string(), ns.to_string(), true),
+                    logged_in: false,
+                }
+            })
+            .collect()
+    }
+
+    fn keys(matches: Vec<&PickerEntry>) -> Vec<String> {
+        matches.into_iter().map(PickerEntry::key).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "dev/c1"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "dev/c1"), None);
+        // The query must be a subsequence, in order
+        assert_eq!(fuzzy_score("1c", "dev/c1"), None);
+        assert_eq!(fuzzy_score("DEV", "dev/c1"), fuzzy_score("dev", "dev/c1"));
+        assert_eq!(fuzzy_score("d c1", "dev/c1"), fuzzy_score("dc1", "dev/c1"));
+        // Consecutive characters after a separator score higher than scattered ones
+        assert!(fuzzy_score("c1", "dev/c1") > fuzzy_score("c1", "dev/cache-1"));
+        assert!(fuzzy_score("web", "dev/web") > fuzzy_score("web", "dev/newebsite"));
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = entries(&["prod/c1", "dev/cache-1", "dev/c1", "staging/web"]);
+        assert_eq!(
+            keys(filter_entries(&entries, "c1")),
+            vec!["dev/c1", "prod/c1", "dev/cache-1"]
+        );
+        assert_eq!(keys(filter_entries(&entries, "stw")), vec!["staging/web"]);
+        assert!(filter_entries(&entries, "missing").is_empty());
+        // Without query, every entry is listed by key
+        assert_eq!(
+            keys(filter_entries(&entries, "")),
+            vec!["dev/c1", "dev/cache-1", "prod/c1", "staging/web"]
+        );
+    }
+}
@@ -0,0 +1,606 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex, PoisonError},
+};
+
+use http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use http_body::Body as _;
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use k8s_openapi::jiff::Timestamp;
+use kube::config::{Cluster, Context, Kubeconfig, NamedCluster, NamedContext};
+use tokio::{net::TcpListener, sync::Mutex};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    cli_config::{cli_cluster_config::StepUp, cli_server_config::CliServerConfig, CliConfig},
+    context::sync::{proxy_cluster_url, sync_entry_name},
+    ctx::CliCtx,
+    error::ProxyAuthK8sError,
+    login::get_token::REFRESH_MARGIN,
+    output::render_value,
+};
+
+/// Headers of a single hop, never forwarded as is
+/// `connection` and `upgrade` are put back on upgrade requests, such as exec and port-forward
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Token injected in the requests, with its expiration if known
+#[derive(Clone, Debug, Default, PartialEq)]
+struct CachedToken {
+    token: String,
+    expires_at: Option<i64>,
+}
+
+impl CachedToken {
+    /// Within the refresh margin, the token is renewed
+    fn is_expiring(&self, now: i64) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - REFRESH_MARGIN <= now)
+    }
+
+    /// Refused by the cluster, a request has to wait for the renewal
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Cluster token served by the proxy, refreshed through the server before it expires
+/// Only the entry of the cluster is written back, other processes may change the config file meanwhile
+struct ProxyToken {
+    server_config: CliServerConfig,
+    config_path: PathBuf,
+    server_name: String,
+    namespace: String,
+    cluster: String,
+    current: CachedToken,
+}
+
+impl ProxyToken {
+    fn cluster_key(&self) -> String {
+        format!("{}/{}", self.namespace, self.cluster)
+    }
+
+    fn load(&mut self) -> Result<(), ProxyAuthK8sError> {
+        let token = self
+            .server_config
+            .get_cluster_token(self.namespace.clone(), self.cluster.clone())?;
+        let expires_at = self
+            .server_config
+            .get_clusters_from_name_ns(self.cluster.clone(), Some(self.namespace.clone()))
+            .and_then(|cluster_config| cluster_config.expires_at);
+        self.current = CachedToken { token, expires_at };
+        Ok(())
+    }
+
+    /// Take the entry of the cluster from the config file, another process may have renewed it
+    fn reload(&mut self) {
+        let config = match CliConfig::read_from_file(self.config_path.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                debug!("Keeping the loaded cluster entry: {}", e);
+                return;
+            }
+        };
+        let key = self.cluster_key();
+        match config
+            .servers
+            .get(&self.server_name)
+            .and_then(|server_config| server_config.clusters.get(&key))
+        {
+            Some(cluster_config) => {
+                self.server_config
+                    .clusters
+                    .insert(key, cluster_config.clone());
+            }
+            None => debug!("Cluster {} is no longer in the config file", key),
+        }
+    }
+
+    /// Write the entry of the cluster into the config file as it is on disk now
+    fn save(&self) {
+        let result = CliConfig::read_from_file(self.config_path.clone()).and_then(|mut config| {
+            let (Some(server_config), Some(cluster_config)) = (
+                config.servers.get_mut(&self.server_name),
+                self.server_config.clusters.get(&self.cluster_key()),
+            ) else {
+                debug!(
+                    "Server {} is no longer in the config file",
+                    self.server_name
+                );
+                return Ok(());
+            };
+            server_config
+                .clusters
+                .insert(self.cluster_key(), cluster_config.clone());
+            config.write_to_file(self.config_path.clone()).map(|_| ())
+        });
+        if let Err(e) = result {
+            error!("Failed to update config file: {}", e);
+        }
+    }
+
+    /// Record the step-up authentication asked by the cluster, requested by the next browser login
+    fn record_step_up(&mut self, step_up: StepUp) {
+        warn!(
+            "Cluster '{}/{}' asks for a step-up authentication, please login using 'login {}{}'.",
+            self.namespace,
+            self.cluster,
+            self.cluster,
+            step_up.login_flags()
+        );
+        self.reload();
+        self.server_config.record_cluster_step_up_challenge(
+            &self.namespace,
+            &self.cluster,
+            step_up,
+        );
+        self.save();
+    }
+
+    /// Renew the token when it is about to expire and return it
+    /// A failed renewal keeps the current token, the cluster answers 401 once it expired
+    async fn renew(&mut self) -> CachedToken {
+        if self.current.is_expiring(Timestamp::now().as_second()) {
+            // Another process may already have renewed it
+            self.reload();
+            if let Err(e) = self.load() {
+                debug!("Keeping the current token: {}", e);
+            }
+        }
+        if self.current.is_expiring(Timestamp::now().as_second()) {
+            debug!(
+                "Token of cluster '{}/{}' is about to expire, refreshing it.",
+                self.namespace, self.cluster
+            );
+            let (ns, cluster) = (self.namespace.clone(), self.cluster.clone());
+            match self
+                .server_config
+                .refresh_cluster_token(&ns, &cluster)
+                .await
+            {
+                Ok(()) => {
+                    self.save();
+                    match self.load() {
+                        Ok(()) => info!("Token of cluster '{}/{}' renewed.", ns, cluster),
+                        Err(e) => error!("Failed to read the renewed token: {}", e),
+                    }
+                }
+                Err(e) => {
+                    warn!("{}. Please login using 'login {}'.", e, cluster);
+                    // Retry on the next request instead of the expiration
+                    self.current.expires_at = None;
+                }
+            }
+        }
+        self.current.clone()
+    }
+}
+
+/// Hosts the proxy answers to, as `kubectl proxy --accept-hosts` by default
+/// Any other name may be a DNS rebinding from a web page
+const ACCEPTED_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// Whether the request comes from a local client, not from a page loaded in a browser
+/// The Host must be a loopback name and a browser Origin is refused
+fn is_local_request(headers: &HeaderMap) -> bool {
+    let host_accepted = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map(|host| match host.rsplit_once(':') {
+            // Keep the brackets of an IPv6 address without port
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        })
+        .is_some_and(|host| {
+            ACCEPTED_HOSTS
+                .iter()
+                .any(|accepted| accepted.eq_ignore_ascii_case(host))
+        });
+    host_accepted && !headers.contains_key(header::ORIGIN)
+}
+
+struct ProxyState {
+    upstream: String,
+    client: reqwest::Client,
+    /// Token injected in the requests, its lock is never held across a renewal
+    token: StdMutex<CachedToken>,
+    /// Held by the single request renewing the token
+    renewal: Mutex<ProxyToken>,
+}
+
+impl ProxyState {
+    fn cached_token(&self) -> CachedToken {
+        self.token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Token to inject, renewed first when it is about to expire
+    /// While a request renews it, the others keep the current token unless it already expired
+    async fn current_token(&self) -> String {
+        let now = Timestamp::now().as_second();
+        let cached = self.cached_token();
+        if !cached.is_expiring(now) {
+            return cached.token;
+        }
+        let mut renewal = if cached.is_expired(now) {
+            self.renewal.lock().await
+        } else {
+            match self.renewal.try_lock() {
+                Ok(renewal) => renewal,
+                Err(_) => return cached.token,
+            }
+        };
+        // Renewed by the request holding the lock before
+        let cached = self.cached_token();
+        if !cached.is_expiring(now) {
+            return cached.token;
+        }
+        let renewed = renewal.renew().await;
+        *self.token.lock().unwrap_or_else(PoisonError::into_inner) = renewed.clone();
+        renewed.token
+    }
+}
+
+fn is_upgrade(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::UPGRADE)
+}
+
+/// Copy the end to end headers, and the upgrade ones when the connection is upgraded
+fn forwarded_headers(headers: &HeaderMap, upgrade: bool) -> HeaderMap {
+    let mut forwarded = HeaderMap::new();
+    for (name, value) in headers {
+        let hop_by_hop = HOP_BY_HOP_HEADERS.contains(&name.as_str());
+        if !hop_by_hop || (upgrade && (name == header::CONNECTION || name == header::UPGRADE)) {
+            forwarded.append(name, value.clone());
+        }
+    }
+    forwarded
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<reqwest::Body> {
+    let mut response = Response::new(reqwest::Body::from(message));
+    *response.status_mut() = status;
+    response
+}
+
+async fn forward(
+    state: Arc<ProxyState>,
+    mut req: Request<Incoming>,
+) -> Result<Response<reqwest::Body>, hyper::Error> {
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let url = format!("{}{}", state.upstream, path);
+    if !is_local_request(req.headers()) {
+        warn!(
+            "Refused {} {} from host {:?} with origin {:?}",
+            req.method(),
+            path,
+            req.headers().get(header::HOST),
+            req.headers().get(header::ORIGIN)
+        );
+        return Ok(error_response(
+            StatusCode::FORBIDDEN,
+            "Only local clients are accepted, with a localhost Host and without Origin".to_string(),
+        ));
+    }
+    let upgrade = is_upgrade(req.headers());
+    debug!("Forwarding {} {}", req.method(), url);
+
+    let mut headers = forwarded_headers(req.headers(), upgrade);
+    headers.remove(header::HOST);
+    let token = state.current_token().await;
+    match HeaderValue::from_str(&format!("Bearer {}", token)) {
+        Ok(value) => {
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        Err(e) => {
+            return Ok(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Invalid cluster token: {}", e),
+            ))
+        }
+    }
+
+    let downstream_upgrade = upgrade.then(|| hyper::upgrade::on(&mut req));
+    let method = req.method().clone();
+    let body = req.into_body();
+    let mut upstream_req = state.client.request(method, &url).headers(headers);
+    if !body.is_end_stream() {
+        upstream_req = upstream_req.body(reqwest::Body::wrap(body));
+    }
+    let upstream_res = match upstream_req.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            error!("Failed to reach {}: {}", url, e);
+            return Ok(error_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to reach the cluster: {}", e),
+            ));
+        }
+    };
+
+    let status = upstream_res.status();
+    if status == StatusCode::UNAUTHORIZED {
+        if let Some(step_up) = upstream_res
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|challenge| challenge.to_str().ok())
+            .and_then(StepUp::from_challenge)
+        {
+            state.renewal.lock().await.record_step_up(step_up);
+        }
+    }
+    let response_headers = forwarded_headers(
+        upstream_res.headers(),
+        status == StatusCode::SWITCHING_PROTOCOLS,
+    );
+    if status != StatusCode::SWITCHING_PROTOCOLS {
+        let mut response: Response<reqwest::Body> = upstream_res.into();
+        *response.headers_mut() = response_headers;
+        return Ok(response);
+    }
+
+    // Both connections are handed over to a raw copy once the cluster switched protocols
+    let Some(downstream_upgrade) = downstream_upgrade else {
+        return Ok(error_response(
+            StatusCode::BAD_GATEWAY,
+            "The cluster switched protocols without an upgrade request".to_string(),
+        ));
+    };
+    tokio::spawn(async move {
+        let upstream = match upstream_res.upgrade().await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                error!("Failed to upgrade the cluster connection: {}", e);
+                return;
+            }
+        };
+        let downstream = match downstream_upgrade.await {
+            Ok(downstream) => downstream,
+            Err(e) => {
+                error!("Failed to upgrade the local connection: {}", e);
+                return;
+            }
+        };
+        let (mut upstream, mut downstream) = (upstream, TokioIo::new(downstream));
+        if let Err(e) = tokio::io::copy_bidirectional(&mut downstream, &mut upstream).await {
+            debug!("Upgraded connection closed: {}", e);
+        }
+    });
+    let mut response = Response::new(reqwest::Body::from(Vec::new()));
+    *response.status_mut() = status;
+    *response.headers_mut() = response_headers;
+    Ok(response)
+}
+
+/// Kubeconfig entries pointing kubectl to the local proxy, without any credential
+pub fn proxy_kubeconfig(name: &str, addr: &SocketAddr) -> Kubeconfig {
+    Kubeconfig {
+        clusters: vec![NamedCluster {
+            name: name.to_string(),
+            cluster: Some(Cluster {
+                server: Some(format!("http://{}", addr)),
+                ..Default::default()
+            }),
+        }],
+        contexts: vec![NamedContext {
+            name: name.to_string(),
+            context: Some(Context {
+                cluster: name.to_string(),
+                ..Default::default()
+            }),
+        }],
+        current_context: Some(name.to_string()),
+        api_version: Some("v1".to_string()),
+        kind: Some("Config".to_string()),
+        ..Default::default()
+    }
+}
+
+impl CliCtx {
+    /// Serve the cluster on localhost, for the clients that can't run the exec credential plugin
+    /// The cluster token is injected in each request, upgrades such as exec and port-forward are relayed
+    pub async fn handle_proxy(&mut self, cluster: String, port: u16) {
+        let server_config =
+            match self
+                .config
+                .get_server_config_by_url(if self.server_url.is_empty() {
+                    None
+                } else {
+                    Some(self.server_url.clone())
+                }) {
+                Ok(config) => config.clone(),
+                Err(e) => {
+                    error!(
+                        "Error retrieving server configuration, please login to server first: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+        let namespace = if self.namespace.is_empty() {
+            server_config.namespace.clone()
+        } else {
+            self.namespace.clone()
+        };
+        let server_name = CliServerConfig::url_to_name_from_string(server_config.url.clone());
+
+        let mut token = ProxyToken {
+            server_config: server_config.clone(),
+            config_path: self.config_path.clone(),
+            server_name: server_name.clone(),
+            namespace: namespace.clone(),
+            cluster: cluster.clone(),
+            current: CachedToken::default(),
+        };
+        if let Err(e) = token.load() {
+            error!(
+                "Failed to retrieve token for cluster '{}/{}': {}. Please login using 'login {}'.",
+                namespace, cluster, e, cluster
+            );
+            return;
+        }
+        // No timeout as watches stay open, upgrades are only negotiated over HTTP/1.1
+        let client = match reqwest::Client::builder()
+            .http1_only()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Failed to build the HTTP client: {}", e);
+                return;
+            }
+        };
+        let state = Arc::new(ProxyState {
+            upstream: proxy_cluster_url(&server_config.url, &namespace, &cluster),
+            client,
+            token: StdMutex::new(token.current.clone()),
+            renewal: Mutex::new(token),
+        });
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", addr, e);
+                return;
+            }
+        };
+        let kubeconfig = proxy_kubeconfig(
+            &sync_entry_name(&format!("local-{}", port), &namespace, &cluster),
+            &addr,
+        );
+        println!("{}", render_value(&kubeconfig, &self.format));
+        info!(
+            "Serving cluster {}/{} of {} on http://{}, press Ctrl+C to stop.",
+            namespace, cluster, server_name, addr
+        );
+
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            debug!("Connection from {}", peer);
+            let state = state.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| forward(state.clone(), req));
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .with_upgrades()
+                    .await
+                {
+                    debug!("Connection from {} closed: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(host: Option<&str>, origin: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(host) = host {
+            headers.insert(header::HOST, HeaderValue::from_str(host).unwrap());
+        }
+        if let Some(origin) = origin {
+            headers.insert(header::ORIGIN, HeaderValue::from_str(origin).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_is_local_request() {
+        assert!(is_local_request(&headers(Some("127.0.0.1:8001"), None)));
+        assert!(is_local_request(&headers(Some("localhost:8001"), None)));
+        assert!(is_local_request(&headers(Some("[::1]:8001"), None)));
+        assert!(is_local_request(&headers(Some("[::1]"), None)));
+        assert!(!is_local_request(&headers(Some("evil.example:8001"), None)));
+        assert!(!is_local_request(&headers(None, None)));
+        assert!(!is_local_request(&headers(
+            Some("127.0.0.1:8001"),
+            Some("http://127.0.0.1:8001")
+        )));
+    }
+
+    fn state(cached: CachedToken) -> Arc<ProxyState> {
+        Arc::new(ProxyState {
+            upstream: "https://proxyauth.example.com/clusters/dev/c1".to_string(),
+            client: reqwest::Client::new(),
+            token: StdMutex::new(cached.clone()),
+            renewal: Mutex::new(ProxyToken {
+                server_config: CliServerConfig::new("https://proxyauth.example.com".to_string()),
+                config_path: PathBuf::from("proxyauth_config.yaml"),
+                server_name: "proxyauth-example-com".to_string(),
+                namespace: "dev".to_string(),
+                cluster: "c1".to_string(),
+                current: cached,
+            }),
+        })
+    }
+
+    fn cached(token: &str, expires_at: i64) -> CachedToken {
+        CachedToken {
+            token: token.to_string(),
+            expires_at: Some(expires_at),
+        }
+    }
+
+    #[test]
+    fn test_cached_token_expiry() {
+        let token = cached("t1", 1_000);
+        assert!(!token.is_expiring(1_000 - REFRESH_MARGIN - 1));
+        assert!(token.is_expiring(1_000 - REFRESH_MARGIN));
+        assert!(!token.is_expired(999));
+        assert!(token.is_expired(1_000));
+        assert!(!CachedToken::default().is_expiring(i64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_current_token_during_renewal() {
+        let now = Timestamp::now().as_second();
+        let state = state(cached("t1", now + 3600));
+        // A renewal in progress holds the lock
+        let renewal = state.renewal.lock().await;
+        assert_eq!(state.current_token().await, "t1");
+
+        // About to expire but still valid, the current token is served without waiting
+        *state.token.lock().unwrap() = cached("t2", now + REFRESH_MARGIN / 2);
+        assert_eq!(state.current_token().await, "t2");
+
+        // Expired, the request waits for the renewal and takes the renewed token
+        *state.token.lock().unwrap() = cached("t3", now - 1);
+        let waiting = tokio::spawn({
+            let state = state.clone();
+            async move { state.current_token().await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        *state.token.lock().unwrap() = cached("t4", now + 3600);
+        drop(renewal);
+        assert_eq!(waiting.await.unwrap(), "t4");
+    }
+}
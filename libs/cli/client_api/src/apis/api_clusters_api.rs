@@ -22,16 +22,23 @@ pub enum GetAllVisibleClusterError {
     UnknownValue(serde_json::Value),
 }
 
-/// Get all cluster visible to the user, if the user is not authenticated return 401, if none return an empty array.
+/// Get all cluster visible to the user, if the user is not authenticated return 401, if none return an empty array. The response carries an ETag, a request sending it back in If-None-Match gets a 304 while the visible clusters are unchanged.
 pub async fn get_all_visible_cluster(
     configuration: &configuration::Configuration,
+    if_none_match: Option<&str>,
 ) -> Result<models::GetAllVisibleClusterBody, Error<GetAllVisibleClusterError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_header_if_none_match = if_none_match;
+
     let uri_str = format!("{}/api/v1/clusters", configuration.base_path);
     let mut req_builder = configuration.client.request(reqwest::Method::GET, &uri_str);
 
     if let Some(ref user_agent) = configuration.user_agent {
         req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
     }
+    if let Some(param_value) = p_header_if_none_match {
+        req_builder = req_builder.header("If-None-Match", param_value.to_string());
+    }
     if let Some(ref token) = configuration.bearer_access_token {
         req_builder = req_builder.bearer_auth(token.to_owned());
     };
@@ -0,0 +1,180 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use super::{configuration, ContentType, Error};
+use crate::{apis::ResponseContent, models};
+use reqwest;
+use serde::{de::Error as _, Deserialize, Serialize};
+
+/// struct for typed errors of method [`create_api_token`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CreateApiTokenError {
+    Status400(),
+    Status401(),
+    Status403(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`list_api_tokens`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ListApiTokensError {
+    Status401(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`revoke_api_token`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RevokeApiTokenError {
+    Status401(),
+    Status403(),
+    Status404(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
+/// Create an access token for automation, owned by the authenticated user. The token is only returned once. An API token can't be used to create another token. The token expires after at most the maximum lifetime set on the server, and only reaches the clusters visible to the user at its creation. API tokens are only accepted by the clusters with `auth_config.issued_token`, the proxy presents a token minted by ProxyAuthK8S for them.
+pub async fn create_api_token(
+    configuration: &configuration::Configuration,
+    create_api_token_body: models::CreateApiTokenBody,
+) -> Result<models::CreatedApiTokenBody, Error<CreateApiTokenError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_body_create_api_token_body = create_api_token_body;
+
+    let uri_str = format!("{}/api/v1/tokens", configuration.base_path);
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+    req_builder = req_builder.json(&p_body_create_api_token_body);
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::CreatedApiTokenBody`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::CreatedApiTokenBody`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<CreateApiTokenError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// List the non expired API tokens owned by the authenticated user, without their secret.
+pub async fn list_api_tokens(
+    configuration: &configuration::Configuration,
+) -> Result<models::ListApiTokenBody, Error<ListApiTokensError>> {
+    let uri_str = format!("{}/api/v1/tokens", configuration.base_path);
+    let mut req_builder = configuration.client.request(reqwest::Method::GET, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::ListApiTokenBody`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::ListApiTokenBody`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<ListApiTokensError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// Revoke an API token owned by the authenticated user, if the token does not exist or is not owned by the user return 404.
+pub async fn revoke_api_token(
+    configuration: &configuration::Configuration,
+    id: &str,
+) -> Result<(), Error<RevokeApiTokenError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_id = id;
+
+    let uri_str = format!(
+        "{}/api/v1/tokens/{id}",
+        configuration.base_path,
+        id = crate::apis::urlencode(p_path_id)
+    );
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::DELETE, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+
+    if !status.is_client_error() && !status.is_server_error() {
+        Ok(())
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<RevokeApiTokenError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
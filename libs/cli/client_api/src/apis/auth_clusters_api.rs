@@ -31,6 +31,61 @@ pub enum ClusterLoginError {
     UnknownValue(serde_json::Value),
 }
 
+/// struct for typed errors of method [`cluster_oidc_configuration`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ClusterOidcConfigurationError {
+    Status401(),
+    Status404(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`device_authorization`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceAuthorizationError {
+    Status401(),
+    Status404(),
+    Status500(),
+    Status501(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`device_token`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceTokenError {
+    Status400(models::DeviceTokenErrorModel),
+    Status401(),
+    Status404(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`logout`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LogoutError {
+    Status400(),
+    Status401(),
+    Status404(),
+    Status500(),
+    Status501(),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`refresh_login`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RefreshLoginError {
+    Status400(),
+    Status401(),
+    Status404(),
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
 /// If the cluster is not found or disabled, return 404.
 pub async fn callback_login(
     configuration: &configuration::Configuration,
@@ -115,12 +170,16 @@ pub async fn cluster_login(
     cluster: &str,
     x_front_callback: Option<&str>,
     x_kubectl_callback: Option<&str>,
+    acr_values: Option<&str>,
+    max_age: Option<i64>,
 ) -> Result<String, Error<ClusterLoginError>> {
     // add a prefix to parameters to efficiently prevent name collisions
     let p_path_ns = ns;
     let p_path_cluster = cluster;
     let p_header_x_front_callback = x_front_callback;
     let p_header_x_kubectl_callback = x_kubectl_callback;
+    let p_query_acr_values = acr_values;
+    let p_query_max_age = max_age;
 
     let uri_str = format!(
         "{}/clusters/{ns}/{cluster}/auth/login",
@@ -130,6 +189,13 @@ pub async fn cluster_login(
     );
     let mut req_builder = configuration.client.request(reqwest::Method::GET, &uri_str);
 
+    if let Some(ref param_value) = p_query_acr_values {
+        req_builder = req_builder.query(&[("acr_values", &param_value.to_string())]);
+    }
+    if let Some(ref param_value) = p_query_max_age {
+        req_builder = req_builder.query(&[("max_age", &param_value.to_string())]);
+    }
+
     if let Some(ref user_agent) = configuration.user_agent {
         req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
     }
@@ -181,3 +247,279 @@ pub async fn cluster_login(
         }))
     }
 }
+
+/// Used by confidential clients, such as CI runners, requesting their tokens with the client credentials grant, and to explain why the cluster rejected a token. The secret of the cluster stays on the server. If the cluster is not found, disabled, not visible to the user or without OIDC provider, return 404.
+pub async fn cluster_oidc_configuration(
+    configuration: &configuration::Configuration,
+    ns: &str,
+    cluster: &str,
+) -> Result<models::ClusterOidcConfiguration, Error<ClusterOidcConfigurationError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_ns = ns;
+    let p_path_cluster = cluster;
+
+    let uri_str = format!(
+        "{}/clusters/{ns}/{cluster}/auth/oidc",
+        configuration.base_path,
+        ns = crate::apis::urlencode(p_path_ns),
+        cluster = crate::apis::urlencode(p_path_cluster)
+    );
+    let mut req_builder = configuration.client.request(reqwest::Method::GET, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::ClusterOidcConfiguration`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::ClusterOidcConfiguration`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<ClusterOidcConfigurationError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// Used by headless clients which can't receive a redirect (RFC 8628). If the cluster is not found, disabled or without OIDC provider, return 404.
+pub async fn device_authorization(
+    configuration: &configuration::Configuration,
+    ns: &str,
+    cluster: &str,
+) -> Result<models::DeviceAuthorizationModel, Error<DeviceAuthorizationError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_ns = ns;
+    let p_path_cluster = cluster;
+
+    let uri_str = format!(
+        "{}/clusters/{ns}/{cluster}/auth/device",
+        configuration.base_path,
+        ns = crate::apis::urlencode(p_path_ns),
+        cluster = crate::apis::urlencode(p_path_cluster)
+    );
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::DeviceAuthorizationModel`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::DeviceAuthorizationModel`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<DeviceAuthorizationError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// Return the tokens once the user approved the device on the OIDC provider. While the authorization is pending, return 400 with the RFC 8628 error and the interval to wait.
+pub async fn device_token(
+    configuration: &configuration::Configuration,
+    ns: &str,
+    cluster: &str,
+    device_token_body: models::DeviceTokenBody,
+) -> Result<models::CallbackModel, Error<DeviceTokenError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_ns = ns;
+    let p_path_cluster = cluster;
+    let p_body_device_token_body = device_token_body;
+
+    let uri_str = format!(
+        "{}/clusters/{ns}/{cluster}/auth/device/token",
+        configuration.base_path,
+        ns = crate::apis::urlencode(p_path_ns),
+        cluster = crate::apis::urlencode(p_path_cluster)
+    );
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+    req_builder = req_builder.json(&p_body_device_token_body);
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::CallbackModel`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::CallbackModel`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<DeviceTokenError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// Revoke the token with the cluster's OIDC provider (RFC 7009), the client secret of the cluster stays on the server. Only the users allowed on the cluster can revoke its tokens. Return 501 if the OIDC provider has no revocation endpoint, the token then stays valid until it expires.
+pub async fn logout(
+    configuration: &configuration::Configuration,
+    ns: &str,
+    cluster: &str,
+    logout_body: models::LogoutBody,
+) -> Result<(), Error<LogoutError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_ns = ns;
+    let p_path_cluster = cluster;
+    let p_body_logout_body = logout_body;
+
+    let uri_str = format!(
+        "{}/clusters/{ns}/{cluster}/auth/logout",
+        configuration.base_path,
+        ns = crate::apis::urlencode(p_path_ns),
+        cluster = crate::apis::urlencode(p_path_cluster)
+    );
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+    req_builder = req_builder.json(&p_body_logout_body);
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+
+    if !status.is_client_error() && !status.is_server_error() {
+        Ok(())
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<LogoutError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
+/// Exchange the refresh token with the cluster's OIDC provider, the client secret of the cluster stays on the server. The refresh token is returned unchanged if the OIDC provider doesn't rotate it.
+pub async fn refresh_login(
+    configuration: &configuration::Configuration,
+    ns: &str,
+    cluster: &str,
+    refresh_body: models::RefreshBody,
+) -> Result<models::CallbackModel, Error<RefreshLoginError>> {
+    // add a prefix to parameters to efficiently prevent name collisions
+    let p_path_ns = ns;
+    let p_path_cluster = cluster;
+    let p_body_refresh_body = refresh_body;
+
+    let uri_str = format!(
+        "{}/clusters/{ns}/{cluster}/auth/refresh",
+        configuration.base_path,
+        ns = crate::apis::urlencode(p_path_ns),
+        cluster = crate::apis::urlencode(p_path_cluster)
+    );
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref token) = configuration.bearer_access_token {
+        req_builder = req_builder.bearer_auth(token.to_owned());
+    };
+    req_builder = req_builder.json(&p_body_refresh_body);
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::CallbackModel`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::CallbackModel`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<RefreshLoginError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
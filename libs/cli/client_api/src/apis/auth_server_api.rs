@@ -0,0 +1,61 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use super::{configuration, ContentType, Error};
+use crate::{apis::ResponseContent, models};
+use reqwest;
+use serde::{de::Error as _, Deserialize, Serialize};
+
+/// struct for typed errors of method [`cli_oidc_configuration`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CliOidcConfigurationError {
+    UnknownValue(serde_json::Value),
+}
+
+/// The tokens obtained with it are accepted by the API of ProxyAuthK8S.
+pub async fn cli_oidc_configuration(
+    configuration: &configuration::Configuration,
+) -> Result<models::CliOidcConfiguration, Error<CliOidcConfigurationError>> {
+    let uri_str = format!("{}/management/oidc", configuration.base_path);
+    let mut req_builder = configuration.client.request(reqwest::Method::GET, &uri_str);
+
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::CliOidcConfiguration`"))),
+            ContentType::Unsupported(unknown_type) => Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::CliOidcConfiguration`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<CliOidcConfigurationError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
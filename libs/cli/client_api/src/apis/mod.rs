@@ -114,8 +114,11 @@ impl From<&str> for ContentType {
 }
 
 pub mod api_clusters_api;
+pub mod api_tokens_api;
 pub mod auth_clusters_api;
+pub mod auth_server_api;
 pub mod health_api;
 pub mod proxy_clusters_api;
+pub mod well_known_api;
 
 pub mod configuration;
@@ -0,0 +1,56 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// ApiTokenInfo : Model representing an API token, without its secret.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiTokenInfo {
+    #[serde(rename = "clusters")]
+    pub clusters: Vec<String>,
+    /// Creation date, unix timestamp in seconds.
+    #[serde(rename = "created_at")]
+    pub created_at: i64,
+    /// Expiration date, unix timestamp in seconds.
+    #[serde(rename = "expires_at")]
+    pub expires_at: i64,
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "owner")]
+    pub owner: String,
+    #[serde(rename = "read_only")]
+    pub read_only: bool,
+}
+
+impl ApiTokenInfo {
+    /// Model representing an API token, without its secret.
+    pub fn new(
+        clusters: Vec<String>,
+        created_at: i64,
+        expires_at: i64,
+        id: String,
+        name: String,
+        owner: String,
+        read_only: bool,
+    ) -> ApiTokenInfo {
+        ApiTokenInfo {
+            clusters,
+            created_at,
+            expires_at,
+            id,
+            name,
+            owner,
+            read_only,
+        }
+    }
+}
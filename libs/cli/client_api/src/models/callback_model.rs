@@ -18,6 +18,14 @@ pub struct CallbackModel {
     pub access_token: String,
     #[serde(rename = "cluster_url")]
     pub cluster_url: String,
+    /// Lifetime of the access token in seconds, if given by the OIDC provider
+    #[serde(
+        rename = "expires_in",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires_in: Option<Option<i64>>,
     #[serde(rename = "id_token")]
     pub id_token: String,
     #[serde(rename = "refresh_token")]
@@ -38,6 +46,7 @@ impl CallbackModel {
         CallbackModel {
             access_token,
             cluster_url,
+            expires_in: None,
             id_token,
             refresh_token,
             subject,
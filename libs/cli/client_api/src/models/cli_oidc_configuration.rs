@@ -0,0 +1,36 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// CliOidcConfiguration : OIDC configuration used by the kubectl plugin to log in to ProxyAuthK8S.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CliOidcConfiguration {
+    /// Public client the plugin logs in with, using PKCE or the device authorization grant
+    #[serde(rename = "client_id")]
+    pub client_id: String,
+    #[serde(rename = "issuer_url")]
+    pub issuer_url: String,
+    /// Space separated scopes to request
+    #[serde(rename = "scopes")]
+    pub scopes: String,
+}
+
+impl CliOidcConfiguration {
+    /// OIDC configuration used by the kubectl plugin to log in to ProxyAuthK8S.
+    pub fn new(client_id: String, issuer_url: String, scopes: String) -> CliOidcConfiguration {
+        CliOidcConfiguration {
+            client_id,
+            issuer_url,
+            scopes,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// ClusterOidcConfiguration : OIDC provider of a cluster, for the clients requesting their tokens from it directly.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClusterOidcConfiguration {
+    /// Client of the cluster, the audience expected in the tokens of the users
+    #[serde(rename = "client_id")]
+    pub client_id: String,
+    #[serde(rename = "issuer_url")]
+    pub issuer_url: String,
+    /// Space separated scopes to request, on top of the ones of the client
+    #[serde(rename = "scopes")]
+    pub scopes: String,
+}
+
+impl ClusterOidcConfiguration {
+    /// OIDC provider of a cluster, for the clients requesting their tokens from it directly.
+    pub fn new(client_id: String, issuer_url: String, scopes: String) -> ClusterOidcConfiguration {
+        ClusterOidcConfiguration {
+            client_id,
+            issuer_url,
+            scopes,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// CreateApiTokenBody : Body of the request to create an API token.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateApiTokenBody {
+    /// ProxyKubeApi the token can reach, as \"namespace/name\", empty to allow every cluster visible to the owner at creation. Only the clusters with issued_token accept API tokens.
+    #[serde(rename = "clusters", skip_serializing_if = "Option::is_none")]
+    pub clusters: Option<Vec<String>>,
+    /// Lifetime of the token in seconds, at most the maximum lifetime set on the server, 90 days by default.
+    #[serde(rename = "expires_in")]
+    pub expires_in: i64,
+    /// Human readable name of the token.
+    #[serde(rename = "name")]
+    pub name: String,
+    /// Only allow the get, list and watch verbs.
+    #[serde(rename = "read_only", skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+}
+
+impl CreateApiTokenBody {
+    /// Body of the request to create an API token.
+    pub fn new(expires_in: i64, name: String) -> CreateApiTokenBody {
+        CreateApiTokenBody {
+            clusters: None,
+            expires_in,
+            name,
+            read_only: None,
+        }
+    }
+}
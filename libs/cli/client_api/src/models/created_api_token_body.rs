@@ -0,0 +1,31 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// CreatedApiTokenBody : Body of the response of the create_api_token endpoint.  The token is only returned once, it can't be retrieved afterwards.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreatedApiTokenBody {
+    #[serde(rename = "info")]
+    pub info: Box<models::ApiTokenInfo>,
+    #[serde(rename = "token")]
+    pub token: String,
+}
+
+impl CreatedApiTokenBody {
+    /// Body of the response of the create_api_token endpoint.  The token is only returned once, it can't be retrieved afterwards.
+    pub fn new(info: models::ApiTokenInfo, token: String) -> CreatedApiTokenBody {
+        CreatedApiTokenBody {
+            info: Box::new(info),
+            token,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// DeviceAuthorizationModel : Device authorization started for a cluster.  The user has to visit the verification URI and enter the user code, the device is then polled with the device id.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAuthorizationModel {
+    /// Identifier of the device authorization, used to poll the token
+    #[serde(rename = "device_id")]
+    pub device_id: String,
+    /// Seconds before the device authorization expires
+    #[serde(rename = "expires_in")]
+    pub expires_in: i64,
+    /// Minimum seconds to wait between two polls
+    #[serde(rename = "interval")]
+    pub interval: i64,
+    /// Code the user has to enter on the verification page
+    #[serde(rename = "user_code")]
+    pub user_code: String,
+    /// Page of the OIDC provider where the user enters the code
+    #[serde(rename = "verification_uri")]
+    pub verification_uri: String,
+    /// Verification page with the user code already filled, if the OIDC provider supports it
+    #[serde(
+        rename = "verification_uri_complete",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub verification_uri_complete: Option<Option<String>>,
+}
+
+impl DeviceAuthorizationModel {
+    /// Device authorization started for a cluster.  The user has to visit the verification URI and enter the user code, the device is then polled with the device id.
+    pub fn new(
+        device_id: String,
+        expires_in: i64,
+        interval: i64,
+        user_code: String,
+        verification_uri: String,
+    ) -> DeviceAuthorizationModel {
+        DeviceAuthorizationModel {
+            device_id,
+            expires_in,
+            interval,
+            user_code,
+            verification_uri,
+            verification_uri_complete: None,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// DeviceTokenBody : Body of a device token poll
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceTokenBody {
+    /// Identifier returned when the device authorization was started
+    #[serde(rename = "device_id")]
+    pub device_id: String,
+}
+
+impl DeviceTokenBody {
+    /// Body of a device token poll
+    pub fn new(device_id: String) -> DeviceTokenBody {
+        DeviceTokenBody { device_id }
+    }
+}
@@ -0,0 +1,29 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// DeviceTokenErrorModel : Device authorization not completed.  The error follows RFC 8628: authorization_pending and slow_down mean the poll has to be retried, access_denied and expired_token end the device authorization.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceTokenErrorModel {
+    #[serde(rename = "error")]
+    pub error: String,
+    /// Minimum seconds to wait before the next poll
+    #[serde(rename = "interval")]
+    pub interval: i64,
+}
+
+impl DeviceTokenErrorModel {
+    /// Device authorization not completed.  The error follows RFC 8628: authorization_pending and slow_down mean the poll has to be retried, access_denied and expired_token end the device authorization.
+    pub fn new(error: String, interval: i64) -> DeviceTokenErrorModel {
+        DeviceTokenErrorModel { error, interval }
+    }
+}
@@ -0,0 +1,26 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// ListApiTokenBody : Body of the response of the list_api_tokens endpoint.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListApiTokenBody {
+    #[serde(rename = "tokens")]
+    pub tokens: Vec<models::ApiTokenInfo>,
+}
+
+impl ListApiTokenBody {
+    /// Body of the response of the list_api_tokens endpoint.
+    pub fn new(tokens: Vec<models::ApiTokenInfo>) -> ListApiTokenBody {
+        ListApiTokenBody { tokens }
+    }
+}
@@ -0,0 +1,38 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// LogoutBody : Body of a cluster logout
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogoutBody {
+    /// Token to revoke, the refresh token when there is one
+    #[serde(rename = "token")]
+    pub token: String,
+    /// Kind of the token, refresh_token or access_token
+    #[serde(
+        rename = "token_type_hint",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub token_type_hint: Option<Option<String>>,
+}
+
+impl LogoutBody {
+    /// Body of a cluster logout
+    pub fn new(token: String) -> LogoutBody {
+        LogoutBody {
+            token,
+            token_type_hint: None,
+        }
+    }
+}
@@ -1,6 +1,30 @@
+pub mod api_token_info;
+pub use self::api_token_info::ApiTokenInfo;
 pub mod callback_model;
 pub use self::callback_model::CallbackModel;
+pub mod cli_oidc_configuration;
+pub use self::cli_oidc_configuration::CliOidcConfiguration;
+pub mod cluster_oidc_configuration;
+pub use self::cluster_oidc_configuration::ClusterOidcConfiguration;
+pub mod create_api_token_body;
+pub use self::create_api_token_body::CreateApiTokenBody;
+pub mod created_api_token_body;
+pub use self::created_api_token_body::CreatedApiTokenBody;
+pub mod device_authorization_model;
+pub use self::device_authorization_model::DeviceAuthorizationModel;
+pub mod device_token_body;
+pub use self::device_token_body::DeviceTokenBody;
+pub mod device_token_error_model;
+pub use self::device_token_error_model::DeviceTokenErrorModel;
 pub mod get_all_visible_cluster_body;
 pub use self::get_all_visible_cluster_body::GetAllVisibleClusterBody;
+pub mod list_api_token_body;
+pub use self::list_api_token_body::ListApiTokenBody;
+pub mod logout_body;
+pub use self::logout_body::LogoutBody;
+pub mod open_id_configuration;
+pub use self::open_id_configuration::OpenIdConfiguration;
+pub mod refresh_body;
+pub use self::refresh_body::RefreshBody;
 pub mod visible_cluster;
 pub use self::visible_cluster::VisibleCluster;
@@ -0,0 +1,50 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// OpenIdConfiguration : Discovery document of ProxyAuthK8S as an OIDC issuer.  Only the fields needed to verify the tokens minted for the downstream clusters.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    #[serde(rename = "claims_supported")]
+    pub claims_supported: Vec<String>,
+    #[serde(rename = "id_token_signing_alg_values_supported")]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    #[serde(rename = "issuer")]
+    pub issuer: String,
+    #[serde(rename = "jwks_uri")]
+    pub jwks_uri: String,
+    #[serde(rename = "response_types_supported")]
+    pub response_types_supported: Vec<String>,
+    #[serde(rename = "subject_types_supported")]
+    pub subject_types_supported: Vec<String>,
+}
+
+impl OpenIdConfiguration {
+    /// Discovery document of ProxyAuthK8S as an OIDC issuer.  Only the fields needed to verify the tokens minted for the downstream clusters.
+    pub fn new(
+        claims_supported: Vec<String>,
+        id_token_signing_alg_values_supported: Vec<String>,
+        issuer: String,
+        jwks_uri: String,
+        response_types_supported: Vec<String>,
+        subject_types_supported: Vec<String>,
+    ) -> OpenIdConfiguration {
+        OpenIdConfiguration {
+            claims_supported,
+            id_token_signing_alg_values_supported,
+            issuer,
+            jwks_uri,
+            response_types_supported,
+            subject_types_supported,
+        }
+    }
+}
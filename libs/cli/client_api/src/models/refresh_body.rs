@@ -0,0 +1,27 @@
+/*
+ * ProxyAuthK8S
+ *
+ * Reverse Proxy K8s Api.
+ *
+ * The version of the OpenAPI document: 0.1.9
+ * Contact: maxleriche.60@gmail.com
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+/// RefreshBody : Body of a cluster token refresh
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefreshBody {
+    /// Refresh token returned by the login to the cluster
+    #[serde(rename = "refresh_token")]
+    pub refresh_token: String,
+}
+
+impl RefreshBody {
+    /// Body of a cluster token refresh
+    pub fn new(refresh_token: String) -> RefreshBody {
+        RefreshBody { refresh_token }
+    }
+}
@@ -0,0 +1,165 @@
+use actix_web::{delete, get, post, web, web::Data, HttpRequest, HttpResponse, Responder};
+use common::State;
+use tracing::{error, info, instrument};
+
+use crate::{
+    api::api_token_model::{
+        ApiTokenInfo, CreateApiTokenBody, CreatedApiTokenBody, ListApiTokenBody,
+    },
+    model::{
+        api_token::{ApiToken, ApiTokenError},
+        user::User,
+    },
+};
+
+/// Create an API token.
+///
+/// Create an access token for automation, owned by the authenticated user.
+/// The token is only returned once.
+/// An API token can't be used to create another token.
+/// The token expires after at most the maximum lifetime set on the server,
+/// and only reaches the clusters visible to the user at its creation.
+/// API tokens are only accepted by the clusters with `auth_config.issued_token`,
+/// the proxy presents a token minted by ProxyAuthK8S for them.
+#[utoipa::path(
+    tag = "api_tokens",
+    request_body = CreateApiTokenBody,
+    responses(
+        (status = 201, description = "Token created.", body = CreatedApiTokenBody),
+        (status = 400, description = "Invalid request, lifetime too long, cluster not visible or not accepting API tokens, or user without username."),
+        (status = 401, description = "User is not authenticated."),
+        (status = 403, description = "API tokens can't manage tokens."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+)]
+#[post("/tokens")]
+#[instrument(name = "create_api_token", skip(state, body))]
+pub async fn create_api_token(
+    req: HttpRequest,
+    user: User,
+    state: Data<State>,
+    body: web::Json<CreateApiTokenBody>,
+) -> impl Responder {
+    if user.api_token.is_some() {
+        return HttpResponse::Forbidden().body("API tokens can't manage tokens");
+    }
+    let body = body.into_inner();
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Token name can't be empty");
+    }
+    match ApiToken::create(
+        &state,
+        user.username.clone(),
+        user.groups.clone(),
+        body.name,
+        body.expires_in,
+        body.clusters,
+        body.read_only,
+    )
+    .await
+    {
+        Ok((token, plain_token)) => {
+            info!(
+                target: "audit",
+                action = "api_token.create",
+                owner = %token.owner,
+                token_id = %token.id,
+                token_name = %token.name,
+                expires_at = token.expires_at,
+                clusters = ?token.clusters,
+                read_only = token.read_only,
+                "API token created"
+            );
+            HttpResponse::Created().json(CreatedApiTokenBody {
+                token: plain_token,
+                info: ApiTokenInfo::from(token),
+            })
+        }
+        Err(ApiTokenError::Invalid(e)) => HttpResponse::BadRequest().body(e),
+        Err(e) => {
+            error!(error = %e, "couldn't create API token");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// List the API tokens of the user.
+///
+/// List the non expired API tokens owned by the authenticated user, without their secret.
+#[utoipa::path(
+    tag = "api_tokens",
+    responses(
+        (status = 200, description = "Tokens of the user.", body = ListApiTokenBody),
+        (status = 401, description = "User is not authenticated."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+)]
+#[get("/tokens")]
+#[instrument(name = "list_api_tokens", skip(state))]
+pub async fn list_api_tokens(req: HttpRequest, user: User, state: Data<State>) -> impl Responder {
+    match ApiToken::list(&state, &user.username).await {
+        Ok(tokens) => HttpResponse::Ok().json(ListApiTokenBody {
+            tokens: tokens.into_iter().map(ApiTokenInfo::from).collect(),
+        }),
+        Err(e) => {
+            error!(error = %e, "couldn't list API tokens");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Revoke an API token.
+///
+/// Revoke an API token owned by the authenticated user,
+/// if the token does not exist or is not owned by the user return 404.
+#[utoipa::path(
+    tag = "api_tokens",
+    responses(
+        (status = 204, description = "Token revoked."),
+        (status = 401, description = "User is not authenticated."),
+        (status = 403, description = "API tokens can't manage tokens."),
+        (status = 404, description = "Token not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    params(
+        ("id" = String, description = "Identifier of the token."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+)]
+#[delete("/tokens/{id}")]
+#[instrument(name = "revoke_api_token", skip(state))]
+pub async fn revoke_api_token(
+    req: HttpRequest,
+    user: User,
+    state: Data<State>,
+    id: web::Path<String>,
+) -> impl Responder {
+    if user.api_token.is_some() {
+        return HttpResponse::Forbidden().body("API tokens can't manage tokens");
+    }
+    match ApiToken::revoke(&state, &user.username, &id).await {
+        Ok(true) => {
+            info!(
+                target: "audit",
+                action = "api_token.revoke",
+                owner = %user.username,
+                token_id = %id,
+                "API token revoked"
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = %e, "couldn't revoke API token");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
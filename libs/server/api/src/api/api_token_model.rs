@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::api_token::ApiToken;
+
+/// Body of the request to create an API token.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateApiTokenBody {
+    /// Human readable name of the token.
+    pub name: String,
+    /// Lifetime of the token in seconds, at most the maximum lifetime set on the server, 90 days by default.
+    pub expires_in: u64,
+    /// ProxyKubeApi the token can reach, as "namespace/name", empty to allow every cluster visible to the owner at creation.
+    /// Only the clusters with issued_token accept API tokens.
+    #[serde(default)]
+    pub clusters: Vec<String>,
+    /// Only allow the get, list and watch verbs.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Model representing an API token, without its secret.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApiTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub owner: String,
+    /// Creation date, unix timestamp in seconds.
+    pub created_at: i64,
+    /// Expiration date, unix timestamp in seconds.
+    pub expires_at: i64,
+    pub clusters: Vec<String>,
+    pub read_only: bool,
+}
+
+impl From<ApiToken> for ApiTokenInfo {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            owner: token.owner,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            clusters: token.clusters,
+            read_only: token.read_only,
+        }
+    }
+}
+
+/// Body of the response of the create_api_token endpoint.
+///
+/// The token is only returned once, it can't be retrieved afterwards.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreatedApiTokenBody {
+    pub token: String,
+    pub info: ApiTokenInfo,
+}
+
+/// Body of the response of the list_api_tokens endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ListApiTokenBody {
+    pub tokens: Vec<ApiTokenInfo>,
+}
@@ -1,4 +1,10 @@
-use actix_web::{dev::PeerAddr, get, http, web::Data, HttpRequest, HttpResponse, Responder};
+use actix_web::{
+    dev::PeerAddr,
+    get,
+    http::{self, header},
+    web::Data,
+    HttpRequest, HttpResponse, Responder,
+};
 use common::{traits::ObjectRedis, State};
 use crd::ProxyKubeApi;
 use deadpool_redis::redis::AsyncCommands;
@@ -6,6 +12,7 @@ use tracing::instrument;
 
 use crate::{
     api::get_all_visible_cluster_model::{GetAllVisibleClusterBody, VisibleCluster},
+    helper::etag::{etag_of, if_none_match},
     model::user::User,
 };
 
@@ -14,10 +21,16 @@ use crate::{
 /// Get all cluster visible to the user,
 /// if the user is not authenticated return 401,
 /// if none return an empty array.
+/// The response carries an ETag, a request sending it back in If-None-Match gets a 304
+/// while the visible clusters are unchanged.
 #[utoipa::path(
     tag = "api_clusters",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the list the client already has."),
+    ),
     responses(
         (status = 200, description = "Get all visible clusters.", body = GetAllVisibleClusterBody),
+        (status = 304, description = "The visible clusters didn't change since the given ETag."),
         (status = 401, description = "User is not authenticated."),
         (status = 500, description = "Internal server error."),
     ),
@@ -40,12 +53,26 @@ pub async fn get_all_visible_cluster(
     // Get all the values for the keys
     let values: Vec<String> = conn.mget(&keys).await.unwrap_or_default();
     // Parse the values into ProxyKubeApi objects
-    let proxies: Vec<VisibleCluster> = values
+    let mut proxies: Vec<VisibleCluster> = values
         .into_iter()
         .filter_map(|v| ProxyKubeApi::from_json(&v))
         .filter(|kube_api| kube_api.is_user_allowed(&user.groups))
         .map(VisibleCluster::from)
         .collect();
-    let body = GetAllVisibleClusterBody { clusters: proxies };
-    HttpResponse::Ok().json(body)
+    // Redis returns the keys in any order, sorting keeps the ETag stable
+    proxies.sort_by(|a, b| a.namespace.cmp(&b.namespace).then(a.name.cmp(&b.name)));
+    let body = match serde_json::to_vec(&GetAllVisibleClusterBody { clusters: proxies }) {
+        Ok(body) => body,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let etag = etag_of(&body);
+    if if_none_match(&req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish();
+    }
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .content_type(header::ContentType::json())
+        .body(body)
 }
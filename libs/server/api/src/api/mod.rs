@@ -1,2 +1,4 @@
+pub mod api_token;
+pub mod api_token_model;
 pub mod get_all_visible_cluster;
 pub mod get_all_visible_cluster_model;
@@ -37,9 +37,12 @@ impl Modify for SecurityAddons {
       ),
     tags(
             (name = "api_clusters", description = "API endpoints used by the front."),
+            (name = "api_tokens", description = "API tokens issued by ProxyAuthK8S for automation."),
             (name = "auth_clusters", description = "Authentication endpoints for clusters."),
+            (name = "auth_server", description = "Authentication of the kubectl plugin to ProxyAuthK8S."),
             (name = "health", description = "Health check endpoints."),
             (name = "proxy_clusters", description = "Proxy cluster endpoints."),
+            (name = "well_known", description = "OIDC issuer endpoints trusted by the downstream clusters."),
         ),
     servers(
         (url = "/", description = "Current usable server.")
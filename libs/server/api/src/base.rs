@@ -1,5 +1,8 @@
-use actix_web::{get, HttpResponse, Responder};
+use actix_web::{get, web::Data, HttpResponse, Responder};
+use common::State;
+use serde::{Deserialize, Serialize};
 use tracing::{info, instrument};
+use utoipa::ToSchema;
 
 /// Base path just to answer if the server is up and running.
 ///
@@ -17,3 +20,36 @@ pub async fn health() -> impl Responder {
     info!("Health check OK");
     HttpResponse::Ok().finish()
 }
+
+/// OIDC configuration used by the kubectl plugin to log in to ProxyAuthK8S.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CliOidcConfiguration {
+    pub issuer_url: String,
+    /// Public client the plugin logs in with, using PKCE or the device authorization grant
+    pub client_id: String,
+    /// Space separated scopes to request
+    pub scopes: String,
+}
+
+/// OIDC configuration of the server for the kubectl plugin.
+///
+/// The tokens obtained with it are accepted by the API of ProxyAuthK8S.
+#[utoipa::path(
+    tag = "auth_server",
+    responses(
+        (status = 200, description = "OIDC configuration.", body = CliOidcConfiguration),
+    )
+)]
+#[get("/oidc")]
+#[instrument(name = "cli_oidc_configuration", skip(state))]
+pub async fn cli_oidc_configuration(state: Data<State>) -> impl Responder {
+    let conf = &state.oidc_client;
+    HttpResponse::Ok().json(CliOidcConfiguration {
+        issuer_url: conf.issuer_url.clone(),
+        client_id: conf
+            .cli_client_id
+            .clone()
+            .unwrap_or_else(|| conf.client_id.clone()),
+        scopes: conf.scopes.clone(),
+    })
+}
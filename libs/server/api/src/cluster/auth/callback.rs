@@ -7,7 +7,9 @@ use serde::Deserialize;
 use tracing::{error, info, instrument};
 use utoipa::{IntoParams, ToSchema};
 
-use crate::cluster::auth::{auth_model::LoginToCallBackModel, callback_model::CallbackModel};
+use crate::cluster::auth::{
+    auth_model::LoginToCallBackModel, callback_header, callback_model::CallbackModel,
+};
 
 #[derive(Deserialize, ToSchema, IntoParams)]
 pub struct CallbackQuery {
@@ -76,12 +78,8 @@ pub async fn callback_login(
     {
         return HttpResponse::NotFound().finish();
     }
-    let redirect_front = req.headers().contains_key("x-front-callback");
-    let redirect_kubectl = req
-        .headers()
-        .get("x-kubectl-callback")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
+    let redirect_front = callback_header(&req, "x-front-callback").is_some();
+    let redirect_kubectl = callback_header(&req, "x-kubectl-callback");
     let oidc_conf =
         match proxy.get_oidc_conf(data.clone().into_inner(), redirect_front, redirect_kubectl) {
             Some(conf) => conf,
@@ -204,6 +202,9 @@ pub async fn callback_login(
             Some(refresh_token) => refresh_token.secret().to_string(),
             None => "".to_string(),
         },
+        expires_in: token_response
+            .expires_in()
+            .map(|expires_in| expires_in.as_secs()),
         cluster_url: proxy.to_full_path(data.clone().into_inner()),
         subject: claims.subject().to_string(),
     };
@@ -8,6 +8,8 @@ use utoipa::ToSchema;
 pub struct CallbackModel {
     pub access_token: String,
     pub refresh_token: String,
+    /// Lifetime of the access token in seconds, if given by the OIDC provider
+    pub expires_in: Option<u64>,
     pub cluster_url: String,
     pub subject: String,
     pub id_token: String,
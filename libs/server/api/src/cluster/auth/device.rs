@@ -0,0 +1,377 @@
+use std::str::FromStr;
+
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use common::State;
+use deadpool_redis::redis::{AsyncTypedCommands, SetExpiry, SetOptions};
+use openidconnect::{core::CoreIdToken, CsrfToken, Nonce};
+use serde::Deserialize;
+use tracing::{error, info, instrument};
+
+use crate::{
+    cluster::auth::{
+        callback_model::CallbackModel,
+        device_model::{
+            DeviceAuthorizationModel, DeviceSessionModel, DeviceTokenBody, DeviceTokenErrorModel,
+        },
+        discover_endpoint, get_proxy_oidc_conf,
+    },
+    model::user::User,
+};
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Polling interval when the OIDC provider doesn't give one (RFC 8628 section 3.2)
+const DEFAULT_INTERVAL: u64 = 5;
+/// Seconds added to the interval when the OIDC provider asks to slow down
+const SLOW_DOWN_INCREMENT: u64 = 5;
+
+/// Answer of the device authorization endpoint
+#[derive(Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    // Google still uses the draft name
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: Option<u64>,
+}
+
+/// Answer of the token endpoint to a device code poll
+#[derive(Deserialize)]
+struct DeviceTokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    id_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+}
+
+/// Outcome of a poll answered with an error by the token endpoint
+#[derive(Debug, PartialEq)]
+enum PendingPoll {
+    /// The user didn't approve the device yet, poll again at the same interval
+    Pending,
+    /// Poll again at the raised interval, stored with the device authorization
+    SlowDown(u64),
+    /// The authorization was denied or expired, it is removed
+    Ended,
+}
+
+fn pending_poll(error: &str, interval: u64) -> PendingPoll {
+    match error {
+        "authorization_pending" => PendingPoll::Pending,
+        "slow_down" => PendingPoll::SlowDown(interval + SLOW_DOWN_INCREMENT),
+        _ => PendingPoll::Ended,
+    }
+}
+
+fn device_key(ns: &str, cluster: &str, device_id: &str) -> String {
+    format!("oidc_device:{}/{}/{}", ns, cluster, device_id)
+}
+
+/// Start a device authorization with the cluster's OIDC provider
+///
+/// Used by headless clients which can't receive a redirect (RFC 8628).
+/// If the cluster is not found, disabled or without OIDC provider, return 404.
+#[utoipa::path(
+    tag = "auth_clusters",
+    responses(
+        (status = 200, description = "Device authorization started.", body = DeviceAuthorizationModel),
+        (status = 401, description = "User is not authenticated."),
+        (status = 404, description = "Cluster not found or disabled."),
+        (status = 500, description = "Internal server error."),
+        (status = 501, description = "The OIDC provider doesn't support the device authorization grant."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("ns" = String, description = "Namespace containing the cluster."),
+        ("cluster" = String, description = "Cluster name that should exist in the namespace."),
+    )
+)]
+#[post("/{ns}/{cluster}/auth/device")]
+#[instrument(name = "cluster_device_authorization", skip(data))]
+pub async fn device_authorization(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+) -> impl Responder {
+    let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
+    let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
+    let (_, oidc_conf) = match get_proxy_oidc_conf(&data, &ns, &cluster).await {
+        Ok(conf) => conf,
+        Err(response) => return response,
+    };
+    let mut conn = match data.get_redis_conn().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!(error = %e, " couldn't get redis connection");
+            return HttpResponse::ServiceUnavailable().body(e.to_string());
+        }
+    };
+    let device_endpoint = match discover_endpoint(&oidc_conf, "device_authorization_endpoint").await
+    {
+        Ok(Some(endpoint)) => endpoint,
+        Ok(None) => {
+            return HttpResponse::NotImplemented()
+                .body("The OIDC provider doesn't support the device authorization grant")
+        }
+        Err(e) => {
+            error!(error = %e, " couldn't get the OIDC discovery document");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+
+    let http_client = oidc_conf.get_reqwest_client();
+    let scope = format!("openid {}", oidc_conf.scopes);
+    let mut form = vec![("scope", scope.trim())];
+    let mut request = http_client.post(&device_endpoint);
+    match &oidc_conf.client_secret {
+        Some(secret) => request = request.basic_auth(&oidc_conf.client_id, Some(secret)),
+        None => form.push(("client_id", oidc_conf.client_id.as_str())),
+    }
+    let response = match request.form(&form).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            error!(error = %e, " couldn't reach the device authorization endpoint");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        error!(%status, body, " device authorization refused");
+        return HttpResponse::InternalServerError().body(body);
+    }
+    let authorization: DeviceAuthorizationResponse = match response.json().await {
+        Ok(authorization) => authorization,
+        Err(e) => {
+            error!(error = %e, " couldn't parse the device authorization");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+
+    // The device code stays on the server, the client polls with an opaque id bound to the user
+    let device_id = CsrfToken::new_random().secret().to_string();
+    let interval = authorization.interval.unwrap_or(DEFAULT_INTERVAL);
+    let session = DeviceSessionModel {
+        device_code: authorization.device_code,
+        username: user.username.clone(),
+        interval,
+    };
+    if let Err(e) = conn
+        .set_ex(
+            device_key(&ns, &cluster, &device_id),
+            session.to_string(),
+            authorization.expires_in,
+        )
+        .await
+    {
+        error!(error = %e, " couldn't store the device authorization in redis");
+        return HttpResponse::InternalServerError().finish();
+    }
+    info!(
+        "User {} started a device authorization for cluster {}/{}",
+        user.username, ns, cluster
+    );
+    HttpResponse::Ok().json(DeviceAuthorizationModel {
+        device_id,
+        user_code: authorization.user_code,
+        verification_uri: authorization.verification_uri,
+        verification_uri_complete: authorization.verification_uri_complete,
+        expires_in: authorization.expires_in,
+        interval,
+    })
+}
+
+/// Poll the token of a device authorization
+///
+/// Return the tokens once the user approved the device on the OIDC provider.
+/// While the authorization is pending, return 400 with the RFC 8628 error and the interval to wait.
+#[utoipa::path(
+    tag = "auth_clusters",
+    request_body = DeviceTokenBody,
+    responses(
+        (status = 200, description = "Device authorized.", body = CallbackModel),
+        (status = 400, description = "Device authorization pending, denied or expired.", body = DeviceTokenErrorModel),
+        (status = 401, description = "User is not authenticated."),
+        (status = 404, description = "Cluster or device authorization not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("ns" = String, description = "Namespace containing the cluster."),
+        ("cluster" = String, description = "Cluster name that should exist in the namespace."),
+    )
+)]
+#[post("/{ns}/{cluster}/auth/device/token")]
+#[instrument(name = "cluster_device_token", skip(data, body))]
+pub async fn device_token(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+    body: web::Json<DeviceTokenBody>,
+) -> impl Responder {
+    let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
+    let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
+    let (proxy, oidc_conf) = match get_proxy_oidc_conf(&data, &ns, &cluster).await {
+        Ok(conf) => conf,
+        Err(response) => return response,
+    };
+    let mut conn = match data.get_redis_conn().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!(error = %e, " couldn't get redis connection");
+            return HttpResponse::ServiceUnavailable().body(e.to_string());
+        }
+    };
+    let key = device_key(&ns, &cluster, &body.device_id);
+    let mut session = match conn.get(&key).await {
+        Ok(Some(session)) => match DeviceSessionModel::from_string(&session) {
+            Some(session) => session,
+            None => {
+                error!("Couldn't parse the device authorization");
+                return HttpResponse::InternalServerError().finish();
+            }
+        },
+        Ok(None) => {
+            return HttpResponse::NotFound().body("Unknown or expired device authorization")
+        }
+        Err(e) => {
+            error!(error = %e, " couldn't get the device authorization");
+            return HttpResponse::ServiceUnavailable().body(e.to_string());
+        }
+    };
+    // Only the user who started the device authorization can get its tokens
+    if session.username != user.username {
+        return HttpResponse::NotFound().body("Unknown or expired device authorization");
+    }
+
+    let client_oidc = match oidc_conf.get_oidc_core().await {
+        Ok(client) => client,
+        Err(e) => {
+            error!(error = %e, " couldn't get oidc client");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    let Some(token_uri) = client_oidc.token_uri() else {
+        error!("The OIDC provider has no token endpoint");
+        return HttpResponse::InternalServerError().finish();
+    };
+    let mut form = vec![
+        ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ("device_code", session.device_code.as_str()),
+    ];
+    let mut request = oidc_conf
+        .get_reqwest_client()
+        .post(token_uri.url().as_str());
+    match &oidc_conf.client_secret {
+        Some(secret) => request = request.basic_auth(&oidc_conf.client_id, Some(secret)),
+        None => form.push(("client_id", oidc_conf.client_id.as_str())),
+    }
+    let token_response: DeviceTokenResponse = match request.form(&form).send().await {
+        Ok(response) => match response.json().await {
+            Ok(token) => token,
+            Err(e) => {
+                error!(error = %e, " couldn't parse the token response");
+                return HttpResponse::InternalServerError().body(e.to_string());
+            }
+        },
+        Err(e) => {
+            error!(error = %e, " couldn't reach the token endpoint");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+
+    if let Some(error) = token_response.error {
+        match pending_poll(&error, session.interval) {
+            PendingPoll::Pending => {}
+            PendingPoll::SlowDown(interval) => {
+                session.interval = interval;
+                // Keep the expiration of the device authorization
+                let options = SetOptions::default().with_expiration(SetExpiry::KEEPTTL);
+                if let Err(e) = conn.set_options(&key, session.to_string(), options).await {
+                    error!(error = %e, " couldn't update the device authorization");
+                }
+            }
+            PendingPoll::Ended => {
+                let _ = conn.del(&key).await;
+            }
+        }
+        return HttpResponse::BadRequest().json(DeviceTokenErrorModel {
+            error,
+            interval: session.interval,
+        });
+    }
+    let _ = conn.del(&key).await;
+
+    let (Some(access_token), Some(raw_id_token)) =
+        (token_response.access_token, token_response.id_token)
+    else {
+        error!("The token response has no access or ID token");
+        return HttpResponse::InternalServerError().body("No ID token received");
+    };
+    let id_token = match CoreIdToken::from_str(&raw_id_token) {
+        Ok(id_token) => id_token,
+        Err(e) => {
+            error!(error = %e, " couldn't parse ID token");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    // The device authorization request carries no nonce
+    let subject =
+        match id_token.claims(&client_oidc.id_token_verifier(), |_: Option<&Nonce>| Ok(())) {
+            Ok(claims) => claims.subject().to_string(),
+            Err(e) => {
+                error!(error = %e, " couldn't verify ID token");
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+    info!(
+        "User {} completed a device authorization for cluster {}/{}",
+        user.username, ns, cluster
+    );
+    HttpResponse::Ok().json(CallbackModel {
+        id_token: raw_id_token,
+        access_token,
+        refresh_token: token_response.refresh_token.unwrap_or_default(),
+        expires_in: token_response.expires_in,
+        cluster_url: proxy.to_full_path(data.clone().into_inner()),
+        subject,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_poll() {
+        assert_eq!(
+            pending_poll("authorization_pending", 5),
+            PendingPoll::Pending
+        );
+        assert_eq!(pending_poll("slow_down", 5), PendingPoll::SlowDown(10));
+        assert_eq!(pending_poll("slow_down", 10), PendingPoll::SlowDown(15));
+        assert_eq!(pending_poll("access_denied", 5), PendingPoll::Ended);
+        assert_eq!(pending_poll("expired_token", 5), PendingPoll::Ended);
+    }
+
+    #[test]
+    fn test_device_authorization_response() {
+        // Google uses the draft name of verification_uri and may omit the interval
+        let response: DeviceAuthorizationResponse = serde_json::from_str(
+            r#"{"device_code":"d","user_code":"ABCD","verification_url":"https://www.google.com/device","expires_in":1800}"#,
+        )
+        .unwrap();
+        assert_eq!(response.verification_uri, "https://www.google.com/device");
+        assert_eq!(
+            response.interval.unwrap_or(DEFAULT_INTERVAL),
+            DEFAULT_INTERVAL
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Device authorization started for a cluster.
+///
+/// The user has to visit the verification URI and enter the user code, the device is then polled with the device id.
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct DeviceAuthorizationModel {
+    /// Identifier of the device authorization, used to poll the token
+    pub device_id: String,
+    /// Code the user has to enter on the verification page
+    pub user_code: String,
+    /// Page of the OIDC provider where the user enters the code
+    pub verification_uri: String,
+    /// Verification page with the user code already filled, if the OIDC provider supports it
+    pub verification_uri_complete: Option<String>,
+    /// Seconds before the device authorization expires
+    pub expires_in: u64,
+    /// Minimum seconds to wait between two polls
+    pub interval: u64,
+}
+
+/// Body of a device token poll
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct DeviceTokenBody {
+    /// Identifier returned when the device authorization was started
+    pub device_id: String,
+}
+
+/// Device authorization not completed.
+///
+/// The error follows RFC 8628: authorization_pending and slow_down mean the poll has to be retried,
+/// access_denied and expired_token end the device authorization.
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct DeviceTokenErrorModel {
+    pub error: String,
+    /// Minimum seconds to wait before the next poll
+    pub interval: u64,
+}
+
+/// Device authorization kept in redis between the polls
+#[derive(Deserialize, Serialize)]
+pub struct DeviceSessionModel {
+    pub device_code: String,
+    pub username: String,
+    pub interval: u64,
+}
+
+impl DeviceSessionModel {
+    pub fn from_string(s: &str) -> Option<DeviceSessionModel> {
+        serde_json::from_str::<DeviceSessionModel>(s).ok()
+    }
+}
+
+impl Display for DeviceSessionModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
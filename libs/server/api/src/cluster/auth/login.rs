@@ -2,10 +2,27 @@ use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
 use common::State;
 use crd::ProxyKubeApi;
 use deadpool_redis::redis::AsyncTypedCommands;
-use openidconnect::{core::CoreAuthenticationFlow, CsrfToken, Nonce, PkceCodeChallenge, Scope};
+use openidconnect::{
+    core::CoreAuthenticationFlow, AuthenticationContextClass, CsrfToken, Nonce, PkceCodeChallenge,
+    Scope,
+};
+use serde::Deserialize;
+use std::time::Duration;
 use tracing::{error, info, instrument};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{cluster::auth::auth_model::LoginToCallBackModel, model::user::User};
+use crate::{
+    cluster::auth::{auth_model::LoginToCallBackModel, callback_header},
+    model::user::User,
+};
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct LoginQuery {
+    /// Space separated authentication context class references to request to the OIDC provider (step-up authentication).
+    pub acr_values: Option<String>,
+    /// Maximum age in seconds of the authentication, forces a re-authentication if older.
+    pub max_age: Option<u64>,
+}
 
 /// Redirect to the cluster's login page
 ///
@@ -25,11 +42,17 @@ use crate::{cluster::auth::auth_model::LoginToCallBackModel, model::user::User};
         ("cluster" = String, description = "Cluster name that should exist in the namespace."),
         ("x-front-callback" = String, Header, nullable, description = "If it's from the frontend, this header will be set."),
         ("x-kubectl-callback" = String, Header, nullable, description = "If it's from kubectl plugin, this header will be set."),
+        LoginQuery,
     )
 )]
 #[get("/{ns}/{cluster}/auth/login")]
-#[instrument(name = "cluster_login", skip(data))]
-pub async fn cluster_login(req: HttpRequest, data: web::Data<State>, user: User) -> impl Responder {
+#[instrument(name = "cluster_login", skip(data, login))]
+pub async fn cluster_login(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+    login: web::Query<LoginQuery>,
+) -> impl Responder {
     let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
     let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
     let mut conn = match data.get_redis_conn().await {
@@ -62,11 +85,8 @@ pub async fn cluster_login(req: HttpRequest, data: web::Data<State>, user: User)
     {
         return HttpResponse::NotFound().finish();
     }
-    let redirect_front = req.headers().contains_key("x-front-callback");
-    let redirect_kubectl = req
-        .headers()
-        .get("x-kubectl-callback")
-        .map(|v| v.to_str().unwrap_or_default().to_string());
+    let redirect_front = callback_header(&req, "x-front-callback").is_some();
+    let redirect_kubectl = callback_header(&req, "x-kubectl-callback");
     let oidc_conf = match proxy.get_oidc_conf(data.into_inner(), redirect_front, redirect_kubectl) {
         Some(conf) => conf,
         None => {
@@ -91,15 +111,25 @@ pub async fn cluster_login(req: HttpRequest, data: web::Data<State>, user: User)
         .map(|s| Scope::new(s.to_string()))
         .collect();
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
-    let (auth_url, csrf_token, nonce) = client
+    let mut auth_request = client
         .authorize_url(
             CoreAuthenticationFlow::AuthorizationCode,
             CsrfToken::new_random,
             Nonce::new_random,
         )
         .set_pkce_challenge(pkce_challenge)
-        .add_scopes(scopes)
-        .url();
+        .add_scopes(scopes);
+    // Step-up authentication, request a stronger or more recent authentication to the OIDC provider
+    if let Some(acr_values) = &login.acr_values {
+        for acr in acr_values.split_whitespace() {
+            auth_request = auth_request
+                .add_auth_context_value(AuthenticationContextClass::new(acr.to_string()));
+        }
+    }
+    if let Some(max_age) = login.max_age {
+        auth_request = auth_request.set_max_age(Duration::from_secs(max_age));
+    }
+    let (auth_url, csrf_token, nonce) = auth_request.url();
 
     // Store the csrf token and nonce in redis with a short TTL to validate later. TTL could be 5 minutes.
     match conn
@@ -0,0 +1,108 @@
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use common::State;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+use utoipa::ToSchema;
+
+use crate::{
+    cluster::auth::{discover_endpoint, get_proxy_oidc_conf},
+    model::user::User,
+};
+
+/// Body of a cluster logout
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct LogoutBody {
+    /// Token to revoke, the refresh token when there is one
+    pub token: String,
+    /// Kind of the token, refresh_token or access_token
+    pub token_type_hint: Option<String>,
+}
+
+/// Revoke a token of a cluster
+///
+/// Revoke the token with the cluster's OIDC provider (RFC 7009), the client secret of the cluster stays on the server.
+/// Only the users allowed on the cluster can revoke its tokens.
+/// Return 501 if the OIDC provider has no revocation endpoint, the token then stays valid until it expires.
+#[utoipa::path(
+    tag = "auth_clusters",
+    request_body = LogoutBody,
+    responses(
+        (status = 204, description = "Token revoked."),
+        (status = 400, description = "Token rejected by the OIDC provider."),
+        (status = 401, description = "User is not authenticated."),
+        (status = 404, description = "Cluster not found, disabled or not visible to the user."),
+        (status = 500, description = "Internal server error."),
+        (status = 501, description = "The OIDC provider doesn't support token revocation."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("ns" = String, description = "Namespace containing the cluster."),
+        ("cluster" = String, description = "Cluster name that should exist in the namespace."),
+    )
+)]
+#[post("/{ns}/{cluster}/auth/logout")]
+#[instrument(name = "cluster_logout", skip(data, body))]
+pub async fn logout(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+    body: web::Json<LogoutBody>,
+) -> impl Responder {
+    let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
+    let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
+    let (proxy, oidc_conf) = match get_proxy_oidc_conf(&data, &ns, &cluster).await {
+        Ok(conf) => conf,
+        Err(response) => return response,
+    };
+    if !proxy.is_user_allowed(&user.groups) {
+        return HttpResponse::NotFound().finish();
+    }
+    let revocation_endpoint = match discover_endpoint(&oidc_conf, "revocation_endpoint").await {
+        Ok(Some(endpoint)) => endpoint,
+        Ok(None) => {
+            return HttpResponse::NotImplemented()
+                .body("The OIDC provider doesn't support token revocation")
+        }
+        Err(e) => {
+            error!(error = %e, " couldn't get the OIDC discovery document");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+
+    let mut form = vec![("token", body.token.as_str())];
+    if let Some(hint) = &body.token_type_hint {
+        form.push(("token_type_hint", hint.as_str()));
+    }
+    let mut request = oidc_conf.get_reqwest_client().post(&revocation_endpoint);
+    match &oidc_conf.client_secret {
+        Some(secret) => request = request.basic_auth(&oidc_conf.client_id, Some(secret)),
+        None => form.push(("client_id", oidc_conf.client_id.as_str())),
+    }
+    let response = match request.form(&form).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            error!(error = %e, " couldn't reach the revocation endpoint");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    if response.status().is_client_error() {
+        let body = response.text().await.unwrap_or_default();
+        info!(
+            body,
+            "Token revocation refused for cluster {}/{}", ns, cluster
+        );
+        return HttpResponse::BadRequest().body(body);
+    }
+    if !response.status().is_success() {
+        let status = response.status();
+        error!(%status, " token revocation failed");
+        return HttpResponse::InternalServerError().finish();
+    }
+    info!(
+        "User {} logged out of cluster {}/{}",
+        user.username, ns, cluster
+    );
+    HttpResponse::NoContent().finish()
+}
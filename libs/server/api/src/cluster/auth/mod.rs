@@ -1,4 +1,72 @@
 pub mod auth_model;
 pub mod callback;
 pub mod callback_model;
+pub mod device;
+pub mod device_model;
 pub mod login;
+pub mod logout;
+pub mod oidc;
+pub mod refresh;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use common::{oidc_conf::OidcConf, State};
+use crd::ProxyKubeApi;
+use tracing::error;
+
+/// Value of a callback header, the generated clients send an empty header when the value is not set
+pub(crate) fn callback_header(req: &HttpRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+/// Fetch the proxy and its OIDC configuration, the response is the error to return
+pub(crate) async fn get_proxy_oidc_conf(
+    data: &web::Data<State>,
+    ns: &str,
+    cluster: &str,
+) -> Result<(ProxyKubeApi, OidcConf), HttpResponse> {
+    let proxy: ProxyKubeApi = match data
+        .get_object_from_redis("proxyk8sauth".to_string(), format!("{}/{}", ns, cluster))
+        .await
+    {
+        Ok(Some(proxy)) => proxy,
+        Ok(None) => return Err(HttpResponse::NotFound().finish()),
+        Err(e) => {
+            error!(error = %e, " couldn't get proxy from redis");
+            return Err(HttpResponse::ServiceUnavailable().body(e.to_string()));
+        }
+    };
+    if !proxy.spec.enabled {
+        return Err(HttpResponse::NotFound().finish());
+    }
+    match proxy.get_oidc_conf(data.clone().into_inner(), false, None) {
+        Some(conf) => Ok((proxy, conf)),
+        None => Err(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Endpoint of the OIDC discovery document missing from the core provider metadata,
+/// such as the device authorization or the revocation endpoint
+pub(crate) async fn discover_endpoint(
+    oidc_conf: &OidcConf,
+    name: &str,
+) -> Result<Option<String>, reqwest::Error> {
+    let discovery_url = format!(
+        "{}/.well-known/openid-configuration",
+        oidc_conf.issuer_url.trim_end_matches('/')
+    );
+    let metadata: serde_json::Value = oidc_conf
+        .get_reqwest_client()
+        .get(&discovery_url)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(metadata
+        .get(name)
+        .and_then(|endpoint| endpoint.as_str())
+        .map(|endpoint| endpoint.to_string()))
+}
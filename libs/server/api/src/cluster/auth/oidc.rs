@@ -0,0 +1,65 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use common::State;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+use utoipa::ToSchema;
+
+use crate::{cluster::auth::get_proxy_oidc_conf, model::user::User};
+
+/// OIDC provider of a cluster, for the clients requesting their tokens from it directly.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ClusterOidcConfiguration {
+    pub issuer_url: String,
+    /// Client of the cluster, the audience expected in the tokens of the users
+    pub client_id: String,
+    /// Space separated scopes to request, on top of the ones of the client
+    pub scopes: String,
+}
+
+/// OIDC provider of a cluster
+///
+/// Used by confidential clients, such as CI runners, requesting their tokens with the client credentials grant,
+/// and to explain why the cluster rejected a token. The secret of the cluster stays on the server.
+/// If the cluster is not found, disabled, not visible to the user or without OIDC provider, return 404.
+#[utoipa::path(
+    tag = "auth_clusters",
+    responses(
+        (status = 200, description = "OIDC provider of the cluster.", body = ClusterOidcConfiguration),
+        (status = 401, description = "User is not authenticated."),
+        (status = 404, description = "Cluster not found or disabled."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("ns" = String, description = "Namespace containing the cluster."),
+        ("cluster" = String, description = "Cluster name that should exist in the namespace."),
+    )
+)]
+#[get("/{ns}/{cluster}/auth/oidc")]
+#[instrument(name = "cluster_oidc_configuration", skip(data))]
+pub async fn cluster_oidc_configuration(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+) -> impl Responder {
+    let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
+    let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
+    let (proxy, oidc_conf) = match get_proxy_oidc_conf(&data, &ns, &cluster).await {
+        Ok(conf) => conf,
+        Err(response) => return response,
+    };
+    if !proxy.is_user_allowed(&user.groups) {
+        return HttpResponse::NotFound().finish();
+    }
+    info!(
+        "User {} got the OIDC provider of cluster {}/{}",
+        user.username, ns, cluster
+    );
+    HttpResponse::Ok().json(ClusterOidcConfiguration {
+        issuer_url: oidc_conf.issuer_url,
+        client_id: oidc_conf.client_id,
+        scopes: oidc_conf.scopes,
+    })
+}
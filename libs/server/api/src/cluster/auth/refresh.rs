@@ -0,0 +1,116 @@
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use common::State;
+use openidconnect::{Nonce, OAuth2TokenResponse, RefreshToken, RequestTokenError, TokenResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+use utoipa::ToSchema;
+
+use crate::{
+    cluster::auth::{callback_model::CallbackModel, get_proxy_oidc_conf},
+    model::user::User,
+};
+
+/// Body of a cluster token refresh
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct RefreshBody {
+    /// Refresh token returned by the login to the cluster
+    pub refresh_token: String,
+}
+
+/// Refresh the tokens of a cluster
+///
+/// Exchange the refresh token with the cluster's OIDC provider, the client secret of the cluster stays on the server.
+/// The refresh token is returned unchanged if the OIDC provider doesn't rotate it.
+#[utoipa::path(
+    tag = "auth_clusters",
+    request_body = RefreshBody,
+    responses(
+        (status = 200, description = "Tokens refreshed.", body = CallbackModel),
+        (status = 400, description = "Refresh token rejected by the OIDC provider."),
+        (status = 401, description = "User is not authenticated."),
+        (status = 404, description = "Cluster not found or disabled."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("ns" = String, description = "Namespace containing the cluster."),
+        ("cluster" = String, description = "Cluster name that should exist in the namespace."),
+    )
+)]
+#[post("/{ns}/{cluster}/auth/refresh")]
+#[instrument(name = "cluster_refresh", skip(data, body))]
+pub async fn refresh_login(
+    req: HttpRequest,
+    data: web::Data<State>,
+    user: User,
+    body: web::Json<RefreshBody>,
+) -> impl Responder {
+    let ns: String = req.match_info().get("ns").unwrap().parse().unwrap();
+    let cluster: String = req.match_info().get("cluster").unwrap().parse().unwrap();
+    let (proxy, oidc_conf) = match get_proxy_oidc_conf(&data, &ns, &cluster).await {
+        Ok(conf) => conf,
+        Err(response) => return response,
+    };
+    let client_oidc = match oidc_conf.get_oidc_core().await {
+        Ok(client) => client,
+        Err(e) => {
+            error!(error = %e, " couldn't get oidc client");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    let refresh_token = RefreshToken::new(body.refresh_token.clone());
+    let refresh_request = match client_oidc.exchange_refresh_token(&refresh_token) {
+        Ok(request) => request,
+        Err(e) => {
+            error!(error = %e, " couldn't create the refresh request");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    let token_response = match refresh_request
+        .request_async(&oidc_conf.get_oidc_reqwest_client())
+        .await
+    {
+        Ok(token) => token,
+        Err(RequestTokenError::ServerResponse(e)) => {
+            info!(error = %e, "Refresh token rejected for cluster {}/{}", ns, cluster);
+            return HttpResponse::BadRequest().body("Refresh token rejected");
+        }
+        Err(e) => {
+            error!(error = %e, " couldn't refresh the token");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+
+    // The ID token is optional on a refresh, the subject is then unknown
+    let (id_token, subject) = match token_response.id_token() {
+        Some(id_token) => {
+            match id_token.claims(&client_oidc.id_token_verifier(), |_: Option<&Nonce>| Ok(())) {
+                Ok(claims) => (id_token.to_string(), claims.subject().to_string()),
+                Err(e) => {
+                    error!(error = %e, " couldn't verify ID token");
+                    return HttpResponse::InternalServerError().finish();
+                }
+            }
+        }
+        None => (String::new(), String::new()),
+    };
+    info!(
+        "User {} refreshed the tokens of cluster {}/{}",
+        user.username, ns, cluster
+    );
+    HttpResponse::Ok().json(CallbackModel {
+        id_token,
+        access_token: token_response.access_token().secret().to_string(),
+        refresh_token: match token_response.refresh_token() {
+            Some(refresh_token) => refresh_token.secret().to_string(),
+            None => body.refresh_token.clone(),
+        },
+        expires_in: token_response
+            .expires_in()
+            .map(|expires_in| expires_in.as_secs()),
+        cluster_url: proxy.to_full_path(data.clone().into_inner()),
+        subject,
+    })
+}
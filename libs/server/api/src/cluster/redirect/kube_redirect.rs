@@ -1,19 +1,28 @@
 use actix_web::{dev::PeerAddr, http, web, HttpRequest, HttpResponse, Responder};
 use common::State;
-use crd::ProxyKubeApi;
-use tracing::{debug, error, info, instrument};
+use crd::{authentication_configuration::validate_against::ValidateAgainst, ProxyKubeApi};
+use tracing::{debug, error, info, instrument, Span};
 
-use crate::helper::extract_authorization_header;
-use crate::model::user::User;
+use crate::helper::{
+    extract_authorization_header,
+    metrics::record_token_validation,
+    request_attributes::{is_upgrade_request, RequestAttributes},
+};
+use crate::model::{
+    api_token::ApiToken, issued_token::mint_cluster_token, token_exchange::exchange_token,
+    user::User, validator_error::ValidatorError,
+};
 
 mod standard;
+mod step_up;
 mod tls;
 mod upgrade;
 
 use standard::standard_redirect;
-use upgrade::{is_upgrade_request, upgrade_redirect};
+use step_up::check_step_up;
+use upgrade::upgrade_redirect;
 
-#[instrument(name = "main_redirect",fields(http.method= ?method, http.response.status_code) ,skip(req, data, payload))]
+#[instrument(name = "main_redirect",fields(http.method= ?method, http.response.status_code, auth.validator) ,skip(req, data, payload))]
 pub async fn redirect(
     req: HttpRequest,
     data: web::Data<State>,
@@ -47,33 +56,53 @@ pub async fn redirect(
     debug!(proxy = ?proxy, "Proxy found for cluster");
     debug!(is_upgrade, "Is upgrade request");
 
-    let _user = if proxy.need_token_validation() {
-        let token = match extract_authorization_header(&req) {
-            Ok(token) => token,
-            Err(e) => {
-                tracing::warn!("Authorization header extraction failed: {}", e);
-                return HttpResponse::Unauthorized().finish();
-            }
-        };
-        match User::get_user_info_with_proxy(
-            data.get_ref().clone(),
-            proxy.clone(),
-            token.to_string(),
-        )
-        .await
-        {
-            Ok(Some(user)) => Some(user),
-            Ok(None) => {
-                tracing::warn!("User info not found in OIDC response");
-                return HttpResponse::Unauthorized().finish();
-            }
-            Err(e) => {
-                tracing::warn!("Error while getting user info from OIDC token: {}", e);
-                return HttpResponse::Unauthorized().finish();
+    let user = match check_api_token(&req, &data, &proxy).await {
+        Ok(Some(user)) => Some(user),
+        Err(response) => return response,
+        Ok(None) if proxy.need_token_validation() => {
+            let token = match extract_authorization_header(&req) {
+                Ok(token) => token,
+                Err(e) => {
+                    tracing::warn!("Authorization header extraction failed: {}", e);
+                    return HttpResponse::Unauthorized().finish();
+                }
+            };
+            match User::get_user_info_with_proxy(
+                data.get_ref().clone(),
+                proxy.clone(),
+                token.to_string(),
+            )
+            .await
+            {
+                Ok(Some(user)) => {
+                    if let Some(validator) = &user.validator {
+                        Span::current().record("auth.validator", validator.name());
+                    }
+                    if let Some(response) = check_step_up(&req, &proxy, token) {
+                        return response;
+                    }
+                    Some(user)
+                }
+                Ok(None) => {
+                    tracing::warn!("User info not found in OIDC response");
+                    return HttpResponse::Unauthorized().finish();
+                }
+                Err(e @ ValidatorError::Unavailable(_)) => {
+                    tracing::warn!("No validator could validate the token: {}", e);
+                    return HttpResponse::ServiceUnavailable().finish();
+                }
+                Err(e) => {
+                    tracing::warn!("Error while getting user info from OIDC token: {}", e);
+                    return HttpResponse::Unauthorized().finish();
+                }
             }
         }
-    } else {
-        None
+        Ok(None) => None,
+    };
+
+    let upstream_token = match upstream_token(&req, &data, &proxy, user.as_ref()).await {
+        Ok(upstream_token) => upstream_token,
+        Err(response) => return response,
     };
 
     // TODO: Check if user need to be validated for this cluster and if so, validate it before forwarding the request
@@ -109,8 +138,143 @@ pub async fn redirect(
         method.as_str()
     );
     if is_upgrade {
-        return upgrade_redirect(req, data, payload, method, peer_addr, proxy, url_to_call).await;
+        return upgrade_redirect(
+            req,
+            data,
+            payload,
+            method,
+            peer_addr,
+            proxy,
+            url_to_call,
+            upstream_token,
+        )
+        .await;
     }
 
-    standard_redirect(req, data, payload, method, peer_addr, proxy, url_to_call).await
+    standard_redirect(
+        req,
+        data,
+        payload,
+        method,
+        peer_addr,
+        proxy,
+        url_to_call,
+        upstream_token,
+    )
+    .await
+}
+
+/// Resolve and check the scopes of an API token issued by ProxyAuthK8S
+/// Return None when the request doesn't use an API token, the response to send back if the token is refused
+async fn check_api_token(
+    req: &HttpRequest,
+    data: &web::Data<State>,
+    proxy: &ProxyKubeApi,
+) -> Result<Option<User>, HttpResponse> {
+    let token = match extract_authorization_header(req) {
+        Ok(token) if ApiToken::is_api_token(token) => token,
+        _ => return Ok(None),
+    };
+    let cluster = proxy.to_path();
+    Span::current().record("auth.validator", "api_token");
+    let api_token = match ApiToken::verify(data, token).await {
+        Ok(api_token) => api_token,
+        Err(e @ ValidatorError::Unavailable(_)) => {
+            record_token_validation(&cluster, "api_token", e.classification());
+            tracing::warn!("Couldn't verify the API token: {}", e);
+            return Err(HttpResponse::ServiceUnavailable().finish());
+        }
+        Err(e) => {
+            record_token_validation(&cluster, "api_token", e.classification());
+            tracing::warn!("Invalid API token: {}", e);
+            return Err(HttpResponse::Unauthorized().finish());
+        }
+    };
+    record_token_validation(&cluster, "api_token", "success");
+    let attributes = RequestAttributes::from_request(req, &format!("/clusters/{}", cluster));
+    let allowed = api_token.allows_cluster(&cluster)
+        && api_token.allows_request(&attributes, is_upgrade_request(req))
+        && proxy.is_user_allowed(&api_token.groups);
+    info!(
+        target: "audit",
+        action = "api_token.use",
+        owner = %api_token.owner,
+        token_id = %api_token.id,
+        cluster = %cluster,
+        verb = %attributes.verb,
+        allowed,
+        "API token used"
+    );
+    if !allowed {
+        return Err(HttpResponse::Forbidden().body("The API token doesn't allow this request"));
+    }
+    // An API token never satisfies a step-up requirement
+    if let Some(response) = check_step_up(req, proxy, token) {
+        return Err(response);
+    }
+    Ok(Some(User::from_api_token(api_token)))
+}
+
+/// Token presented to the cluster instead of the token of the user
+/// Minted when ProxyAuthK8S is the issuer trusted by the cluster, exchanged when token exchange is configured
+/// Return None when the token of the user is forwarded as is
+async fn upstream_token(
+    req: &HttpRequest,
+    data: &web::Data<State>,
+    proxy: &ProxyKubeApi,
+    user: Option<&User>,
+) -> Result<Option<String>, HttpResponse> {
+    let auth_config = proxy.spec.auth_config.as_ref();
+    if let Some(config) = auth_config.and_then(|auth_config| auth_config.issued_token.as_ref()) {
+        // A token is only minted for an identified user
+        let Some(user) = user.filter(|user| !user.username.is_empty()) else {
+            return Err(HttpResponse::Unauthorized().finish());
+        };
+        return match mint_cluster_token(data, proxy, config, user).await {
+            Ok(token) => {
+                debug!(user = %user.username, "Cluster token minted");
+                Ok(Some(token))
+            }
+            Err(e) => {
+                error!(error = %e, "couldn't mint the cluster token");
+                Err(HttpResponse::ServiceUnavailable().finish())
+            }
+        };
+    }
+    if user.is_some_and(|user| user.api_token.is_some()) {
+        // The cluster only trusts its own validators, the proxy can't present the API token downstream
+        return Err(HttpResponse::Forbidden()
+            .body("This cluster doesn't trust the ProxyAuthK8S issuer, API tokens can't be used"));
+    }
+    let Some(oidc_provider) = auth_config
+        .map(|auth_config| &auth_config.oidc_provider)
+        .filter(|oidc_provider| oidc_provider.token_exchange.is_some())
+    else {
+        if user.is_some_and(|user| {
+            matches!(
+                user.validator,
+                Some(ValidateAgainst::TokenReview | ValidateAgainst::Webhook)
+            )
+        }) {
+            // The token is trusted by the management cluster or the webhook, never by the cluster
+            return Err(HttpResponse::Forbidden().body(
+                "Tokens validated by TokenReview or a webhook require issued_token or token_exchange",
+            ));
+        }
+        return Ok(None);
+    };
+    let Ok(token) = extract_authorization_header(req) else {
+        return Err(HttpResponse::Unauthorized().finish());
+    };
+    match exchange_token(data, proxy, oidc_provider, token).await {
+        Ok(token) => Ok(Some(token)),
+        Err(e @ ValidatorError::Unavailable(_)) => {
+            error!(error = %e, "couldn't exchange the token");
+            Err(HttpResponse::ServiceUnavailable().finish())
+        }
+        Err(e) => {
+            tracing::warn!("Token exchange refused: {}", e);
+            Err(HttpResponse::Unauthorized().finish())
+        }
+    }
 }
@@ -22,7 +22,8 @@ fn body_for_debug_log(body: &[u8]) -> String {
     )
 }
 
-#[instrument(skip(req, data, payload))]
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip(req, data, payload, upstream_token))]
 pub(super) async fn standard_redirect(
     req: HttpRequest,
     data: web::Data<State>,
@@ -31,6 +32,7 @@ pub(super) async fn standard_redirect(
     peer_addr: Option<PeerAddr>,
     proxy: ProxyKubeApi,
     url_to_call: String,
+    upstream_token: Option<String>,
 ) -> HttpResponse {
     let is_debug_enabled = tracing::enabled!(tracing::Level::DEBUG);
     // watch=true/1 and follow=true/1 produce infinite streaming responses; treat them specially
@@ -132,6 +134,7 @@ pub(super) async fn standard_redirect(
             || name.eq_ignore_ascii_case("transfer-encoding")
             || name.eq_ignore_ascii_case("content-length")
             || name.eq_ignore_ascii_case("host")
+            || (upstream_token.is_some() && name.eq_ignore_ascii_case("authorization"))
         {
             continue;
         }
@@ -145,6 +148,11 @@ pub(super) async fn standard_redirect(
         }
     }
 
+    // The token minted or exchanged for the cluster replaces the token of the user
+    if let Some(token) = upstream_token {
+        forwarded_req = forwarded_req.bearer_auth(token);
+    }
+
     if let Some(PeerAddr(addr)) = peer_addr {
         forwarded_req = forwarded_req.header("x-forwarded-for", addr.ip().to_string());
     }
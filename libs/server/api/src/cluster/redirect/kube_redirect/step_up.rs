@@ -0,0 +1,122 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use crd::{authentication_configuration::step_up_requirement::StepUpRequirement, ProxyKubeApi};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Status, StatusCause, StatusDetails};
+use kube::ResourceExt;
+use tracing::{debug, warn};
+
+use crate::{
+    helper::request_attributes::RequestAttributes, model::token_claims::AuthenticationClaims,
+};
+
+/// Check the step-up requirements of the proxy against the claims of the token
+/// Return the response to send back if the token does not satisfy the requirement applying to the request
+pub(super) fn check_step_up(
+    req: &HttpRequest,
+    proxy: &ProxyKubeApi,
+    token: &str,
+) -> Option<HttpResponse> {
+    let auth_config = proxy.spec.auth_config.as_ref()?;
+    if auth_config.step_up.is_empty() {
+        return None;
+    }
+    let attributes =
+        RequestAttributes::from_request(req, &format!("/clusters/{}", proxy.to_path()));
+    let requirement = auth_config.step_up_requirement_for(
+        &attributes.verb,
+        attributes.resource.as_deref(),
+        attributes.subresource.as_deref(),
+    )?;
+    let claims = AuthenticationClaims::from_validated_token(token);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    if requirement.is_satisfied_by(claims.acr.as_deref(), &claims.amr, claims.auth_time, now) {
+        debug!(verb = %attributes.verb, resource = ?attributes.resource_path(), "Step-up requirement satisfied");
+        return None;
+    }
+    warn!(
+        verb = %attributes.verb,
+        resource = ?attributes.resource_path(),
+        acr = ?claims.acr,
+        amr = ?claims.amr,
+        auth_time = ?claims.auth_time,
+        "Step-up authentication required"
+    );
+    Some(step_up_response(proxy, &attributes, requirement))
+}
+
+/// Build the 401 answer, a Kubernetes Status understood by kubectl and a RFC 9470 challenge
+fn step_up_response(
+    proxy: &ProxyKubeApi,
+    attributes: &RequestAttributes,
+    requirement: &StepUpRequirement,
+) -> HttpResponse {
+    let acr_values = requirement.acr_values.join(" ");
+    let mut causes = vec![];
+    let mut challenge = "Bearer error=\"insufficient_user_authentication\"".to_string();
+    if !acr_values.is_empty() {
+        challenge.push_str(&format!(", acr_values=\"{}\"", acr_values));
+        causes.push(StatusCause {
+            field: Some("acr_values".to_string()),
+            message: Some(acr_values.clone()),
+            reason: Some("StepUpRequired".to_string()),
+        });
+    }
+    if let Some(max_age) = requirement.max_age {
+        challenge.push_str(&format!(", max_age={}", max_age));
+        causes.push(StatusCause {
+            field: Some("max_age".to_string()),
+            message: Some(max_age.to_string()),
+            reason: Some("StepUpRequired".to_string()),
+        });
+    }
+    if !requirement.amr.is_empty() {
+        causes.push(StatusCause {
+            field: Some("amr".to_string()),
+            message: Some(requirement.amr.join(" ")),
+            reason: Some("StepUpRequired".to_string()),
+        });
+    }
+
+    let mut relogin = format!(
+        "kubectl proxyauth login {} -n {}",
+        proxy.name_any(),
+        proxy.namespace().unwrap_or_default()
+    );
+    if !acr_values.is_empty() {
+        relogin.push_str(&format!(" --acr-values \"{}\"", acr_values));
+    }
+    if let Some(max_age) = requirement.max_age {
+        relogin.push_str(&format!(" --max-age {}", max_age));
+    }
+    let amr = if requirement.amr.is_empty() {
+        String::new()
+    } else {
+        format!(" authenticating with amr=\"{}\"", requirement.amr.join(" "))
+    };
+    let status = Status {
+        status: Some("Failure".to_string()),
+        code: Some(401),
+        reason: Some("Unauthorized".to_string()),
+        message: Some(format!(
+            "step-up authentication required to {} {}, login again with `{}`{}",
+            attributes.verb,
+            attributes
+                .resource_path()
+                .unwrap_or_else(|| "this path".to_string()),
+            relogin,
+            amr
+        )),
+        details: Some(StatusDetails {
+            causes: Some(causes),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, challenge))
+        .json(status)
+}
@@ -16,24 +16,6 @@ use tracing::{error, instrument};
 
 use super::tls::build_tls_config;
 
-pub(super) fn is_upgrade_request(req: &HttpRequest) -> bool {
-    let has_upgrade_header = req.headers().contains_key(http::header::UPGRADE);
-    let connection_has_upgrade_token = req
-        .headers()
-        .get(http::header::CONNECTION)
-        .and_then(|v| v.to_str().ok())
-        .map(|v| {
-            v.split(',').any(|token| {
-                token
-                    .trim()
-                    .eq_ignore_ascii_case(http::header::UPGRADE.as_str())
-            })
-        })
-        .unwrap_or(false);
-
-    has_upgrade_header || connection_has_upgrade_token
-}
-
 trait AsyncIo: AsyncRead + AsyncWrite + Unpin + Send {}
 
 impl<T> AsyncIo for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
@@ -76,6 +58,7 @@ fn serialize_upgrade_request(
     method: &http::Method,
     upstream_url: &reqwest::Url,
     peer_addr: Option<PeerAddr>,
+    upstream_token: Option<&str>,
 ) -> Vec<u8> {
     let path = match upstream_url.query() {
         Some(query) => format!("{}?{}", upstream_url.path(), query),
@@ -90,7 +73,9 @@ fn serialize_upgrade_request(
     request_bytes.extend_from_slice(format!("Host: {}\r\n", authority).as_bytes());
 
     for (header_name, header_value) in req.headers() {
-        if header_name == http::header::HOST {
+        if header_name == http::header::HOST
+            || (upstream_token.is_some() && header_name == http::header::AUTHORIZATION)
+        {
             continue;
         }
 
@@ -100,6 +85,10 @@ fn serialize_upgrade_request(
         request_bytes.extend_from_slice(b"\r\n");
     }
 
+    if let Some(token) = upstream_token {
+        request_bytes.extend_from_slice(format!("authorization: Bearer {}\r\n", token).as_bytes());
+    }
+
     if let Some(PeerAddr(addr)) = peer_addr {
         request_bytes.extend_from_slice(format!("x-forwarded-for: {}\r\n", addr.ip()).as_bytes());
     }
@@ -153,7 +142,8 @@ async fn read_upgrade_response_headers(
     }
 }
 
-#[instrument(skip(req, data, payload))]
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip(req, data, payload, upstream_token))]
 pub(super) async fn upgrade_redirect(
     req: HttpRequest,
     data: web::Data<State>,
@@ -162,6 +152,7 @@ pub(super) async fn upgrade_redirect(
     peer_addr: Option<PeerAddr>,
     proxy: ProxyKubeApi,
     url_to_call: String,
+    upstream_token: Option<String>,
 ) -> HttpResponse {
     let upstream_url = match reqwest::Url::parse(&url_to_call) {
         Ok(url) => url,
@@ -173,7 +164,13 @@ pub(super) async fn upgrade_redirect(
         Err(err) => return HttpResponse::ServiceUnavailable().body(err),
     };
 
-    let request_bytes = serialize_upgrade_request(&req, &method, &upstream_url, peer_addr);
+    let request_bytes = serialize_upgrade_request(
+        &req,
+        &method,
+        &upstream_url,
+        peer_addr,
+        upstream_token.as_deref(),
+    );
     if let Err(err) = upstream.write_all(&request_bytes).await {
         return HttpResponse::ServiceUnavailable().body(err.to_string());
     }
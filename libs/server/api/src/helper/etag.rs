@@ -0,0 +1,54 @@
+use actix_web::{http::header, HttpRequest};
+use sha2::{Digest, Sha256};
+
+/// Strong entity tag of a response body, quoted as sent in the `ETag` header
+pub fn etag_of(body: &[u8]) -> String {
+    format!("\"{:x}\"", Sha256::digest(body))
+}
+
+/// Whether the `If-None-Match` header of the request matches `etag`
+/// The comparison is weak as required for GET, `W/"x"` matches `"x"`
+pub fn if_none_match(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = etag_of(b"{\"clusters\":[]}");
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+        let req = TestRequest::default().to_http_request();
+        assert!(!if_none_match(&req, &etag));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_http_request();
+        assert!(if_none_match(&req, &etag));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, format!("\"other\", W/{}", etag)))
+            .to_http_request();
+        assert!(if_none_match(&req, &etag));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"other\""))
+            .to_http_request();
+        assert!(!if_none_match(&req, &etag));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "*"))
+            .to_http_request();
+        assert!(if_none_match(&req, &etag));
+    }
+}
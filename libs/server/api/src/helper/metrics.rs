@@ -0,0 +1,29 @@
+use std::sync::LazyLock;
+
+use opentelemetry::{
+    global,
+    metrics::{Counter, Meter},
+    KeyValue,
+};
+
+static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("proxyauthk8s.api"));
+
+static TOKEN_VALIDATION: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("proxyauthk8s.auth.validation")
+        .with_description("Token validations per cluster, validator and outcome")
+        .build()
+});
+
+/// Record the outcome of a validator for a cluster
+/// outcome is either "success" or the classification of the failure
+pub fn record_token_validation(cluster: &str, validator: &str, outcome: &str) {
+    TOKEN_VALIDATION.add(
+        1,
+        &[
+            KeyValue::new("cluster", cluster.to_string()),
+            KeyValue::new("validator", validator.to_string()),
+            KeyValue::new("outcome", outcome.to_string()),
+        ],
+    );
+}
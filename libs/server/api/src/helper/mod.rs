@@ -1,3 +1,8 @@
+pub mod etag;
+pub mod metrics;
+pub mod request_attributes;
+pub mod tls;
+
 use actix_web::{http::header::ContentType, HttpRequest, HttpResponse};
 use thiserror::Error;
 
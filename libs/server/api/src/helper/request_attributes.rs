@@ -0,0 +1,214 @@
+use actix_web::{http, HttpRequest};
+
+// Mirror of the RequestInfoFactory of the Kubernetes API server
+// https://github.com/kubernetes/apiserver/blob/master/pkg/endpoints/request/requestinfo.go
+
+/// Subresources of a namespace that are not namespaced resources
+const NAMESPACE_SUBRESOURCES: [&str; 2] = ["status", "finalize"];
+
+/// Whether the request asks to switch protocols, as exec, attach and port-forward do over WebSocket or SPDY
+pub fn is_upgrade_request(req: &HttpRequest) -> bool {
+    let has_upgrade_header = req.headers().contains_key(http::header::UPGRADE);
+    let connection_has_upgrade_token = req
+        .headers()
+        .get(http::header::CONNECTION)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',').any(|token| {
+                token
+                    .trim()
+                    .eq_ignore_ascii_case(http::header::UPGRADE.as_str())
+            })
+        })
+        .unwrap_or(false);
+
+    has_upgrade_header || connection_has_upgrade_token
+}
+
+/// Attributes of a request made to the Kubernetes API, computed from the path and method
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RequestAttributes {
+    pub is_resource_request: bool,
+    pub verb: String,
+    pub api_group: String,
+    pub api_version: String,
+    pub namespace: Option<String>,
+    pub resource: Option<String>,
+    pub subresource: Option<String>,
+    pub name: Option<String>,
+}
+
+impl RequestAttributes {
+    /// Compute the attributes of a proxied request
+    /// `path` is the path given to the kube api server, without the /clusters/{ns}/{cluster} prefix
+    pub fn new(method: &http::Method, path: &str, query_string: &str) -> Self {
+        let parts: Vec<&str> = path
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect();
+        let non_resource = RequestAttributes {
+            is_resource_request: false,
+            verb: method.as_str().to_lowercase(),
+            ..Default::default()
+        };
+
+        let (api_group, api_version, mut parts) = match parts.as_slice() {
+            ["api", version, rest @ ..] if !rest.is_empty() => {
+                (String::new(), version.to_string(), rest.to_vec())
+            }
+            ["apis", group, version, rest @ ..] if !rest.is_empty() => {
+                (group.to_string(), version.to_string(), rest.to_vec())
+            }
+            _ => return non_resource,
+        };
+
+        let mut namespace = None;
+        if parts[0] == "namespaces" && parts.len() >= 2 {
+            namespace = Some(parts[1].to_string());
+            if parts.len() >= 3 && !NAMESPACE_SUBRESOURCES.contains(&parts[2]) {
+                parts = parts[2..].to_vec();
+            }
+        }
+
+        let resource = parts.first().map(|p| p.to_string());
+        let name = parts.get(1).map(|p| p.to_string());
+        let subresource = parts.get(2).map(|p| p.to_string());
+
+        let is_watch = query_string
+            .split('&')
+            .any(|param| param == "watch=true" || param == "watch=1" || param == "watch");
+        let verb = match *method {
+            http::Method::GET | http::Method::HEAD => {
+                if is_watch {
+                    "watch"
+                } else if name.is_some() {
+                    "get"
+                } else {
+                    "list"
+                }
+            }
+            http::Method::POST => "create",
+            http::Method::PUT => "update",
+            http::Method::PATCH => "patch",
+            http::Method::DELETE => {
+                if name.is_some() {
+                    "delete"
+                } else {
+                    "deletecollection"
+                }
+            }
+            _ => return non_resource,
+        };
+
+        RequestAttributes {
+            is_resource_request: true,
+            verb: verb.to_string(),
+            api_group,
+            api_version,
+            namespace,
+            resource,
+            subresource,
+            name,
+        }
+    }
+
+    /// Compute the attributes of a proxied request
+    /// An upgrade opens a stream to the pod whatever its method, it is a create as for the API server
+    pub fn from_request(req: &HttpRequest, cluster_prefix: &str) -> Self {
+        let path = req
+            .path()
+            .strip_prefix(cluster_prefix)
+            .unwrap_or(req.path());
+        let mut attributes = Self::new(req.method(), path, req.query_string());
+        if attributes.is_resource_request && is_upgrade_request(req) {
+            attributes.verb = "create".to_string();
+        }
+        attributes
+    }
+
+    /// Resource of the request, "resource" or "resource/subresource"
+    pub fn resource_path(&self) -> Option<String> {
+        match (&self.resource, &self.subresource) {
+            (Some(resource), Some(subresource)) => Some(format!("{}/{}", resource, subresource)),
+            (Some(resource), None) => Some(resource.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_requests() {
+        let attrs = RequestAttributes::new(
+            &http::Method::POST,
+            "/api/v1/namespaces/default/pods/nginx/exec",
+            "command=sh&stdin=true",
+        );
+        assert!(attrs.is_resource_request);
+        assert_eq!(attrs.verb, "create");
+        assert_eq!(attrs.namespace.as_deref(), Some("default"));
+        assert_eq!(attrs.resource_path().as_deref(), Some("pods/exec"));
+        assert_eq!(attrs.name.as_deref(), Some("nginx"));
+
+        let attrs = RequestAttributes::new(
+            &http::Method::GET,
+            "/apis/apps/v1/namespaces/default/deployments",
+            "watch=true",
+        );
+        assert_eq!(attrs.verb, "watch");
+        assert_eq!(attrs.api_group, "apps");
+        assert_eq!(attrs.resource_path().as_deref(), Some("deployments"));
+
+        let attrs =
+            RequestAttributes::new(&http::Method::DELETE, "/api/v1/namespaces/default/pods", "");
+        assert_eq!(attrs.verb, "deletecollection");
+
+        let attrs = RequestAttributes::new(&http::Method::DELETE, "/api/v1/namespaces/dev", "");
+        assert_eq!(attrs.verb, "delete");
+        assert_eq!(attrs.resource.as_deref(), Some("namespaces"));
+        assert_eq!(attrs.name.as_deref(), Some("dev"));
+
+        let attrs =
+            RequestAttributes::new(&http::Method::PUT, "/api/v1/namespaces/dev/finalize", "");
+        assert_eq!(attrs.verb, "update");
+        assert_eq!(
+            attrs.resource_path().as_deref(),
+            Some("namespaces/finalize")
+        );
+    }
+
+    #[test]
+    fn test_upgrade_requests() {
+        let req = actix_web::test::TestRequest::get()
+            .uri("/clusters/dev/c1/api/v1/namespaces/default/pods/nginx/exec?command=sh")
+            .insert_header((http::header::CONNECTION, "Upgrade"))
+            .insert_header((http::header::UPGRADE, "websocket"))
+            .to_http_request();
+        assert!(is_upgrade_request(&req));
+        let attrs = RequestAttributes::from_request(&req, "/clusters/dev/c1");
+        assert_eq!(attrs.verb, "create");
+        assert_eq!(attrs.resource_path().as_deref(), Some("pods/exec"));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/clusters/dev/c1/api/v1/namespaces/default/pods/nginx/log")
+            .to_http_request();
+        assert!(!is_upgrade_request(&req));
+        let attrs = RequestAttributes::from_request(&req, "/clusters/dev/c1");
+        assert_eq!(attrs.verb, "get");
+    }
+
+    #[test]
+    fn test_non_resource_requests() {
+        let attrs = RequestAttributes::new(&http::Method::GET, "/version", "");
+        assert!(!attrs.is_resource_request);
+        assert_eq!(attrs.verb, "get");
+        assert_eq!(attrs.resource_path(), None);
+
+        let attrs = RequestAttributes::new(&http::Method::GET, "/apis", "");
+        assert!(!attrs.is_resource_request);
+    }
+}
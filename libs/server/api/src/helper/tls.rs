@@ -0,0 +1,26 @@
+use rustls::{
+    pki_types::{pem::PemObject as _, CertificateDer},
+    ClientConfig, RootCertStore,
+};
+use rustls_platform_verifier::BuilderVerifierExt;
+
+/// Build the client TLS config trusting the given PEM CA, or the platform CA if none is given
+pub fn build_client_tls_config(cert_pem: Option<String>) -> Result<ClientConfig, String> {
+    if let Some(cert_pem) = cert_pem {
+        let mut root_store = RootCertStore::empty();
+        let certs: Vec<CertificateDer<'static>> =
+            CertificateDer::pem_slice_iter(cert_pem.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+        root_store.add_parsable_certificates(certs);
+
+        Ok(ClientConfig::builder()
+            .with_root_certificates(root_store)
+            .with_no_client_auth())
+    } else {
+        ClientConfig::builder()
+            .with_platform_verifier()
+            .map_err(|e| e.to_string())
+            .map(|builder| builder.with_no_client_auth())
+    }
+}
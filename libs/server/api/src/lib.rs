@@ -1,8 +1,12 @@
 use crate::{
-    api::get_all_visible_cluster::get_all_visible_cluster,
+    api::{
+        api_token::{create_api_token, list_api_tokens, revoke_api_token},
+        get_all_visible_cluster::get_all_visible_cluster,
+    },
     api_doc::ApiDoc,
-    base::health,
+    base::{cli_oidc_configuration, health},
     cluster::{auth, redirect},
+    well_known::{jwks, openid_configuration},
 };
 use actix_web::App;
 use utoipa::{openapi::OpenApi as OpenApiType, OpenApi};
@@ -14,16 +18,26 @@ pub mod base;
 pub mod cluster;
 pub mod helper;
 pub mod model;
+pub mod well_known;
 
 pub fn init_base_api() -> impl FnOnce(&mut ServiceConfig) {
     |cfg: &mut ServiceConfig| {
-        cfg.service(health);
+        cfg.service(health).service(cli_oidc_configuration);
     }
 }
 
 pub fn init_api() -> impl FnOnce(&mut ServiceConfig) {
     |cfg: &mut ServiceConfig| {
-        cfg.service(get_all_visible_cluster);
+        cfg.service(get_all_visible_cluster)
+            .service(create_api_token)
+            .service(list_api_tokens)
+            .service(revoke_api_token);
+    }
+}
+
+pub fn init_well_known_api() -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.service(openid_configuration).service(jwks);
     }
 }
 
@@ -31,6 +45,11 @@ pub fn init_cluster_api() -> impl FnOnce(&mut ServiceConfig) {
     |cfg: &mut ServiceConfig| {
         cfg.service(auth::login::cluster_login)
             .service(auth::callback::callback_login)
+            .service(auth::device::device_authorization)
+            .service(auth::device::device_token)
+            .service(auth::refresh::refresh_login)
+            .service(auth::oidc::cluster_oidc_configuration)
+            .service(auth::logout::logout)
             .service(redirect::get_redirect)
             .service(redirect::post_redirect)
             .service(redirect::put_redirect)
@@ -47,6 +66,7 @@ pub fn gen_openapi() -> OpenApiType {
         .openapi(api_doc.clone())
         .service(scope("/management").configure(init_base_api()))
         .service(scope("/api/v1").configure(init_api()))
+        .service(scope("/.well-known").configure(init_well_known_api()))
         .service(scope("/clusters").configure(init_cluster_api()))
         .split_for_parts();
     api
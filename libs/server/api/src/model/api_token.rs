@@ -0,0 +1,480 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::{traits::ObjectRedis, State};
+use crd::ProxyKubeApi;
+use deadpool_redis::redis::AsyncTypedCommands;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::instrument;
+
+use crate::{
+    helper::request_attributes::RequestAttributes, model::validator_error::ValidatorError,
+};
+
+/// Error of the creation of an API token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiTokenError {
+    /// The request can't be honored, answered with 400
+    Invalid(String),
+    /// Redis couldn't be reached
+    Storage(String),
+}
+
+impl std::fmt::Display for ApiTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiTokenError::Invalid(e) | ApiTokenError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Prefix of the tokens issued by ProxyAuthK8S, `pak8s_<id>_<secret>`
+pub const API_TOKEN_PREFIX: &str = "pak8s_";
+
+/// Longest lifetime of a token in seconds when `API_TOKEN_MAX_LIFETIME` is not set, 90 days
+const DEFAULT_API_TOKEN_MAX_LIFETIME: u64 = 90 * 24 * 3600;
+
+/// Verbs allowed to a read-only token
+const READ_ONLY_VERBS: [&str; 3] = ["get", "list", "watch"];
+/// Subresources reached with a GET that run commands or relay traffic, refused to a read-only token
+const READ_ONLY_DENIED_SUBRESOURCES: [&str; 4] = ["exec", "attach", "portforward", "proxy"];
+
+/// Access token issued by ProxyAuthK8S for automation, stored in redis with the hash of its secret
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ApiToken {
+    /// Identifier of the token, part of the token itself
+    pub id: String,
+    /// Human readable name of the token
+    pub name: String,
+    /// Username of the user who created the token
+    pub owner: String,
+    /// Groups of the owner when the token was created
+    pub groups: Vec<String>,
+    /// Creation date, unix timestamp in seconds
+    pub created_at: i64,
+    /// Expiration date, unix timestamp in seconds
+    pub expires_at: i64,
+    /// ProxyKubeApi the token can reach, as "namespace/name", among the clusters visible to the owner when it was created
+    pub clusters: Vec<String>,
+    /// Only allow the get, list and watch verbs
+    pub read_only: bool,
+    /// SHA-256 of the secret part of the token
+    pub secret_hash: String,
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+fn random_string(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn token_key(id: &str) -> String {
+    format!("api_token:{}", id)
+}
+
+fn owner_key(owner: &str) -> String {
+    format!("api_token_owner:{}", owner)
+}
+
+/// Longest lifetime of a token in seconds, from `API_TOKEN_MAX_LIFETIME`
+pub fn max_lifetime() -> u64 {
+    std::env::var("API_TOKEN_MAX_LIFETIME")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(DEFAULT_API_TOKEN_MAX_LIFETIME)
+}
+
+/// ProxyKubeApi visible to the groups, as "namespace/name", with whether they accept API tokens
+async fn visible_clusters(state: &State, groups: &[String]) -> Result<Vec<(String, bool)>, String> {
+    let mut conn = state.get_redis_conn().await.map_err(|e| e.to_string())?;
+    let keys: Vec<String> = conn
+        .keys("proxyk8sauth:*")
+        .await
+        .map_err(|e| e.to_string())?;
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+    let values: Vec<Option<String>> = conn.mget(&keys).await.map_err(|e| e.to_string())?;
+    Ok(values
+        .into_iter()
+        .flatten()
+        .filter_map(|value| ProxyKubeApi::from_json(&value))
+        .filter(|proxy| proxy.is_user_allowed(groups))
+        .map(|proxy| (proxy.to_path(), proxy.accepts_api_tokens()))
+        .collect())
+}
+
+/// Clusters a new token can reach, every visible cluster accepting API tokens when none is requested
+/// A requested cluster must be visible and trust the ProxyAuthK8S issuer (issued_token)
+fn select_clusters(
+    requested: Vec<String>,
+    visible: &[(String, bool)],
+) -> Result<Vec<String>, ApiTokenError> {
+    if requested.is_empty() {
+        let clusters: Vec<String> = visible
+            .iter()
+            .filter(|(_, accepts)| *accepts)
+            .map(|(cluster, _)| cluster.clone())
+            .collect();
+        if clusters.is_empty() && !visible.is_empty() {
+            return Err(ApiTokenError::Invalid(
+                "None of the visible clusters accepts API tokens, they need issued_token"
+                    .to_string(),
+            ));
+        }
+        return Ok(clusters);
+    }
+    for cluster in &requested {
+        match visible.iter().find(|(path, _)| path == cluster) {
+            None => {
+                return Err(ApiTokenError::Invalid(format!(
+                    "Cluster {} is not visible to the user",
+                    cluster
+                )))
+            }
+            Some((_, false)) => {
+                return Err(ApiTokenError::Invalid(format!(
+                    "Cluster {} doesn't accept API tokens, it needs issued_token",
+                    cluster
+                )))
+            }
+            Some((_, true)) => {}
+        }
+    }
+    Ok(requested)
+}
+
+impl ApiToken {
+    pub fn is_api_token(token: &str) -> bool {
+        token.starts_with(API_TOKEN_PREFIX)
+    }
+
+    /// Split a token into its id and secret
+    fn parse(token: &str) -> Option<(&str, &str)> {
+        let rest = token.strip_prefix(API_TOKEN_PREFIX)?;
+        let (id, secret) = rest.split_once('_')?;
+        if id.is_empty() || secret.is_empty() {
+            return None;
+        }
+        Some((id, secret))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now()
+    }
+
+    /// Check if the token can reach the cluster, given as "namespace/name"
+    pub fn allows_cluster(&self, cluster: &str) -> bool {
+        self.clusters.iter().any(|c| c == cluster)
+    }
+
+    /// Check if the token allows the request
+    /// A read-only token is refused the upgrades, such as exec over WebSocket, and the exec, attach, portforward and proxy subresources
+    pub fn allows_request(&self, attributes: &RequestAttributes, upgrade: bool) -> bool {
+        if !self.read_only {
+            return true;
+        }
+        READ_ONLY_VERBS.contains(&attributes.verb.as_str())
+            && !upgrade
+            && !attributes
+                .subresource
+                .as_deref()
+                .is_some_and(|subresource| READ_ONLY_DENIED_SUBRESOURCES.contains(&subresource))
+    }
+
+    /// Create a token, store it in redis and return it with its plain value
+    /// The plain value is only known at creation
+    /// The clusters are resolved against the ones visible to the owner now, all of those accepting API tokens if none is given,
+    /// so the token never reaches a cluster opened to the owner's groups afterwards
+    #[instrument(skip(state, groups))]
+    pub async fn create(
+        state: &State,
+        owner: String,
+        groups: Vec<String>,
+        name: String,
+        expires_in: u64,
+        clusters: Vec<String>,
+        read_only: bool,
+    ) -> Result<(Self, String), ApiTokenError> {
+        if owner.is_empty() {
+            return Err(ApiTokenError::Invalid(
+                "A user without username can't own API tokens".to_string(),
+            ));
+        }
+        if expires_in == 0 || expires_in > max_lifetime() {
+            return Err(ApiTokenError::Invalid(format!(
+                "expires_in must be between 1 and {} seconds",
+                max_lifetime()
+            )));
+        }
+        let visible = visible_clusters(state, &groups)
+            .await
+            .map_err(ApiTokenError::Storage)?;
+        let clusters = select_clusters(clusters, &visible)?;
+        let id = random_string(12).replace(['-', '_'], "x");
+        let secret = random_string(32);
+        let created_at = now();
+        let token = ApiToken {
+            id: id.clone(),
+            name,
+            owner,
+            groups,
+            created_at,
+            expires_at: created_at + expires_in as i64,
+            clusters,
+            read_only,
+            secret_hash: hash_secret(&secret),
+        };
+        let json =
+            serde_json::to_string(&token).map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+        let mut conn = state
+            .get_redis_conn()
+            .await
+            .map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+        conn.set_ex(token_key(&id), json, expires_in)
+            .await
+            .map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+        conn.sadd(owner_key(&token.owner), &id)
+            .await
+            .map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+        Ok((token, format!("{}{}_{}", API_TOKEN_PREFIX, id, secret)))
+    }
+
+    async fn get(state: &State, id: &str) -> Result<Option<Self>, String> {
+        let mut conn = state.get_redis_conn().await.map_err(|e| e.to_string())?;
+        match conn.get(token_key(id)).await.map_err(|e| e.to_string())? {
+            Some(json) => Ok(serde_json::from_str(&json).ok()),
+            None => Ok(None),
+        }
+    }
+
+    /// List the tokens of an owner, the expired tokens are removed from the owner index
+    #[instrument(skip(state))]
+    pub async fn list(state: &State, owner: &str) -> Result<Vec<Self>, String> {
+        let mut conn = state.get_redis_conn().await.map_err(|e| e.to_string())?;
+        let ids = conn
+            .smembers(owner_key(owner))
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut tokens = vec![];
+        for id in ids {
+            match Self::get(state, &id).await? {
+                Some(token) if !token.is_expired() => tokens.push(token),
+                _ => {
+                    conn.srem(owner_key(owner), &id)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        tokens.sort_by_key(|token| token.created_at);
+        Ok(tokens)
+    }
+
+    /// Revoke a token of an owner, return false if the owner has no token with this id
+    #[instrument(skip(state))]
+    pub async fn revoke(state: &State, owner: &str, id: &str) -> Result<bool, String> {
+        match Self::get(state, id).await? {
+            Some(token) if token.owner == owner => {}
+            _ => return Ok(false),
+        }
+        let mut conn = state.get_redis_conn().await.map_err(|e| e.to_string())?;
+        conn.del(token_key(id)).await.map_err(|e| e.to_string())?;
+        conn.srem(owner_key(owner), id)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+
+    /// Resolve a plain token, the secret is compared in constant time
+    #[instrument(skip(state, token))]
+    pub async fn verify(state: &State, token: &str) -> Result<Self, ValidatorError> {
+        let (id, secret) = Self::parse(token)
+            .ok_or(ValidatorError::Rejected("Malformed API token".to_string()))?;
+        let api_token = Self::get(state, id)
+            .await
+            .map_err(ValidatorError::Unavailable)?
+            .ok_or(ValidatorError::Rejected("Unknown API token".to_string()))?;
+        let secret_hash = hash_secret(secret);
+        if !bool::from(
+            api_token
+                .secret_hash
+                .as_bytes()
+                .ct_eq(secret_hash.as_bytes()),
+        ) {
+            return Err(ValidatorError::Rejected("Invalid API token".to_string()));
+        }
+        if api_token.is_expired() {
+            return Err(ValidatorError::Rejected("Expired API token".to_string()));
+        }
+        Ok(api_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http;
+
+    use super::*;
+
+    #[test]
+    fn test_select_clusters() {
+        let visible = vec![
+            ("dev/issued".to_string(), true),
+            ("dev/forwarded".to_string(), false),
+        ];
+        assert_eq!(
+            select_clusters(vec![], &visible).unwrap(),
+            vec!["dev/issued".to_string()]
+        );
+        assert_eq!(
+            select_clusters(vec!["dev/issued".to_string()], &visible).unwrap(),
+            vec!["dev/issued".to_string()]
+        );
+        assert!(matches!(
+            select_clusters(vec!["dev/forwarded".to_string()], &visible),
+            Err(ApiTokenError::Invalid(_))
+        ));
+        assert!(matches!(
+            select_clusters(vec!["dev/hidden".to_string()], &visible),
+            Err(ApiTokenError::Invalid(_))
+        ));
+        assert!(matches!(
+            select_clusters(vec![], &visible[1..]),
+            Err(ApiTokenError::Invalid(_))
+        ));
+        assert!(select_clusters(vec![], &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ApiToken::parse("pak8s_abc_secret_with_underscore"),
+            Some(("abc", "secret_with_underscore"))
+        );
+        assert_eq!(ApiToken::parse("pak8s_abc_"), None);
+        assert_eq!(ApiToken::parse("eyJhbGciOi.payload.sig"), None);
+    }
+
+    fn read_only_token() -> ApiToken {
+        ApiToken {
+            id: "abc".to_string(),
+            name: "ci".to_string(),
+            owner: "alice".to_string(),
+            groups: vec![],
+            created_at: 0,
+            expires_at: i64::MAX,
+            clusters: vec![],
+            read_only: true,
+            secret_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_allows_cluster() {
+        let token = ApiToken {
+            clusters: vec!["dev/c1".to_string()],
+            ..read_only_token()
+        };
+        assert!(token.allows_cluster("dev/c1"));
+        assert!(!token.allows_cluster("dev/c2"));
+        // The clusters are resolved at creation, an empty list reaches nothing
+        let token = read_only_token();
+        assert!(!token.allows_cluster("dev/c1"));
+    }
+
+    fn attributes(method: http::Method, path: &str) -> RequestAttributes {
+        RequestAttributes::new(&method, path, "")
+    }
+
+    #[test]
+    fn test_read_only_allows_request() {
+        let token = read_only_token();
+        assert!(token.allows_request(
+            &attributes(http::Method::GET, "/api/v1/namespaces/default/pods"),
+            false
+        ));
+        assert!(token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/log"
+            ),
+            false
+        ));
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::DELETE,
+                "/api/v1/namespaces/default/pods/nginx"
+            ),
+            false
+        ));
+        // exec and attach over WebSocket are GET requests
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/exec"
+            ),
+            true
+        ));
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/attach"
+            ),
+            false
+        ));
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/portforward"
+            ),
+            true
+        ));
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/proxy"
+            ),
+            false
+        ));
+        assert!(!token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/services/web/proxy"
+            ),
+            false
+        ));
+        // Any upgrade, whatever the path
+        assert!(!token.allows_request(
+            &attributes(http::Method::GET, "/api/v1/namespaces/default/pods"),
+            true
+        ));
+
+        let token = ApiToken {
+            read_only: false,
+            ..read_only_token()
+        };
+        assert!(token.allows_request(
+            &attributes(
+                http::Method::GET,
+                "/api/v1/namespaces/default/pods/nginx/exec"
+            ),
+            true
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use common::State;
+use crd::{
+    authentication_configuration::{
+        issued_token::IssuedTokenConfig, validate_against::ValidateAgainst,
+    },
+    ProxyKubeApi,
+};
+use deadpool_redis::redis::AsyncTypedCommands;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::model::{api_token::now, user::User};
+
+/// Seconds removed from the lifetime of the minted token before it is dropped from the cache
+const EXPIRY_MARGIN: u64 = 30;
+
+/// Subject of the minted token, prefixed by the source of the identity
+/// The same username from two validators (e.g. a static token and a webhook) must never be the same subject,
+/// an OIDC user is prefixed by its issuer like the kube-apiserver does (`<issuer>#<username>`)
+fn subject(proxy: &ProxyKubeApi, user: &User) -> String {
+    match &user.validator {
+        Some(ValidateAgainst::OidcProvider) => {
+            let issuer_url = proxy
+                .spec
+                .auth_config
+                .as_ref()
+                .map(|auth_config| auth_config.oidc_provider.issuer_url.as_str())
+                .unwrap_or_default();
+            format!("{}#{}", issuer_url, user.username)
+        }
+        Some(validator) => format!("{}:{}", validator.name(), user.username),
+        None if user.api_token.is_some() => format!("api_token:{}", user.username),
+        None => format!("proxyauthk8s:{}", user.username),
+    }
+}
+
+/// Claims of the minted token that don't depend on the time it is minted
+fn identity_claims(
+    state: &State,
+    proxy: &ProxyKubeApi,
+    config: &IssuedTokenConfig,
+    user: &User,
+) -> Value {
+    let mut claims = json!({
+        "iss": state.issuer.conf.issuer_url,
+        "sub": subject(proxy, user),
+        "aud": config.audience.clone().unwrap_or(proxy.to_path()),
+        "preferred_username": user.username,
+        "groups": config.map_groups(&user.groups),
+    });
+    if !user.email.is_empty() {
+        claims["email"] = json!(user.email);
+    }
+    claims
+}
+
+/// Key of the cached minted token, a change of the user groups or of the configuration gives another key
+fn cache_key(cluster: &str, config: &IssuedTokenConfig, claims: &Value) -> String {
+    format!(
+        "issued_token:{}:{:x}",
+        cluster,
+        Sha256::digest(format!("{}:{}", config.ttl, claims).as_bytes())
+    )
+}
+
+/// Mint the short-lived token presented to the cluster instead of the token of the user
+/// The token is cached per user and cluster until shortly before it expires, signing it on every request is costly
+#[instrument(skip(state, proxy, config, user), fields(cluster = %proxy.to_path()))]
+pub async fn mint_cluster_token(
+    state: &State,
+    proxy: &ProxyKubeApi,
+    config: &IssuedTokenConfig,
+    user: &User,
+) -> Result<String, String> {
+    if user.username.is_empty() {
+        return Err("Refusing to mint a cluster token without username".to_string());
+    }
+    let mut claims = identity_claims(state, proxy, config, user);
+    let key = cache_key(&proxy.to_path(), config, &claims);
+    let mut conn = match state.get_redis_conn().await {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            tracing::warn!(error = %e, "Couldn't read issued token cache");
+            None
+        }
+    };
+    if let Some(conn) = conn.as_mut() {
+        match conn.get(&key).await {
+            Ok(Some(token)) => return Ok(token),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "Couldn't read issued token cache"),
+        }
+    }
+
+    let issued_at = now();
+    claims["iat"] = json!(issued_at);
+    claims["nbf"] = json!(issued_at);
+    claims["exp"] = json!(issued_at + config.ttl as i64);
+    let token = state.issuer.sign(&state.client, &claims).await?;
+
+    let ttl = config.ttl.saturating_sub(EXPIRY_MARGIN);
+    if let Some(conn) = conn.as_mut().filter(|_| ttl > 0) {
+        if let Err(e) = conn.set_ex(&key, &token, ttl).await {
+            tracing::warn!(error = %e, "Couldn't cache the issued token");
+        }
+    }
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let config = IssuedTokenConfig {
+            audience: None,
+            ttl: 300,
+            groups_mapping: BTreeMap::new(),
+            groups_prefix: None,
+        };
+        let claims = |groups: &[&str]| json!({ "sub": "alice", "groups": groups });
+
+        let key = cache_key("dev/c1", &config, &claims(&["dev"]));
+        assert!(key.starts_with("issued_token:dev/c1:"));
+        assert_eq!(key, cache_key("dev/c1", &config, &claims(&["dev"])));
+        assert_ne!(
+            key,
+            cache_key("dev/c1", &config, &claims(&["dev", "admins"]))
+        );
+        let longer = IssuedTokenConfig {
+            ttl: 600,
+            ..config.clone()
+        };
+        assert_ne!(key, cache_key("dev/c1", &longer, &claims(&["dev"])));
+    }
+
+    #[test]
+    fn test_subject() {
+        let proxy: ProxyKubeApi = serde_json::from_value(json!({
+            "apiVersion": "weebo.si.rs/v1",
+            "kind": "ProxyKubeApi",
+            "metadata": { "name": "c1", "namespace": "dev" },
+            "spec": {
+                "cert": { "Insecure": true },
+                "service": { "ExternalService": { "url": "https://kube.example.com" } },
+                "auth_config": {
+                    "oidc_provider": { "issuer_url": "https://idp.example.com", "client_id": "c1" }
+                }
+            }
+        }))
+        .unwrap();
+        let user = |validator: Option<ValidateAgainst>| User {
+            username: "alice".to_string(),
+            email: String::new(),
+            groups: vec![],
+            uid: String::new(),
+            extra: BTreeMap::new(),
+            validator,
+            api_token: None,
+        };
+        assert_eq!(
+            subject(&proxy, &user(Some(ValidateAgainst::OidcProvider))),
+            "https://idp.example.com#alice"
+        );
+        assert_eq!(
+            subject(&proxy, &user(Some(ValidateAgainst::StaticTokens))),
+            "static_tokens:alice"
+        );
+        assert_eq!(
+            subject(&proxy, &user(Some(ValidateAgainst::Webhook))),
+            "webhook:alice"
+        );
+        assert_ne!(
+            subject(&proxy, &user(Some(ValidateAgainst::TokenReview))),
+            subject(&proxy, &user(Some(ValidateAgainst::Kubernetes)))
+        );
+    }
+}
@@ -1,2 +1,8 @@
+pub mod api_token;
+pub mod issued_token;
+pub mod token_claims;
+pub mod token_exchange;
 pub mod user;
 pub mod user_claim;
+pub mod validator_error;
+pub mod webhook_review;
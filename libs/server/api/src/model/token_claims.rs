@@ -0,0 +1,52 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Decode the payload of an already validated JWT without verifying the signature
+/// Return None if the token is not a JWT
+pub fn decode_validated_payload<T: DeserializeOwned>(token: &str) -> Option<T> {
+    let payload = token.split('.').nth(1)?;
+    URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()
+        .and_then(|payload| serde_json::from_slice(&payload).ok())
+}
+
+/// Authentication claims of a JWT, used to check the step-up requirements
+/// The payload is decoded without verifying the signature, the token MUST have been validated before
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AuthenticationClaims {
+    #[serde(default)]
+    pub acr: Option<String>,
+    #[serde(default)]
+    pub amr: Vec<String>,
+    #[serde(default)]
+    pub auth_time: Option<i64>,
+}
+
+impl AuthenticationClaims {
+    /// Decode the payload of an already validated JWT
+    /// Return the default (empty) claims if the token is not a JWT
+    pub fn from_validated_token(token: &str) -> Self {
+        decode_validated_payload(token).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_validated_token() {
+        let payload = URL_SAFE_NO_PAD
+            .encode(r#"{"sub":"user","acr":"gold","amr":["pwd","otp"],"auth_time":42}"#);
+        let claims = AuthenticationClaims::from_validated_token(&format!("e30.{}.sig", payload));
+        assert_eq!(claims.acr.as_deref(), Some("gold"));
+        assert_eq!(claims.amr, vec!["pwd".to_string(), "otp".to_string()]);
+        assert_eq!(claims.auth_time, Some(42));
+
+        assert_eq!(
+            AuthenticationClaims::from_validated_token("opaque-token"),
+            AuthenticationClaims::default()
+        );
+    }
+}
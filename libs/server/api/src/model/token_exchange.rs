@@ -0,0 +1,283 @@
+use common::{oidc_conf::OidcConf, State};
+use crd::{authentication_configuration::oidc_provider::OidcProvider, ProxyKubeApi};
+use deadpool_redis::redis::AsyncTypedCommands;
+use openidconnect::{core::CoreProviderMetadata, IssuerUrl};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::model::{
+    api_token::now, token_claims::decode_validated_payload, validator_error::ValidatorError,
+};
+
+const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+/// Seconds the token endpoint discovered for an issuer is reused
+const METADATA_CACHE_TTL: u64 = 3600;
+/// Seconds removed from the lifetime of the exchanged token before it is dropped from the cache
+const EXPIRY_MARGIN: u64 = 30;
+
+/// Answer of the token endpoint to a token exchange
+#[derive(Deserialize, Debug)]
+struct TokenExchangeResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct ExpirationClaim {
+    exp: Option<i64>,
+}
+
+/// Key of the cached exchanged token, the token of the user is only stored hashed
+fn cache_key(proxy: &ProxyKubeApi, token: &str) -> String {
+    format!(
+        "token_exchange:{}:{:x}",
+        proxy.to_path(),
+        Sha256::digest(token.as_bytes())
+    )
+}
+
+/// Seconds before the exp claim of a JWT, None for opaque tokens
+fn remaining_lifetime(token: &str) -> Option<u64> {
+    decode_validated_payload::<ExpirationClaim>(token)
+        .and_then(|claims| claims.exp)
+        .map(|exp| (exp - now()).max(0) as u64)
+}
+
+/// Time in seconds the exchanged token can be cached, from expires_in or the exp claim
+/// Never beyond the expiration of the token of the user, the cache must not outlive it
+fn cache_ttl(response: &TokenExchangeResponse, subject_token: &str) -> u64 {
+    let exchanged = [
+        response.expires_in,
+        remaining_lifetime(&response.access_token),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+    let lifetime = match (exchanged, remaining_lifetime(subject_token)) {
+        (Some(exchanged), Some(subject)) => exchanged.min(subject),
+        (exchanged, _) => exchanged.unwrap_or_default(),
+    };
+    lifetime.saturating_sub(EXPIRY_MARGIN)
+}
+
+/// Client authenticating the exchange, the client of the oidc_provider of the cluster
+/// The client of ProxyAuthK8S is only used when the cluster shares its issuer,
+/// its secret must never be sent to an issuer set in a ProxyKubeApi
+fn exchange_client<'a>(
+    server: &'a OidcConf,
+    oidc_provider: &'a OidcProvider,
+) -> Result<(&'a str, &'a str), ValidatorError> {
+    if let Some(secret) = oidc_provider.client_secret.as_deref() {
+        return Ok((&oidc_provider.client_id, secret));
+    }
+    match server.client_secret.as_deref() {
+        Some(secret)
+            if server.issuer_url.trim_end_matches('/')
+                == oidc_provider.issuer_url.trim_end_matches('/') =>
+        {
+            Ok((&server.client_id, secret))
+        }
+        _ => Err(ValidatorError::NotApplicable(
+            "Token exchange needs the client_secret of the oidc_provider".to_string(),
+        )),
+    }
+}
+
+/// Token endpoint of the issuer, the discovery is cached per issuer
+async fn token_endpoint(
+    state: &State,
+    conn: &mut deadpool_redis::Connection,
+    issuer_url: &str,
+) -> Result<String, ValidatorError> {
+    let key = format!("oidc_token_endpoint:{}", issuer_url);
+    match conn.get(&key).await {
+        Ok(Some(endpoint)) => return Ok(endpoint),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = %e, "Couldn't read OIDC metadata cache"),
+    }
+    let metadata = CoreProviderMetadata::discover_async(
+        IssuerUrl::new(issuer_url.to_string())
+            .map_err(|e| ValidatorError::Unavailable(e.to_string()))?,
+        &state.oidc_client.get_oidc_reqwest_client(),
+    )
+    .await
+    .map_err(|e| ValidatorError::Unavailable(format!("OIDC discovery failed: {}", e)))?;
+    let endpoint = metadata
+        .token_endpoint()
+        .ok_or(ValidatorError::Unavailable(
+            "The OIDC provider has no token endpoint".to_string(),
+        ))?
+        .url()
+        .to_string();
+    if let Err(e) = conn.set_ex(&key, &endpoint, METADATA_CACHE_TTL).await {
+        tracing::warn!(error = %e, "Couldn't cache the OIDC metadata");
+    }
+    Ok(endpoint)
+}
+
+/// Exchange the token of the user for a token whose audience is the cluster (RFC 8693)
+/// The exchanged token is cached until it expires
+/// A token refused by the token endpoint is Rejected, any other failure is Unavailable
+#[instrument(skip(state, proxy, oidc_provider, token), fields(cluster = %proxy.to_path()))]
+pub async fn exchange_token(
+    state: &State,
+    proxy: &ProxyKubeApi,
+    oidc_provider: &OidcProvider,
+    token: &str,
+) -> Result<String, ValidatorError> {
+    let config = oidc_provider
+        .token_exchange
+        .as_ref()
+        .ok_or(ValidatorError::NotApplicable(
+            "Token exchange is not configured".to_string(),
+        ))?;
+    let key = cache_key(proxy, token);
+    let mut conn = state
+        .get_redis_conn()
+        .await
+        .map_err(|e| ValidatorError::Unavailable(e.to_string()))?;
+    match conn.get(&key).await {
+        Ok(Some(exchanged)) => return Ok(exchanged),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = %e, "Couldn't read token exchange cache"),
+    }
+
+    let (client_id, client_secret) = exchange_client(&state.oidc_client, oidc_provider)?;
+    let token_endpoint = token_endpoint(state, &mut conn, &oidc_provider.issuer_url).await?;
+
+    let audience = oidc_provider.token_exchange_audience().unwrap_or_default();
+    let mut form = vec![
+        ("grant_type", TOKEN_EXCHANGE_GRANT_TYPE),
+        ("subject_token", token),
+        ("subject_token_type", ACCESS_TOKEN_TYPE),
+        ("requested_token_type", ACCESS_TOKEN_TYPE),
+        ("audience", audience.as_str()),
+    ];
+    if let Some(scope) = &config.scope {
+        form.push(("scope", scope.as_str()));
+    }
+    let response = state
+        .oidc_client
+        .get_reqwest_client()
+        .post(&token_endpoint)
+        .basic_auth(client_id, Some(client_secret))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| ValidatorError::Unavailable(format!("Token endpoint unreachable: {}", e)))?;
+    if response.status().is_server_error() {
+        return Err(ValidatorError::Unavailable(format!(
+            "Token endpoint failed with status {}",
+            response.status()
+        )));
+    }
+    if !response.status().is_success() {
+        return Err(ValidatorError::Rejected(format!(
+            "Token exchange refused with status {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        )));
+    }
+    let response: TokenExchangeResponse = response.json().await.map_err(|e| {
+        ValidatorError::Unavailable(format!("Invalid token exchange answer: {}", e))
+    })?;
+
+    let ttl = cache_ttl(&response, token);
+    if ttl > 0 {
+        if let Err(e) = conn.set_ex(&key, &response.access_token, ttl).await {
+            tracing::warn!(error = %e, "Couldn't cache the exchanged token");
+        }
+    }
+    Ok(response.access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    use super::*;
+
+    fn oidc_provider(issuer_url: &str, client_secret: Option<&str>) -> OidcProvider {
+        serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "issuer_url": issuer_url,
+            "client_id": "cluster-client",
+            "client_secret": client_secret,
+            "token_exchange": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_exchange_client() {
+        let server = OidcConf {
+            client_id: "server-client".to_string(),
+            client_secret: Some("server-secret".to_string()),
+            issuer_url: "https://idp.example.com/realms/main".to_string(),
+            scopes: String::new(),
+            audience: String::new(),
+            redirect_url: None,
+            claims: Default::default(),
+            cli_client_id: None,
+        };
+        assert_eq!(
+            exchange_client(
+                &server,
+                &oidc_provider("https://evil.example.com", Some("s"))
+            )
+            .unwrap(),
+            ("cluster-client", "s")
+        );
+        assert_eq!(
+            exchange_client(
+                &server,
+                &oidc_provider("https://idp.example.com/realms/main/", None)
+            )
+            .unwrap(),
+            ("server-client", "server-secret")
+        );
+        // The secret of the server is never sent to another issuer
+        assert!(matches!(
+            exchange_client(&server, &oidc_provider("https://evil.example.com", None)),
+            Err(ValidatorError::NotApplicable(_))
+        ));
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let jwt = |exp: i64| {
+            format!(
+                "e30.{}.sig",
+                URL_SAFE_NO_PAD.encode(format!(r#"{{"exp":{}}}"#, exp))
+            )
+        };
+        let response = TokenExchangeResponse {
+            access_token: "opaque".to_string(),
+            expires_in: Some(300),
+        };
+        assert_eq!(cache_ttl(&response, "opaque"), 270);
+
+        let response = TokenExchangeResponse {
+            access_token: jwt(now() + 120),
+            expires_in: None,
+        };
+        assert!((89..=90).contains(&cache_ttl(&response, "opaque")));
+
+        let response = TokenExchangeResponse {
+            access_token: "opaque".to_string(),
+            expires_in: None,
+        };
+        assert_eq!(cache_ttl(&response, &jwt(now() + 600)), 0);
+
+        // The shortest of expires_in, the exchanged exp and the subject exp wins
+        let response = TokenExchangeResponse {
+            access_token: jwt(now() + 200),
+            expires_in: Some(300),
+        };
+        assert!((169..=170).contains(&cache_ttl(&response, "opaque")));
+        assert!((89..=90).contains(&cache_ttl(&response, &jwt(now() + 120))));
+        assert_eq!(cache_ttl(&response, &jwt(now() - 10)), 0);
+    }
+}
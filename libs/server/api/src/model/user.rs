@@ -1,22 +1,56 @@
+use std::collections::BTreeMap;
+
 use actix_web::{
     error::{ErrorInternalServerError, ErrorUnauthorized},
     web, FromRequest,
 };
 use common::{oidc_conf::OidcConf, State};
-use crd::ProxyKubeApi;
-use k8s_openapi::api::authentication::v1::SelfSubjectReview;
+use crd::{
+    authentication_configuration::{
+        static_tokens::{hash_static_token, StaticTokenEntry},
+        validate_against::ValidateAgainst,
+    },
+    ProxyKubeApi,
+};
+use deadpool_redis::redis::AsyncTypedCommands;
+use k8s_openapi::api::authentication::v1::{
+    SelfSubjectReview, TokenReview, TokenReviewSpec, TokenReviewStatus, UserInfo,
+};
 use kube::{api::PostParams, Api};
 use openidconnect::{AccessToken, UserInfoError};
 use serde::{Deserialize, Serialize};
-use tracing::instrument;
+use serde_json::Value;
+use std::future::Future;
+use subtle::ConstantTimeEq;
+use tracing::{field, instrument, Span};
 
-use crate::{helper::extract_authorization_header, model::user_claim::GroupsUserInfoClaims};
+use crate::{
+    helper::extract_authorization_header,
+    helper::metrics::record_token_validation,
+    model::{
+        api_token::ApiToken, token_claims::decode_validated_payload,
+        user_claim::MappedUserInfoClaims, validator_error::ValidatorError,
+        webhook_review::review_token_with_webhook,
+    },
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct User {
     pub username: String,
     pub email: String,
     pub groups: Vec<String>,
+    /// Unique identifier of the user, only set by the validators based on a TokenReview
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub uid: String,
+    /// Extra information of the user, only set by the validators based on a TokenReview
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, Vec<String>>,
+    /// Validator that identified the user, None for the users of the ProxyAuthK8S server itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validator: Option<ValidateAgainst>,
+    /// API token used by the user, None when the user authenticated with an OIDC token
+    #[serde(skip)]
+    pub api_token: Option<ApiToken>,
 }
 
 impl FromRequest for User {
@@ -47,6 +81,26 @@ impl FromRequest for User {
                 }
             };
 
+            if ApiToken::is_api_token(token) {
+                return match ApiToken::verify(&oidc_handler, token).await {
+                    Ok(api_token) => {
+                        tracing::info!(
+                            target: "audit",
+                            action = "api_token.use",
+                            owner = %api_token.owner,
+                            token_id = %api_token.id,
+                            path = %req.path(),
+                            "API token used"
+                        );
+                        Ok(User::from_api_token(api_token))
+                    }
+                    Err(e) => {
+                        tracing::warn!("Error while verifying API token: {}", e);
+                        Err(e.into_actix_error())
+                    }
+                };
+            }
+
             match User::get_user_info_from_oidc_token(
                 token.to_string(),
                 oidc_handler.oidc_client.clone(),
@@ -60,7 +114,7 @@ impl FromRequest for User {
                 }
                 Err(e) => {
                     tracing::warn!("Error while getting user info from OIDC token: {}", e);
-                    Err(ErrorUnauthorized("Invalid user info from OIDC token"))
+                    Err(e.into_actix_error())
                 }
             }
         })
@@ -68,6 +122,36 @@ impl FromRequest for User {
 }
 
 impl User {
+    /// Build the user from the user info of a SelfSubjectReview or a TokenReview
+    pub fn from_user_info(user_info: UserInfo) -> Self {
+        let extra = user_info.extra.unwrap_or_default();
+        User {
+            username: user_info.username.unwrap_or_default(),
+            email: extra
+                .get("email")
+                .and_then(|emails| emails.first().cloned())
+                .unwrap_or_default(),
+            groups: user_info.groups.unwrap_or_default(),
+            uid: user_info.uid.unwrap_or_default(),
+            extra,
+            validator: None,
+            api_token: None,
+        }
+    }
+
+    /// Build the user owning an API token
+    pub fn from_api_token(api_token: ApiToken) -> Self {
+        User {
+            username: api_token.owner.clone(),
+            email: String::new(),
+            groups: api_token.groups.clone(),
+            uid: String::new(),
+            extra: BTreeMap::new(),
+            validator: None,
+            api_token: Some(api_token),
+        }
+    }
+
     pub fn is_in_group(&self, group: &str) -> bool {
         self.groups.iter().any(|g| g == group)
     }
@@ -77,36 +161,108 @@ impl User {
         ns: String,
         cluster: String,
         token: String,
-    ) -> Result<Option<Self>, String> {
+    ) -> Result<Option<Self>, ValidatorError> {
         let proxy: ProxyKubeApi = match state
             .get_object_from_redis("proxyk8sauth".to_string(), format!("{}/{}", ns, cluster))
             .await
         {
             Ok(Some(proxy)) => proxy,
-            Ok(None) => return Err("Proxy not found".to_string()),
-            Err(e) => return Err(format!("Error fetching proxy from Redis: {}", e)),
+            Ok(None) => return Err(ValidatorError::NotApplicable("Proxy not found".to_string())),
+            Err(e) => {
+                return Err(ValidatorError::Unavailable(format!(
+                    "Error fetching proxy from Redis: {}",
+                    e
+                )))
+            }
         };
         User::get_user_info_with_proxy(state, proxy, token).await
     }
 
-    #[instrument(skip(state, proxy, token))]
+    /// Try the validators of the proxy in order, the first one that identifies the token wins
+    /// A validator rejecting the token stops the chain, otherwise the most relevant failure is returned (unavailable over not applicable)
+    #[instrument(skip(state, proxy, token), fields(auth.validator = field::Empty))]
     pub async fn get_user_info_with_proxy(
         state: State,
         proxy: ProxyKubeApi,
         token: String,
-    ) -> Result<Option<Self>, String> {
-        if proxy.spec.auth_config.clone().is_none() {
-            return Ok(None);
-        }
-
-        match proxy.spec.auth_config.clone().unwrap().validate_against {
-            crd::authentication_configuration::validate_against::ValidateAgainst::OidcProvider => {
-                Self::auth_against_oidc_provider(state, proxy, token).await
+    ) -> Result<Option<Self>, ValidatorError> {
+        let auth_config = match proxy.spec.auth_config.clone() {
+            Some(auth_config) => auth_config,
+            None => return Ok(None),
+        };
+        let cluster = proxy.to_path();
+        Self::run_validators(&cluster, auth_config.validators(), |validator| {
+            let (state, proxy, token) = (state.clone(), proxy.clone(), token.clone());
+            let audiences = auth_config.token_review.audiences.clone();
+            async move {
+                match validator {
+                    ValidateAgainst::OidcProvider => {
+                        Self::auth_against_oidc_provider(state, proxy, token).await
+                    }
+                    ValidateAgainst::Kubernetes => {
+                        Self::auth_against_kubernetes(state, proxy, token).await
+                    }
+                    ValidateAgainst::Webhook => {
+                        Self::auth_against_webhook(state, proxy, token).await
+                    }
+                    ValidateAgainst::StaticTokens => {
+                        Self::auth_against_static_tokens(state, proxy, token).await
+                    }
+                    ValidateAgainst::TokenReview => {
+                        Self::auth_against_token_review(state, audiences, token).await
+                    }
+                }
             }
-            crd::authentication_configuration::validate_against::ValidateAgainst::Kubernetes => {
-                Self::auth_against_kubernetes(state, proxy, token).await
+        })
+        .await
+    }
+
+    /// Run the validators in order until one of them identifies the token or rejects it
+    /// A not applicable or unavailable validator falls through to the next one
+    async fn run_validators<F, Fut>(
+        cluster: &str,
+        validators: Vec<ValidateAgainst>,
+        mut validate: F,
+    ) -> Result<Option<Self>, ValidatorError>
+    where
+        F: FnMut(ValidateAgainst) -> Fut,
+        Fut: Future<Output = Result<Option<Self>, ValidatorError>>,
+    {
+        let mut last_error: Option<ValidatorError> = None;
+        for validator in validators {
+            match validate(validator.clone()).await {
+                Ok(Some(mut user)) => {
+                    record_token_validation(cluster, validator.name(), "success");
+                    Span::current().record("auth.validator", validator.name());
+                    user.validator = Some(validator);
+                    return Ok(Some(user));
+                }
+                Ok(None) => {
+                    record_token_validation(cluster, validator.name(), "rejected");
+                    return Err(ValidatorError::Rejected("No user found".to_string()));
+                }
+                Err(error @ ValidatorError::Rejected(_)) => {
+                    // The validator recognized the token and refused it, the next ones must not accept it
+                    tracing::debug!(validator = %validator, "Validator rejected the token: {}", error);
+                    record_token_validation(cluster, validator.name(), error.classification());
+                    return Err(error);
+                }
+                Err(error) => {
+                    tracing::debug!(validator = %validator, classification = error.classification(), "Validator failed: {}", error);
+                    record_token_validation(cluster, validator.name(), error.classification());
+                    if last_error
+                        .as_ref()
+                        .is_none_or(|previous| error.severity() >= previous.severity())
+                    {
+                        last_error = Some(error);
+                    }
+                }
             }
         }
+        match last_error {
+            Some(error) => Err(error),
+            None => Ok(None),
+        }
     }
 
     #[instrument(skip(state, proxy, token))]
@@ -114,7 +270,7 @@ impl User {
         state: State,
         proxy: ProxyKubeApi,
         token: String,
-    ) -> Result<Option<Self>, String> {
+    ) -> Result<Option<Self>, ValidatorError> {
         // Create a Kubernetes client using the provided token and targeting the proxy from the request
         let client = proxy
             .to_kube_client(
@@ -125,7 +281,10 @@ impl User {
             .await
             .map_err(|e| {
                 tracing::error!("Error while creating Kubernetes client: {}", e);
-                format!("Error while creating Kubernetes client: {}", e)
+                ValidatorError::Unavailable(format!(
+                    "Error while creating Kubernetes client: {}",
+                    e
+                ))
             })?;
         let review: Api<SelfSubjectReview> = Api::all(client);
 
@@ -139,24 +298,154 @@ impl User {
                     .unwrap_or_default()
                     .user_info
                     .unwrap_or_default();
-                Ok(Some(User {
-                    username: user_info.username.unwrap_or_default(),
-                    email: user_info
-                        .extra
-                        .and_then(|extra| {
-                            extra
-                                .get("email")
-                                .and_then(|emails| emails.first().cloned())
-                        })
-                        .unwrap_or_default(),
-                    groups: user_info.groups.unwrap_or_default(),
-                }))
+                Ok(Some(User::from_user_info(user_info)))
+            }
+            Err(kube::Error::Api(status)) if status.code == 401 || status.code == 403 => {
+                tracing::debug!("Token rejected by the Kubernetes API: {}", status);
+                Err(ValidatorError::Rejected(
+                    "Token rejected by the Kubernetes API".to_string(),
+                ))
             }
             Err(e) => {
                 tracing::warn!("Error while executing SelfSubjectReview request: {}", e);
-                Err("Invalid SelfSubjectReview response".to_string())
+                Err(ValidatorError::Unavailable(
+                    "Invalid SelfSubjectReview response".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Validate the token with a TokenReview against the management cluster, using the client of the ProxyAuthK8S server
+    #[instrument(skip(state, token))]
+    pub async fn auth_against_token_review(
+        state: State,
+        audiences: Vec<String>,
+        token: String,
+    ) -> Result<Option<Self>, ValidatorError> {
+        let review: Api<TokenReview> = Api::all(state.client.clone());
+        let token_review = TokenReview {
+            spec: TokenReviewSpec {
+                token: Some(token),
+                audiences: if audiences.is_empty() {
+                    None
+                } else {
+                    Some(audiences.clone())
+                },
+            },
+            ..Default::default()
+        };
+        let status = match review.create(&PostParams::default(), &token_review).await {
+            Ok(review_content) => review_content.status.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("Error while executing TokenReview request: {}", e);
+                return Err(ValidatorError::Unavailable(
+                    "Invalid TokenReview response".to_string(),
+                ));
+            }
+        };
+        Self::from_token_review_status(status, &audiences).map(Some)
+    }
+
+    /// Build the user from the status of a TokenReview sent to the management cluster
+    fn from_token_review_status(
+        status: TokenReviewStatus,
+        audiences: &[String],
+    ) -> Result<Self, ValidatorError> {
+        if status.authenticated != Some(true) {
+            tracing::debug!(error = ?status.error, "Token rejected by the TokenReview");
+            return Err(ValidatorError::Rejected(status.error.unwrap_or(
+                "Token not authenticated by the TokenReview".to_string(),
+            )));
+        }
+        // The API server returns the intersection of the requested audiences and the audiences of the token
+        if !audiences.is_empty() && status.audiences.unwrap_or_default().is_empty() {
+            return Err(ValidatorError::Rejected(
+                "Token not valid for the expected audiences".to_string(),
+            ));
+        }
+        Ok(User::from_user_info(status.user.unwrap_or_default()))
+    }
+
+    /// Validate the token by sending a TokenReview to the webhook configured on the proxy
+    #[instrument(skip(state, proxy, token))]
+    pub async fn auth_against_webhook(
+        state: State,
+        proxy: ProxyKubeApi,
+        token: String,
+    ) -> Result<Option<Self>, ValidatorError> {
+        let webhook = match proxy
+            .spec
+            .auth_config
+            .as_ref()
+            .and_then(|auth_config| auth_config.webhook.as_ref())
+        {
+            Some(webhook) => webhook,
+            None => {
+                return Err(ValidatorError::NotApplicable(
+                    "No webhook configured for this proxy".to_string(),
+                ))
+            }
+        };
+        let status = review_token_with_webhook(&state, &proxy, webhook, &token).await?;
+        if status.authenticated != Some(true) {
+            tracing::debug!(error = ?status.error, "Token rejected by the webhook");
+            return Err(ValidatorError::Rejected(
+                status
+                    .error
+                    .unwrap_or("Token not authenticated by the webhook".to_string()),
+            ));
+        }
+        Ok(Some(User::from_user_info(status.user.unwrap_or_default())))
+    }
+
+    /// Validate the token against the hashed static tokens loaded by the controller
+    /// Every token is compared in constant time so the answer does not leak which token matched
+    #[instrument(skip(state, proxy, token))]
+    pub async fn auth_against_static_tokens(
+        state: State,
+        proxy: ProxyKubeApi,
+        token: String,
+    ) -> Result<Option<Self>, ValidatorError> {
+        let mut conn = state
+            .get_redis_conn()
+            .await
+            .map_err(|e| ValidatorError::Unavailable(e.to_string()))?;
+        let entries_json = match conn
+            .get(proxy.to_static_tokens_identifier())
+            .await
+            .map_err(|e| ValidatorError::Unavailable(e.to_string()))?
+        {
+            Some(entries_json) => entries_json,
+            None => {
+                return Err(ValidatorError::NotApplicable(
+                    "No static tokens loaded for this proxy".to_string(),
+                ))
+            }
+        };
+        let entries: Vec<StaticTokenEntry> = serde_json::from_str(&entries_json)
+            .map_err(|e| ValidatorError::Unavailable(format!("Invalid static tokens: {}", e)))?;
+        let token_hash = hash_static_token(&token);
+        let mut found: Option<&StaticTokenEntry> = None;
+        for entry in &entries {
+            if bool::from(entry.token_hash.as_bytes().ct_eq(token_hash.as_bytes())) {
+                found = Some(entry);
             }
         }
+        match found {
+            Some(entry) => Ok(Some(User {
+                username: entry.username.clone(),
+                email: String::new(),
+                groups: entry.groups.clone(),
+                uid: entry.uid.clone(),
+                extra: BTreeMap::new(),
+                validator: None,
+                api_token: None,
+            })),
+            // An unknown token can be one of the next validators
+            None => Err(ValidatorError::NotApplicable(
+                "Token is not a known static token".to_string(),
+            )),
+        }
     }
 
     #[instrument(skip(state, proxy, token))]
@@ -164,7 +453,7 @@ impl User {
         state: State,
         proxy: ProxyKubeApi,
         token: String,
-    ) -> Result<Option<Self>, String> {
+    ) -> Result<Option<Self>, ValidatorError> {
         let oidc_conf = match proxy.get_oidc_conf(state.clone().into(), false, None) {
             Some(conf) => conf,
             None => {
@@ -172,10 +461,18 @@ impl User {
                     "No OIDC configuration found for proxy {:?}",
                     proxy.metadata.name
                 );
-                return Err("No OIDC configuration found for this proxy".to_string());
+                return Err(ValidatorError::NotApplicable(
+                    "No OIDC configuration found for this proxy".to_string(),
+                ));
             }
         };
         tracing::debug!("OIDC configuration found for proxy: {:?}", oidc_conf);
+        if is_foreign_jwt(&token, &oidc_conf.issuer_url) {
+            // A JWT of another issuer (e.g. a ServiceAccount token) is left to the next validators
+            return Err(ValidatorError::NotApplicable(
+                "Token issued by another issuer".to_string(),
+            ));
+        }
         Self::get_user_info_from_oidc_token(token, oidc_conf).await
     }
 
@@ -183,10 +480,10 @@ impl User {
     pub async fn get_user_info_from_oidc_token(
         token: String,
         oidc_conf: OidcConf,
-    ) -> Result<Option<Self>, String> {
+    ) -> Result<Option<Self>, ValidatorError> {
         let oidc_core = oidc_conf.get_oidc_core().await.map_err(|e| {
             tracing::error!("Error while getting OIDC core client: {}", e);
-            format!("Error while getting OIDC core client: {}", e)
+            ValidatorError::Unavailable(format!("Error while getting OIDC core client: {}", e))
         })?;
         let http_client = oidc_conf.get_oidc_reqwest_client();
 
@@ -198,36 +495,79 @@ impl User {
             Ok(req) => req,
             Err(e) => {
                 tracing::warn!("Error while creating user info request: {}", e);
-                return Err("Invalid user info request".to_string());
+                return Err(ValidatorError::NotApplicable(
+                    "Invalid user info request".to_string(),
+                ));
             }
         };
-        let user_info: GroupsUserInfoClaims = match user_claim_req.request_async(&http_client).await
+        let user_info: MappedUserInfoClaims = match user_claim_req.request_async(&http_client).await
         {
             Ok(info) => info,
+            Err(UserInfoError::Response(status, _, _)) if status.is_client_error() => {
+                tracing::debug!("Token rejected by the user info endpoint: {}", status);
+                return Err(ValidatorError::Rejected(
+                    "Token rejected by the OIDC provider".to_string(),
+                ));
+            }
+            Err(UserInfoError::ClaimsVerification(err)) => {
+                tracing::warn!("Error while verifying user info claims: {}", err);
+                return Err(ValidatorError::Rejected(
+                    "Invalid user info claims".to_string(),
+                ));
+            }
             Err(UserInfoError::Other(err)) => {
                 tracing::warn!("Error while executing user info request: {}", err);
-                return Err("Invalid user info response".to_string());
+                return Err(ValidatorError::Unavailable(
+                    "Invalid user info response".to_string(),
+                ));
             }
             Err(e) => {
                 tracing::warn!("Error while executing user info request: {:#?}", e);
-                return Err("Invalid user info response".to_string());
+                return Err(ValidatorError::Unavailable(
+                    "Invalid user info response".to_string(),
+                ));
             }
         };
-        let email = match user_info.email() {
-            Some(email) => email.to_string(),
+        let mut claims = match serde_json::to_value(&user_info) {
+            Ok(claims) => claims,
+            Err(e) => {
+                tracing::warn!("Error while reading user info claims: {}", e);
+                return Err(ValidatorError::Unavailable(
+                    "Invalid user info response".to_string(),
+                ));
+            }
+        };
+        // Claims missing from the user info (e.g. Keycloak realm_access) are looked up in the access token,
+        // it has just been accepted by the OIDC provider
+        if let (Some(claims), Some(Value::Object(token_claims))) = (
+            claims.as_object_mut(),
+            decode_validated_payload::<Value>(&token),
+        ) {
+            for (name, value) in token_claims {
+                claims.entry(name).or_insert(value);
+            }
+        }
+        let email = match oidc_conf.claims.email(&claims) {
+            Some(email) => email,
             None => {
-                tracing::warn!("No email found in user info");
+                tracing::warn!(claim = %oidc_conf.claims.email, "No email found in user info");
                 "".to_string()
             }
         };
-        let username = match user_info.preferred_username() {
-            Some(username) => username.to_string(),
+        let username = match oidc_conf.claims.username(&claims) {
+            Some(username) => username,
             None => {
-                tracing::warn!("No preferred username found in user info");
+                tracing::warn!(claim = %oidc_conf.claims.username, "No username found in user info");
                 "".to_string()
             }
         };
-        let groups = user_info.additional_claims().groups.clone();
+        let groups = match oidc_conf.claims.groups(&claims) {
+            Some(groups) => groups,
+            None => {
+                tracing::warn!(claim = %oidc_conf.claims.groups, "No groups found in user info");
+                vec![]
+            }
+        };
         tracing::info!("User groups: {:?}", groups);
         // In a real implementation, extract user info from request (e.g., headers, tokens)
         // Here we return a dummy user for illustration
@@ -235,6 +575,220 @@ impl User {
             username,
             email,
             groups,
+            uid: String::new(),
+            extra: BTreeMap::new(),
+            validator: None,
+            api_token: None,
         }))
     }
 }
+
+#[derive(Deserialize)]
+struct IssuerClaim {
+    iss: Option<String>,
+}
+
+/// Check if the token is a JWT issued by another issuer, the unverified claim is only used to pick the validator
+fn is_foreign_jwt(token: &str, issuer_url: &str) -> bool {
+    decode_validated_payload::<IssuerClaim>(token)
+        .and_then(|claims| claims.iss)
+        .is_some_and(|iss| iss.trim_end_matches('/') != issuer_url.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    use super::*;
+
+    fn token_review_status(authenticated: bool, audiences: Option<Vec<&str>>) -> TokenReviewStatus {
+        TokenReviewStatus {
+            authenticated: Some(authenticated),
+            audiences: audiences
+                .map(|audiences| audiences.into_iter().map(str::to_string).collect()),
+            user: Some(UserInfo {
+                username: Some("system:serviceaccount:ci:deployer".to_string()),
+                groups: Some(vec!["system:serviceaccounts".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_token_review_status() {
+        let audiences = vec!["proxyauthk8s".to_string()];
+        let user = User::from_token_review_status(
+            token_review_status(true, Some(vec!["proxyauthk8s"])),
+            &audiences,
+        )
+        .unwrap();
+        assert_eq!(user.username, "system:serviceaccount:ci:deployer");
+        assert!(User::from_token_review_status(token_review_status(true, None), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_from_token_review_status_audience_mismatch() {
+        let audiences = vec!["proxyauthk8s".to_string()];
+        // The API server answers without audience when none of the requested audiences is in the token
+        for status in [
+            token_review_status(true, None),
+            token_review_status(true, Some(vec![])),
+        ] {
+            assert!(matches!(
+                User::from_token_review_status(status, &audiences),
+                Err(ValidatorError::Rejected(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_from_token_review_status_not_authenticated() {
+        let mut status = token_review_status(false, None);
+        status.error = Some("token expired".to_string());
+        match User::from_token_review_status(status, &[]) {
+            Err(ValidatorError::Rejected(error)) => assert_eq!(error, "token expired"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut status = token_review_status(true, None);
+        status.authenticated = None;
+        assert!(matches!(
+            User::from_token_review_status(status, &[]),
+            Err(ValidatorError::Rejected(_))
+        ));
+    }
+
+    fn user(username: &str) -> User {
+        User {
+            username: username.to_string(),
+            email: String::new(),
+            groups: vec![],
+            uid: String::new(),
+            extra: BTreeMap::new(),
+            validator: None,
+            api_token: None,
+        }
+    }
+
+    /// Run the chain with a fixed answer per validator, returning the result and the validators called
+    async fn run_chain(
+        validators: Vec<ValidateAgainst>,
+        answer: impl Fn(&ValidateAgainst) -> Result<Option<User>, ValidatorError>,
+    ) -> (Result<Option<User>, ValidatorError>, Vec<ValidateAgainst>) {
+        let mut called = vec![];
+        let result = User::run_validators("dev/c1", validators, |validator| {
+            called.push(validator.clone());
+            std::future::ready(answer(&validator))
+        })
+        .await;
+        (result, called)
+    }
+
+    #[tokio::test]
+    async fn test_run_validators_rejected_stops_the_chain() {
+        let (result, called) = run_chain(
+            vec![ValidateAgainst::OidcProvider, ValidateAgainst::TokenReview],
+            |validator| match validator {
+                ValidateAgainst::OidcProvider => {
+                    Err(ValidatorError::Rejected("expired".to_string()))
+                }
+                _ => Ok(Some(user("ci"))),
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ValidatorError::Rejected(_))));
+        assert_eq!(called, vec![ValidateAgainst::OidcProvider]);
+    }
+
+    #[tokio::test]
+    async fn test_run_validators_not_applicable_falls_through() {
+        let (result, called) = run_chain(
+            vec![ValidateAgainst::OidcProvider, ValidateAgainst::TokenReview],
+            |validator| match validator {
+                ValidateAgainst::OidcProvider => Err(ValidatorError::NotApplicable(
+                    "Token issued by another issuer".to_string(),
+                )),
+                _ => Ok(Some(user("ci"))),
+            },
+        )
+        .await;
+        let user = result.unwrap().unwrap();
+        assert_eq!(user.username, "ci");
+        assert_eq!(user.validator, Some(ValidateAgainst::TokenReview));
+        assert_eq!(called.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_validators_unavailable() {
+        let (result, called) = run_chain(
+            vec![
+                ValidateAgainst::Webhook,
+                ValidateAgainst::StaticTokens,
+                ValidateAgainst::OidcProvider,
+            ],
+            |validator| match validator {
+                ValidateAgainst::Webhook => Err(ValidatorError::Unavailable("timeout".to_string())),
+                _ => Err(ValidatorError::NotApplicable("unknown".to_string())),
+            },
+        )
+        .await;
+        assert_eq!(called.len(), 3);
+        let error = result.unwrap_err();
+        assert!(matches!(error, ValidatorError::Unavailable(_)));
+        assert_eq!(
+            error.into_actix_error().as_response_error().status_code(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // An unavailable validator doesn't prevent the next ones from identifying the token
+        let (result, _) = run_chain(
+            vec![ValidateAgainst::Webhook, ValidateAgainst::StaticTokens],
+            |validator| match validator {
+                ValidateAgainst::Webhook => Err(ValidatorError::Unavailable("timeout".to_string())),
+                _ => Ok(Some(user("lab"))),
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap().unwrap().username, "lab");
+    }
+
+    #[tokio::test]
+    async fn test_run_validators_order() {
+        let validators = vec![
+            ValidateAgainst::StaticTokens,
+            ValidateAgainst::Kubernetes,
+            ValidateAgainst::OidcProvider,
+        ];
+        let (result, called) = run_chain(validators.clone(), |validator| match validator {
+            ValidateAgainst::StaticTokens => {
+                Err(ValidatorError::NotApplicable("unknown".to_string()))
+            }
+            validator => Ok(Some(user(validator.name()))),
+        })
+        .await;
+        // Both remaining validators would accept the token, the first one wins
+        assert_eq!(result.unwrap().unwrap().username, "kubernetes");
+        assert_eq!(called, validators[..2].to_vec());
+
+        let (result, called) = run_chain(vec![], |_| Ok(Some(user("nobody")))).await;
+        assert!(result.unwrap().is_none());
+        assert!(called.is_empty());
+    }
+
+    #[test]
+    fn test_is_foreign_jwt() {
+        let jwt = |payload: &str| format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(payload));
+        let issuer = "https://idp.example.com/realms/main";
+        assert!(!is_foreign_jwt(
+            &jwt(r#"{"iss":"https://idp.example.com/realms/main/"}"#),
+            issuer
+        ));
+        assert!(is_foreign_jwt(
+            &jwt(r#"{"iss":"https://kubernetes.default.svc"}"#),
+            issuer
+        ));
+        // Opaque tokens and JWTs without issuer are left to the OIDC provider
+        assert!(!is_foreign_jwt("opaque-token", issuer));
+        assert!(!is_foreign_jwt(&jwt(r#"{"sub":"alice"}"#), issuer));
+    }
+}
@@ -1,10 +1,16 @@
+use std::collections::HashMap;
+
 use openidconnect::{core::CoreGenderClaim, AdditionalClaims, UserInfoClaims};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
-pub struct GroupsAdditionalClaims {
-    pub groups: Vec<String>,
+/// Every non standard claim returned by the user info endpoint
+/// The username, email and groups are then resolved using the claim mapping of the OIDC configuration
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MappedAdditionalClaims {
+    #[serde(flatten)]
+    pub claims: HashMap<String, Value>,
 }
-impl AdditionalClaims for GroupsAdditionalClaims {}
+impl AdditionalClaims for MappedAdditionalClaims {}
 
-pub type GroupsUserInfoClaims = UserInfoClaims<GroupsAdditionalClaims, CoreGenderClaim>;
+pub type MappedUserInfoClaims = UserInfoClaims<MappedAdditionalClaims, CoreGenderClaim>;
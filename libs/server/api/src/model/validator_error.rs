@@ -0,0 +1,43 @@
+use actix_web::error::{ErrorServiceUnavailable, ErrorUnauthorized};
+use thiserror::Error;
+
+/// Failure of a token validator, classified so the validator chain knows how to go on
+#[derive(Error, Debug)]
+pub enum ValidatorError {
+    /// The validator recognized the token and refused it, the chain stops
+    #[error("Token rejected: {0}")]
+    Rejected(String),
+    /// The validator can't be used with the current configuration or the token is not one of its tokens, the next validator is tried
+    #[error("Validator not applicable: {0}")]
+    NotApplicable(String),
+    /// The validator backend could not be reached or answered with an error, the next validator is tried
+    #[error("Validator unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl ValidatorError {
+    /// Classification of the failure, used in traces and metrics
+    pub fn classification(&self) -> &'static str {
+        match self {
+            ValidatorError::Rejected(_) => "rejected",
+            ValidatorError::NotApplicable(_) => "not_applicable",
+            ValidatorError::Unavailable(_) => "unavailable",
+        }
+    }
+
+    /// Relevance of the failure when every validator failed
+    pub fn severity(&self) -> u8 {
+        match self {
+            ValidatorError::NotApplicable(_) => 0,
+            ValidatorError::Rejected(_) => 1,
+            ValidatorError::Unavailable(_) => 2,
+        }
+    }
+
+    pub fn into_actix_error(&self) -> actix_web::Error {
+        match self {
+            ValidatorError::Unavailable(_) => ErrorServiceUnavailable(self.to_string()),
+            _ => ErrorUnauthorized(self.to_string()),
+        }
+    }
+}
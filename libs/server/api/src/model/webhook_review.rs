@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use common::State;
+use crd::{authentication_configuration::webhook::WebhookConfig, ProxyKubeApi};
+use deadpool_redis::redis::AsyncTypedCommands;
+use k8s_openapi::api::authentication::v1::{TokenReview, TokenReviewSpec, TokenReviewStatus};
+use kube::ResourceExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::{
+    helper::tls::build_client_tls_config,
+    model::{
+        api_token::now, token_claims::decode_validated_payload, validator_error::ValidatorError,
+    },
+};
+
+const WEBHOOK_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time a webhook client is reused before its CA is read again, a rotated CA is picked up after it
+const CLIENT_TTL: Duration = Duration::from_secs(600);
+/// Seconds a rejected token is cached, a token rejected by a transient webhook error isn't refused for long
+const NEGATIVE_CACHE_TTL: u64 = 10;
+
+/// Client of each webhook, built once with its CA instead of on every cache miss
+static CLIENTS: LazyLock<Mutex<HashMap<String, (Instant, reqwest::Client)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Deserialize)]
+struct ExpirationClaim {
+    exp: Option<i64>,
+}
+
+/// Key of the cached webhook answer, the token is only stored hashed
+fn cache_key(proxy: &ProxyKubeApi, token: &str) -> String {
+    format!(
+        "webhook_token_review:{}:{:x}",
+        proxy.to_path(),
+        Sha256::digest(token.as_bytes())
+    )
+}
+
+async fn get_cached_status(state: &State, key: &str) -> Option<TokenReviewStatus> {
+    let mut conn = state.get_redis_conn().await.ok()?;
+    match conn.get(key).await {
+        Ok(Some(json)) => serde_json::from_str(&json).ok(),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!(error = %e, "Couldn't read webhook cache");
+            None
+        }
+    }
+}
+
+async fn set_cached_status(state: &State, key: &str, status: &TokenReviewStatus, ttl: u64) {
+    if ttl == 0 {
+        return;
+    }
+    let json = match serde_json::to_string(status) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    match state.get_redis_conn().await {
+        Ok(mut conn) => {
+            if let Err(e) = conn.set_ex(key, json, ttl).await {
+                tracing::warn!(error = %e, "Couldn't write webhook cache");
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "Couldn't get redis connection"),
+    }
+}
+
+/// Key of the webhook client, a change of the URL or of the CA source gives another client
+fn client_key(proxy: &ProxyKubeApi, webhook: &WebhookConfig) -> String {
+    format!(
+        "{}:{}:{}",
+        proxy.to_path(),
+        webhook.url,
+        serde_json::to_string(&webhook.cert).unwrap_or_default()
+    )
+}
+
+/// Client of the webhook, with its CA and timeouts
+async fn webhook_client(
+    state: &State,
+    proxy: &ProxyKubeApi,
+    webhook: &WebhookConfig,
+) -> Result<reqwest::Client, ValidatorError> {
+    let key = client_key(proxy, webhook);
+    if let Some((created, client)) = CLIENTS.lock().unwrap().get(&key) {
+        if created.elapsed() < CLIENT_TTL {
+            return Ok(client.clone());
+        }
+    }
+    let cert_pem = match &webhook.cert {
+        Some(cert) => cert
+            .get_cert(state.client.clone(), &proxy.namespace().unwrap_or_default())
+            .await
+            .map_err(|e| ValidatorError::Unavailable(format!("Couldn't get webhook CA: {}", e)))?,
+        None => None,
+    };
+    let tls_config = build_client_tls_config(cert_pem).map_err(ValidatorError::Unavailable)?;
+    let client = reqwest::ClientBuilder::new()
+        .use_preconfigured_tls(tls_config)
+        .connect_timeout(WEBHOOK_CONNECT_TIMEOUT)
+        .timeout(WEBHOOK_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| ValidatorError::Unavailable(e.to_string()))?;
+    CLIENTS
+        .lock()
+        .unwrap()
+        .insert(key, (Instant::now(), client.clone()));
+    Ok(client)
+}
+
+/// Time in seconds the answer of the webhook can be cached
+/// A rejected token is only cached briefly, an authenticated one never beyond the exp claim of the token
+fn status_cache_ttl(status: &TokenReviewStatus, token: &str, cache_ttl: u64) -> u64 {
+    if status.authenticated != Some(true) {
+        return cache_ttl.min(NEGATIVE_CACHE_TTL);
+    }
+    match decode_validated_payload::<ExpirationClaim>(token).and_then(|claims| claims.exp) {
+        Some(exp) => cache_ttl.min((exp - now()).max(0) as u64),
+        None => cache_ttl,
+    }
+}
+
+/// POST a TokenReview to the webhook of the proxy and return its status
+/// Authenticated answers are cached for cache_ttl seconds at most, rejected ones for a few seconds
+#[instrument(skip(state, proxy, webhook, token), fields(webhook.url = %webhook.url))]
+pub async fn review_token_with_webhook(
+    state: &State,
+    proxy: &ProxyKubeApi,
+    webhook: &WebhookConfig,
+    token: &str,
+) -> Result<TokenReviewStatus, ValidatorError> {
+    let key = cache_key(proxy, token);
+    if let Some(status) = get_cached_status(state, &key).await {
+        tracing::debug!(authenticated = ?status.authenticated, "Webhook answer found in cache");
+        return Ok(status);
+    }
+
+    let client = webhook_client(state, proxy, webhook).await?;
+    let review = TokenReview {
+        spec: TokenReviewSpec {
+            token: Some(token.to_string()),
+            audiences: None,
+        },
+        ..Default::default()
+    };
+    let response = client
+        .post(&webhook.url)
+        .json(&review)
+        .send()
+        .await
+        .map_err(|e| ValidatorError::Unavailable(format!("Webhook unreachable: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(ValidatorError::Unavailable(format!(
+            "Webhook answered with status {}",
+            response.status()
+        )));
+    }
+    let review: TokenReview = response
+        .json()
+        .await
+        .map_err(|e| ValidatorError::Unavailable(format!("Invalid webhook answer: {}", e)))?;
+    let status = review.status.unwrap_or_default();
+    let ttl = status_cache_ttl(&status, token, webhook.cache_ttl);
+    set_cached_status(state, &key, &status, ttl).await;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use crd::certificate::CertSource;
+
+    use super::*;
+
+    fn status(authenticated: bool) -> TokenReviewStatus {
+        TokenReviewStatus {
+            authenticated: Some(authenticated),
+            ..Default::default()
+        }
+    }
+
+    fn jwt(exp: i64) -> String {
+        format!(
+            "e30.{}.sig",
+            URL_SAFE_NO_PAD.encode(format!(r#"{{"exp":{}}}"#, exp))
+        )
+    }
+
+    #[test]
+    fn test_status_cache_ttl() {
+        assert_eq!(status_cache_ttl(&status(true), "opaque-token", 120), 120);
+        assert_eq!(status_cache_ttl(&status(false), "opaque-token", 120), 10);
+        assert_eq!(status_cache_ttl(&status(false), "opaque-token", 5), 5);
+        assert_eq!(status_cache_ttl(&status(false), "opaque-token", 0), 0);
+        // Never cached beyond the expiration of the token
+        let ttl = status_cache_ttl(&status(true), &jwt(now() + 30), 120);
+        assert!((29..=30).contains(&ttl));
+        assert_eq!(status_cache_ttl(&status(true), &jwt(now() + 600), 120), 120);
+        assert_eq!(status_cache_ttl(&status(true), &jwt(now() - 10), 120), 0);
+    }
+
+    #[test]
+    fn test_client_key() {
+        let proxy: ProxyKubeApi = serde_json::from_value(serde_json::json!({
+            "apiVersion": "weebo.si.rs/v1",
+            "kind": "ProxyKubeApi",
+            "metadata": { "name": "c1", "namespace": "dev" },
+            "spec": {
+                "cert": { "Insecure": true },
+                "service": { "ExternalService": { "url": "https://kube.example.com" } }
+            }
+        }))
+        .unwrap();
+        let webhook = WebhookConfig {
+            url: "https://webhook.example.com/authenticate".to_string(),
+            cert: None,
+            cache_ttl: 120,
+        };
+        let key = client_key(&proxy, &webhook);
+        assert_eq!(key, client_key(&proxy, &webhook.clone()));
+        let with_ca = WebhookConfig {
+            cert: Some(CertSource::Cert("Y2E=".to_string())),
+            ..webhook.clone()
+        };
+        assert_ne!(key, client_key(&proxy, &with_ca));
+        let other_url = WebhookConfig {
+            url: "https://other.example.com/authenticate".to_string(),
+            ..webhook
+        };
+        assert_ne!(key, client_key(&proxy, &other_url));
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::{get, web::Data, HttpResponse, Responder};
+use common::State;
+use serde::{Deserialize, Serialize};
+use tracing::{error, instrument};
+use utoipa::ToSchema;
+
+/// Discovery document of ProxyAuthK8S as an OIDC issuer.
+///
+/// Only the fields needed to verify the tokens minted for the downstream clusters.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+/// OIDC discovery document of the issuer.
+///
+/// Used by the downstream clusters to trust the tokens minted by ProxyAuthK8S, return 404 if the issuer is disabled.
+#[utoipa::path(
+    tag = "well_known",
+    responses(
+        (status = 200, description = "Discovery document.", body = OpenIdConfiguration),
+        (status = 404, description = "The issuer is disabled."),
+    )
+)]
+#[get("/openid-configuration")]
+#[instrument(name = "openid_configuration", skip(state))]
+pub async fn openid_configuration(state: Data<State>) -> impl Responder {
+    let conf = &state.issuer.conf;
+    if !conf.enabled {
+        return HttpResponse::NotFound().finish();
+    }
+    let to_strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+    HttpResponse::Ok().json(OpenIdConfiguration {
+        issuer: conf.issuer_url.clone(),
+        jwks_uri: conf.jwks_uri(),
+        response_types_supported: to_strings(&["id_token"]),
+        subject_types_supported: to_strings(&["public"]),
+        id_token_signing_alg_values_supported: to_strings(&["RS256"]),
+        claims_supported: to_strings(&[
+            "iss",
+            "sub",
+            "aud",
+            "exp",
+            "iat",
+            "nbf",
+            "preferred_username",
+            "email",
+            "groups",
+        ]),
+    })
+}
+
+/// Public keys of the issuer.
+///
+/// Every key of the signing Secret is published, so the tokens signed before a rotation stay valid.
+#[utoipa::path(
+    tag = "well_known",
+    responses(
+        (status = 200, description = "JSON Web Key Set."),
+        (status = 404, description = "The issuer is disabled."),
+        (status = 503, description = "The signing keys couldn't be loaded."),
+    )
+)]
+#[get("/jwks.json")]
+#[instrument(name = "jwks", skip(state))]
+pub async fn jwks(state: Data<State>) -> impl Responder {
+    if !state.issuer.conf.enabled {
+        return HttpResponse::NotFound().finish();
+    }
+    match state.issuer.jwks(&state.client).await {
+        Ok(jwks) => HttpResponse::Ok().json(jwks),
+        Err(e) => {
+            error!(error = %e, "couldn't load the issuer signing keys");
+            HttpResponse::ServiceUnavailable().finish()
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Path of the claims used to build the user from the OIDC provider answer
+/// A path is either the exact name of a claim or a dotted path to a nested claim,
+/// for example `realm_access.roles` or `resource_access.my-client.roles`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClaimMapping {
+    pub username: String,
+    pub email: String,
+    pub groups: String,
+}
+
+impl Default for ClaimMapping {
+    fn default() -> Self {
+        Self {
+            username: "preferred_username".to_string(),
+            email: "email".to_string(),
+            groups: "groups".to_string(),
+        }
+    }
+}
+
+impl ClaimMapping {
+    /// An unset or empty variable keeps the default claim, as the chart renders unset values as empty strings
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        let claim = |name: &str, default: String| {
+            var(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or(default)
+        };
+        Self {
+            username: claim("OIDC_USERNAME_CLAIM", default.username),
+            email: claim("OIDC_EMAIL_CLAIM", default.email),
+            groups: claim("OIDC_GROUPS_CLAIM", default.groups),
+        }
+    }
+
+    pub fn username(&self, claims: &Value) -> Option<String> {
+        resolve_claim(claims, &self.username).and_then(claim_to_string)
+    }
+
+    pub fn email(&self, claims: &Value) -> Option<String> {
+        resolve_claim(claims, &self.email).and_then(claim_to_string)
+    }
+
+    pub fn groups(&self, claims: &Value) -> Option<Vec<String>> {
+        resolve_claim(claims, &self.groups).map(claim_to_list)
+    }
+}
+
+/// Find a claim by its exact name, or by following a dotted path
+pub fn resolve_claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return None;
+    }
+    if let Some(value) = claims.get(path) {
+        return Some(value);
+    }
+    path.split('.')
+        .try_fold(claims, |current, segment| current.get(segment))
+}
+
+fn claim_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Convert a claim to a list, a string claim is split on commas
+fn claim_to_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(values) => values.iter().filter_map(claim_to_string).collect(),
+        Value::String(value) => value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        _ => claim_to_string(value).into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_claim() {
+        let claims = json!({
+            "preferred_username": "max",
+            "https://example.com/groups": ["admin"],
+            "realm_access": { "roles": ["dev", "ops"] },
+            "resource_access": { "proxy-auth-k8s": { "roles": ["viewer"] } },
+        });
+        assert_eq!(
+            resolve_claim(&claims, "preferred_username"),
+            Some(&json!("max"))
+        );
+        assert_eq!(
+            resolve_claim(&claims, "https://example.com/groups"),
+            Some(&json!(["admin"]))
+        );
+        assert_eq!(
+            resolve_claim(&claims, "resource_access.proxy-auth-k8s.roles"),
+            Some(&json!(["viewer"]))
+        );
+        assert_eq!(resolve_claim(&claims, "realm_access.missing"), None);
+        assert_eq!(resolve_claim(&claims, ""), None);
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let mapping = ClaimMapping::from_vars(|name| match name {
+            "OIDC_USERNAME_CLAIM" => Some("upn".to_string()),
+            "OIDC_EMAIL_CLAIM" => Some("".to_string()),
+            "OIDC_GROUPS_CLAIM" => Some("  ".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            mapping,
+            ClaimMapping {
+                username: "upn".to_string(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(ClaimMapping::from_vars(|_| None), ClaimMapping::default());
+    }
+
+    #[test]
+    fn test_claim_mapping() {
+        let mapping = ClaimMapping {
+            username: "upn".to_string(),
+            email: "mail".to_string(),
+            groups: "realm_access.roles".to_string(),
+        };
+        let claims = json!({
+            "upn": "max@example.com",
+            "realm_access": { "roles": ["dev", "ops"] },
+        });
+        assert_eq!(
+            mapping.username(&claims),
+            Some("max@example.com".to_string())
+        );
+        assert_eq!(mapping.email(&claims), None);
+        assert_eq!(
+            mapping.groups(&claims),
+            Some(vec!["dev".to_string(), "ops".to_string()])
+        );
+
+        let claims = json!({ "roles": "admin, dev,,ops" });
+        let mapping = ClaimMapping {
+            groups: "roles".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            mapping.groups(&claims),
+            Some(vec![
+                "admin".to_string(),
+                "dev".to_string(),
+                "ops".to_string()
+            ])
+        );
+    }
+}
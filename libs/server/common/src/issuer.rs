@@ -0,0 +1,229 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client};
+use openidconnect::{
+    core::{CoreJsonWebKeySet, CoreJwsSigningAlgorithm, CoreRsaPrivateSigningKey},
+    JsonWebKeyId, PrivateSigningKey,
+};
+use serde_json::{json, Value};
+use tracing::{info, instrument, warn};
+
+/// Key of the Secret holding the kid of the key used to sign the new tokens
+pub const ACTIVE_KEY: &str = "active";
+/// Suffix of the keys of the Secret holding a PKCS#1 PEM RSA private key, the kid is the key without the suffix
+pub const SIGNING_KEY_SUFFIX: &str = ".pem";
+
+/// Configuration of ProxyAuthK8S acting as an OIDC issuer for the downstream clusters
+#[derive(Clone, Debug)]
+pub struct IssuerConf {
+    pub enabled: bool,
+    /// Issuer of the minted tokens, the discovery document is served under `{issuer_url}/.well-known`
+    pub issuer_url: String,
+    /// Secret holding the signing keys
+    pub secret_name: String,
+    pub secret_namespace: String,
+    /// Time in seconds before the Secret is read again, to pick up a rotation
+    pub refresh_interval: u64,
+}
+
+impl IssuerConf {
+    pub fn from_env(base_url: &str, namespace: &str) -> Self {
+        Self {
+            enabled: std::env::var("ISSUER_ENABLED")
+                .unwrap_or("false".to_string())
+                .parse()
+                .unwrap_or(false),
+            issuer_url: std::env::var("ISSUER_URL")
+                .unwrap_or(base_url.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            secret_name: std::env::var("ISSUER_SIGNING_SECRET")
+                .unwrap_or("proxy-auth-k8s-issuer".to_string()),
+            secret_namespace: std::env::var("ISSUER_SIGNING_SECRET_NAMESPACE")
+                .unwrap_or(namespace.to_string()),
+            refresh_interval: std::env::var("ISSUER_KEYS_REFRESH_INTERVAL")
+                .unwrap_or("60".to_string())
+                .parse()
+                .unwrap_or(60),
+        }
+    }
+
+    pub fn jwks_uri(&self) -> String {
+        format!("{}/.well-known/jwks.json", self.issuer_url)
+    }
+}
+
+/// Keys loaded from the Secret
+/// Every key is published in the JWKS so the tokens signed before a rotation stay valid until they expire
+struct SigningKeys {
+    active_kid: String,
+    active: CoreRsaPrivateSigningKey,
+    jwks: CoreJsonWebKeySet,
+    loaded_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct Issuer {
+    pub conf: IssuerConf,
+    keys: Arc<RwLock<Option<Arc<SigningKeys>>>>,
+}
+
+impl Issuer {
+    pub fn new(conf: IssuerConf) -> Self {
+        Self {
+            conf,
+            keys: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Get the signing keys, read the Secret again once the refresh interval is over
+    /// If the Secret can't be read, the previously loaded keys are kept
+    #[instrument(skip(self, client))]
+    async fn signing_keys(&self, client: &Client) -> Result<Arc<SigningKeys>, String> {
+        if !self.conf.enabled {
+            return Err("The issuer is not enabled".to_string());
+        }
+        let current = self.keys.read().map_err(|e| e.to_string())?.clone();
+        if let Some(keys) = &current {
+            if keys.loaded_at.elapsed() < Duration::from_secs(self.conf.refresh_interval) {
+                return Ok(keys.clone());
+            }
+        }
+        match self.load_keys(client).await {
+            Ok(keys) => {
+                let keys = Arc::new(keys);
+                if current
+                    .as_ref()
+                    .is_none_or(|current| current.active_kid != keys.active_kid)
+                {
+                    info!(kid = %keys.active_kid, "Issuer signing key loaded");
+                }
+                *self.keys.write().map_err(|e| e.to_string())? = Some(keys.clone());
+                Ok(keys)
+            }
+            Err(e) => match current {
+                Some(keys) => {
+                    warn!(error = %e, "Couldn't reload the issuer signing keys, keeping the previous ones");
+                    Ok(keys)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn load_keys(&self, client: &Client) -> Result<SigningKeys, String> {
+        let secret = Api::<Secret>::namespaced(client.clone(), &self.conf.secret_namespace)
+            .get(&self.conf.secret_name)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Couldn't get the issuer Secret {}/{}: {}",
+                    self.conf.secret_namespace, self.conf.secret_name, e
+                )
+            })?;
+        let data = secret
+            .data
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, String::from_utf8_lossy(&value.0).to_string()))
+            .collect();
+        let (active_kid, keys) = parse_signing_keys(&data)?;
+        let mut active = None;
+        let mut verification_keys = vec![];
+        for (kid, pem) in keys {
+            let key =
+                CoreRsaPrivateSigningKey::from_pem(&pem, Some(JsonWebKeyId::new(kid.clone())))
+                    .map_err(|e| format!("Invalid signing key {}: {}", kid, e))?;
+            verification_keys.push(key.as_verification_key());
+            if kid == active_kid {
+                active = Some(key);
+            }
+        }
+        Ok(SigningKeys {
+            active: active.ok_or(format!("Active signing key {} not found", active_kid))?,
+            active_kid,
+            jwks: CoreJsonWebKeySet::new(verification_keys),
+            loaded_at: Instant::now(),
+        })
+    }
+
+    /// Public keys used to verify the minted tokens
+    pub async fn jwks(&self, client: &Client) -> Result<CoreJsonWebKeySet, String> {
+        Ok(self.signing_keys(client).await?.jwks.clone())
+    }
+
+    /// Sign the claims with the active key, RS256
+    #[instrument(skip(self, client, claims))]
+    pub async fn sign(&self, client: &Client, claims: &Value) -> Result<String, String> {
+        let keys = self.signing_keys(client).await?;
+        let header = json!({ "alg": "RS256", "typ": "JWT", "kid": keys.active_kid });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = keys
+            .active
+            .sign(
+                &CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+                message.as_bytes(),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)))
+    }
+}
+
+/// Find the signing keys of the Secret and the kid of the active one
+/// The active key is the one named by the `active` key, or the last one by name
+fn parse_signing_keys(
+    data: &BTreeMap<String, String>,
+) -> Result<(String, Vec<(String, String)>), String> {
+    let keys: Vec<(String, String)> = data
+        .iter()
+        .filter_map(|(key, value)| {
+            key.strip_suffix(SIGNING_KEY_SUFFIX)
+                .map(|kid| (kid.to_string(), value.clone()))
+        })
+        .collect();
+    let active_kid = match data.get(ACTIVE_KEY) {
+        Some(kid) => kid.trim().to_string(),
+        None => keys
+            .last()
+            .map(|(kid, _)| kid.clone())
+            .ok_or("The issuer Secret doesn't contain any signing key".to_string())?,
+    };
+    if !keys.iter().any(|(kid, _)| kid == &active_kid) {
+        return Err(format!("Active signing key {} not found", active_kid));
+    }
+    Ok((active_kid, keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signing_keys() {
+        let mut data = BTreeMap::from([
+            ("2025-01.pem".to_string(), "old".to_string()),
+            ("2025-06.pem".to_string(), "new".to_string()),
+            ("README".to_string(), "ignored".to_string()),
+        ]);
+        let (active, keys) = parse_signing_keys(&data).unwrap();
+        assert_eq!(active, "2025-06");
+        assert_eq!(keys.len(), 2);
+
+        data.insert(ACTIVE_KEY.to_string(), "2025-01\n".to_string());
+        assert_eq!(parse_signing_keys(&data).unwrap().0, "2025-01");
+
+        data.insert(ACTIVE_KEY.to_string(), "missing".to_string());
+        assert!(parse_signing_keys(&data).is_err());
+        assert!(parse_signing_keys(&BTreeMap::new()).is_err());
+    }
+}
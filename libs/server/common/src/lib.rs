@@ -2,12 +2,14 @@ use std::{env, sync::Arc};
 
 use kube::Client;
 use rustls::pki_types::{pem::PemObject as _, CertificateDer, PrivateKeyDer};
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use deadpool_redis::{redis::AsyncTypedCommands, Config, Pool, Runtime};
 
 use crate::traits::ObjectRedis;
 
+pub mod claim_mapping;
+pub mod issuer;
 pub mod oidc_conf;
 pub mod oidc_error;
 pub mod traits;
@@ -17,6 +19,7 @@ pub struct State {
     pub client: Client,
     redis: Pool,
     pub oidc_client: oidc_conf::OidcConf,
+    pub issuer: issuer::Issuer,
     pub oidc_cluster_redirect_base_url: String,
     pub oidc_front_redirect_base_url: String,
     pub is_leader: Arc<std::sync::atomic::AtomicBool>,
@@ -46,10 +49,21 @@ impl State {
             .unwrap_or("https://localhost:4200/auth/callback/".to_string());
         let lease_namespace = env::var("LEASE_NAMESPACE").unwrap_or("default".to_string());
         let lease_name = env::var("HOSTNAME").unwrap_or("NOT_A_POD".to_string());
+        let issuer = issuer::Issuer::new(issuer::IssuerConf::from_env(
+            &oidc_cluster_redirect_base_url,
+            &lease_namespace,
+        ));
+        if issuer.conf.enabled {
+            match issuer.jwks(&client).await {
+                Ok(_) => info!(issuer = %issuer.conf.issuer_url, "Issuer signing keys loaded"),
+                Err(e) => warn!(error = %e, "Issuer signing keys not loaded yet"),
+            }
+        }
         Self {
             client,
             redis: pool,
             oidc_client,
+            issuer,
             oidc_cluster_redirect_base_url,
             oidc_front_redirect_base_url,
             is_leader: Arc::new(std::sync::atomic::AtomicBool::new(false)),
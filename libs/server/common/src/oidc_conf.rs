@@ -8,7 +8,7 @@ use openidconnect::{
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use crate::oidc_error::OidcError;
+use crate::{claim_mapping::ClaimMapping, oidc_error::OidcError};
 
 pub type CoreClientFront = CoreClient<
     EndpointSet,
@@ -27,6 +27,11 @@ pub struct OidcConf {
     pub scopes: String,
     pub audience: String,
     pub redirect_url: Option<String>,
+    #[serde(default)]
+    pub claims: ClaimMapping,
+    /// Public client used by the kubectl plugin to log in, the server client is used if not set
+    #[serde(default)]
+    pub cli_client_id: Option<String>,
 }
 
 impl Debug for OidcConf {
@@ -38,6 +43,8 @@ impl Debug for OidcConf {
             .field("scopes", &self.scopes)
             .field("audience", &self.audience)
             .field("redirect_url", &self.redirect_url)
+            .field("claims", &self.claims)
+            .field("cli_client_id", &self.cli_client_id)
             .finish()
     }
 }
@@ -57,6 +64,8 @@ impl OidcConf {
         let scopes = std::env::var("OIDC_SCOPES").unwrap_or("openid email profile".to_string());
         let audience = std::env::var("OIDC_AUDIENCE").unwrap_or("proxy-auth-k8s".to_string());
         let redirect_url = std::env::var("OIDC_REDIRECT_URL").ok();
+        let claims = ClaimMapping::from_env();
+        let cli_client_id = std::env::var("OIDC_CLI_CLIENT_ID").ok();
         Self {
             client_id,
             client_secret,
@@ -64,6 +73,8 @@ impl OidcConf {
             scopes,
             audience,
             redirect_url,
+            claims,
+            cli_client_id,
         }
     }
 
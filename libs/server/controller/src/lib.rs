@@ -4,11 +4,12 @@ use std::{
 };
 
 use common::State;
-use crd::ProxyKubeApi;
+use crd::{authentication_configuration::static_tokens::STATIC_TOKENS_LABEL, ProxyKubeApi};
 use futures::StreamExt;
+use k8s_openapi::api::core::v1::Secret;
 use kube::{
-    runtime::{watcher::Config, Controller},
-    Api,
+    runtime::{reflector::ObjectRef, watcher::Config, Controller},
+    Api, ResourceExt,
 };
 use kube_leader_election::{LeaseLock, LeaseLockParams, LeaseLockResult};
 use tokio::time::interval;
@@ -80,9 +81,40 @@ pub async fn run(state: State) {
     }
 
     let controller_state = Arc::new(state.clone());
+    let controller = Controller::new(proxy_kube_apis.clone(), Config::default().any_semantic());
+    let proxy_store = controller.store();
 
     tokio::select! {
-        _ = Controller::new(proxy_kube_apis.clone(), Config::default().any_semantic())
+        // Reconcile the proxies using a static tokens Secret when the Secret changes, reloading the tokens
+        // Only the labeled Secrets are watched, the other Secrets of the cluster are never read
+        _ = controller
+            .watches(
+                Api::<Secret>::all(client.clone()),
+                Config::default().labels(&format!("{}=true", STATIC_TOKENS_LABEL)),
+                move |secret| {
+                    let secret_ns = secret.namespace().unwrap_or_default();
+                    let secret_name = secret.name_any();
+                    proxy_store
+                        .state()
+                        .into_iter()
+                        .filter(|proxy| {
+                            proxy
+                                .spec
+                                .auth_config
+                                .as_ref()
+                                .and_then(|auth_config| auth_config.static_tokens.as_ref())
+                                .is_some_and(|static_tokens| {
+                                    static_tokens.references(
+                                        &secret_ns,
+                                        &secret_name,
+                                        &proxy.namespace().unwrap_or_default(),
+                                    )
+                                })
+                        })
+                        .map(|proxy| ObjectRef::from_obj(proxy.as_ref()))
+                        .collect::<Vec<_>>()
+                },
+            )
             .shutdown_on_signal()
             .run(
                 main_reconcile_proxy_kube_api,
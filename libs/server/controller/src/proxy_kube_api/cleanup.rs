@@ -19,6 +19,17 @@ pub async fn clean_proxy_kube_api(proxy: &ProxyKubeApi, ctx: Arc<State>) -> Resu
                 info!("Failed to delete ProxyKubeApi: {}. Error: {}", id, err);
             }
         }
+        let static_tokens_id = proxy.to_static_tokens_identifier();
+        if let Err(err) = cmd("DEL")
+            .arg(&static_tokens_id)
+            .query_async::<()>(&mut redis_conn)
+            .await
+        {
+            info!(
+                "Failed to delete static tokens: {}. Error: {}",
+                static_tokens_id, err
+            );
+        }
     }
     Ok(Action::await_change()) // No need to requeue, object is being deleted
 }
@@ -17,6 +17,7 @@ use crate::error::Result;
 
 pub mod cleanup;
 pub mod reconcile;
+pub mod static_tokens;
 
 #[instrument(skip(ctx))]
 pub fn error_policy_proxy_kube_api(
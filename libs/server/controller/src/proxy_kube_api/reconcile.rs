@@ -5,7 +5,10 @@ use kube::{api::PatchParams, runtime::controller::Action, Api};
 use std::sync::Arc;
 use tracing::{info, instrument, warn};
 
-use crate::error::{ControllerError, Result};
+use crate::{
+    error::{ControllerError, Result},
+    proxy_kube_api::static_tokens::{clear_static_tokens, sync_static_tokens},
+};
 
 #[instrument(skip(proxy, ctx), fields(name = %proxy.to_identifier()))]
 pub async fn reconcile_proxy_kube_api(proxy: &ProxyKubeApi, ctx: Arc<State>) -> Result<Action> {
@@ -40,6 +43,28 @@ pub async fn reconcile_proxy_kube_api(proxy: &ProxyKubeApi, ctx: Arc<State>) ->
         }
     };
 
+    if new_status.error.is_some() {
+        // an invalid configuration must not keep serving the tokens loaded before
+        if let Err(e) = clear_static_tokens(proxy, ctx.clone()).await {
+            tracing::error!(
+                "Failed to remove static tokens of ProxyKubeApi {}: {}",
+                proxy.to_identifier(),
+                e
+            );
+        }
+    } else if let Err(e) = sync_static_tokens(proxy, ctx.clone()).await {
+        tracing::error!(
+            "Failed to load static tokens of ProxyKubeApi {}: {}",
+            proxy.to_identifier(),
+            e
+        );
+        new_status = ProxyKubeApiStatus::new(
+            false,
+            None,
+            Some(format!("Failed to load static tokens: {}", e)),
+        );
+    }
+
     if new_status.error.is_none() {
         new_status = match proxy.clone().is_reachable(ctx.clone()).await {
             Ok(reachable) => {
@@ -0,0 +1,154 @@
+use common::State;
+use crd::{authentication_configuration::static_tokens::StaticTokenEntry, ProxyKubeApi};
+use deadpool_redis::redis::{aio::ConnectionLike, cmd};
+use kube::ResourceExt;
+use std::sync::Arc;
+use tracing::{info, instrument};
+
+/// Load the static tokens of the proxy from its Secret and store them hashed in redis
+/// Remove them from redis if the proxy does not use static tokens anymore
+/// Fail closed : the stored tokens are removed when the Secret can't be loaded, a deleted or invalid Secret revokes them
+#[instrument(skip(proxy, ctx), fields(name = %proxy.to_identifier()))]
+pub async fn sync_static_tokens(proxy: &ProxyKubeApi, ctx: Arc<State>) -> Result<(), String> {
+    let mut redis_conn = ctx.get_redis_conn().await.map_err(|e| e.to_string())?;
+    let loaded = match proxy
+        .spec
+        .auth_config
+        .as_ref()
+        .and_then(|auth_config| auth_config.static_tokens.as_ref())
+    {
+        Some(static_tokens) => static_tokens
+            .load(ctx.client.clone(), &proxy.namespace().unwrap_or_default())
+            .await
+            .map(Some),
+        None => Ok(None),
+    };
+    let count = store_static_tokens(
+        &mut redis_conn,
+        &proxy.to_static_tokens_identifier(),
+        loaded,
+    )
+    .await?;
+    if let Some(count) = count {
+        info!(
+            "Loaded {} static tokens for ProxyKubeApi {}",
+            count,
+            proxy.to_identifier()
+        );
+    }
+    Ok(())
+}
+
+/// Remove the static tokens of a proxy whose configuration is invalid
+#[instrument(skip(proxy, ctx), fields(name = %proxy.to_identifier()))]
+pub async fn clear_static_tokens(proxy: &ProxyKubeApi, ctx: Arc<State>) -> Result<(), String> {
+    let mut redis_conn = ctx.get_redis_conn().await.map_err(|e| e.to_string())?;
+    store_static_tokens(
+        &mut redis_conn,
+        &proxy.to_static_tokens_identifier(),
+        Ok(None),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Store the loaded tokens and return their number, the key is deleted when there is no token or when loading failed
+async fn store_static_tokens(
+    redis_conn: &mut impl ConnectionLike,
+    key: &str,
+    loaded: Result<Option<Vec<StaticTokenEntry>>, String>,
+) -> Result<Option<usize>, String> {
+    let entries = match loaded {
+        Ok(Some(entries)) => entries,
+        Ok(None) | Err(_) => {
+            cmd("DEL")
+                .arg(key)
+                .query_async::<()>(redis_conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            return loaded.map(|_| None);
+        }
+    };
+    let entries_json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    cmd("SET")
+        .arg(key)
+        .arg(&entries_json)
+        .query_async::<()>(redis_conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(entries.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use deadpool_redis::redis::{Cmd, Pipeline, RedisFuture, Value};
+
+    use super::*;
+
+    /// Connection recording the commands sent to redis
+    #[derive(Default)]
+    struct RecordingConnection {
+        commands: Vec<Vec<String>>,
+    }
+
+    impl ConnectionLike for RecordingConnection {
+        fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+            self.commands.push(
+                cmd.args_iter()
+                    .map(|arg| match arg {
+                        deadpool_redis::redis::Arg::Simple(arg) => {
+                            String::from_utf8_lossy(arg).to_string()
+                        }
+                        _ => "cursor".to_string(),
+                    })
+                    .collect(),
+            );
+            Box::pin(async { Ok(Value::Okay) })
+        }
+
+        fn req_packed_commands<'a>(
+            &'a mut self,
+            _cmd: &'a Pipeline,
+            _offset: usize,
+            _count: usize,
+        ) -> RedisFuture<'a, Vec<Value>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_static_tokens_fail_closed() {
+        let key = "static_tokens:dev/c1".to_string();
+        let entry = StaticTokenEntry {
+            token_hash: "hash".to_string(),
+            username: "alice".to_string(),
+            uid: "1".to_string(),
+            groups: vec![],
+        };
+
+        let mut conn = RecordingConnection::default();
+        assert_eq!(
+            store_static_tokens(&mut conn, &key, Ok(Some(vec![entry])))
+                .await
+                .unwrap(),
+            Some(1)
+        );
+        assert_eq!(conn.commands[0][..2], ["SET".to_string(), key.clone()]);
+
+        // A Secret that can't be loaded anymore revokes the stored tokens
+        let error = store_static_tokens(&mut conn, &key, Err("secret not found".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error, "secret not found");
+        assert_eq!(conn.commands[1], vec!["DEL".to_string(), key.clone()]);
+
+        store_static_tokens(&mut conn, &key, Ok(None))
+            .await
+            .unwrap();
+        assert_eq!(conn.commands[2], vec!["DEL".to_string(), key]);
+    }
+}
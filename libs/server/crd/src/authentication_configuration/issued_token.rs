@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::default::default_issued_token_ttl;
+
+/// Maximum lifetime of a token minted by ProxyAuthK8S, in seconds
+pub const MAX_ISSUED_TOKEN_TTL: u64 = 3600;
+
+/// Replace the token of the user by a short-lived JWT minted by ProxyAuthK8S before forwarding the request
+/// The target cluster only has to trust the issuer of ProxyAuthK8S through its structured authentication configuration,
+/// the discovery document and the JWKS are served under `/.well-known`
+/// The sub claim is the username prefixed by its source, `<validator>:<username>` or `<issuer>#<username>` for the oidc_provider,
+/// the preferred_username claim is the username as is
+/// Require the token validation to be enabled
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct IssuedTokenConfig {
+    /// Audience of the minted token, must match the audience of the cluster JWT authenticator
+    /// Default : the namespace/name of the ProxyKubeApi
+    pub audience: Option<String>,
+    /// Lifetime of the minted token in seconds, at most 3600
+    /// Default : 300
+    #[serde(default = "default_issued_token_ttl")]
+    pub ttl: u64,
+    /// Rename the groups of the user, the groups not listed are kept as is
+    /// Default : no rename
+    #[serde(default)]
+    pub groups_mapping: BTreeMap<String, String>,
+    /// Prefix added to every group after the rename
+    /// Default : no prefix
+    pub groups_prefix: Option<String>,
+}
+
+impl IssuedTokenConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ttl == 0 || self.ttl > MAX_ISSUED_TOKEN_TTL {
+            return Err(format!(
+                "issued_token ttl must be between 1 and {} seconds",
+                MAX_ISSUED_TOKEN_TTL
+            ));
+        }
+        if self
+            .audience
+            .as_ref()
+            .is_some_and(|audience| audience.is_empty())
+        {
+            return Err("issued_token audience must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Apply the rename and the prefix to the groups of the user
+    pub fn map_groups(&self, groups: &[String]) -> Vec<String> {
+        let prefix = self.groups_prefix.as_deref().unwrap_or_default();
+        groups
+            .iter()
+            .map(|group| {
+                let group = self.groups_mapping.get(group).unwrap_or(group);
+                format!("{}{}", prefix, group)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_groups() {
+        let config = IssuedTokenConfig {
+            audience: None,
+            ttl: default_issued_token_ttl(),
+            groups_mapping: BTreeMap::from([("admins".to_string(), "cluster-admins".to_string())]),
+            groups_prefix: Some("proxy:".to_string()),
+        };
+        assert_eq!(
+            config.map_groups(&["admins".to_string(), "dev".to_string()]),
+            vec!["proxy:cluster-admins".to_string(), "proxy:dev".to_string()]
+        );
+        assert!(config.validate().is_ok());
+        assert!(IssuedTokenConfig { ttl: 0, ..config }.validate().is_err());
+    }
+}
@@ -1,15 +1,23 @@
 pub mod claim_mappings;
 pub mod claim_validation_rules;
+pub mod issued_token;
 pub mod issuer;
 pub mod jwt_authenticator;
 pub mod oidc_provider;
+pub mod static_tokens;
+pub mod step_up_requirement;
+pub mod token_exchange;
+pub mod token_review;
 pub mod user_validation_rule;
 pub mod validate_against;
+pub mod webhook;
 
 use crate::{
     authentication_configuration::{
-        jwt_authenticator::JWTAuthenticator, oidc_provider::OidcProvider,
-        validate_against::ValidateAgainst,
+        issued_token::IssuedTokenConfig, jwt_authenticator::JWTAuthenticator,
+        oidc_provider::OidcProvider, static_tokens::StaticTokensConfig,
+        step_up_requirement::StepUpRequirement, token_review::TokenReviewConfig,
+        validate_against::ValidateAgainst, webhook::WebhookConfig,
     },
     default::{default_disabled, default_empty_array, default_validate_against},
 };
@@ -22,6 +30,19 @@ pub struct AuthenticationConfiguration {
     #[serde(default = "default_empty_array::<JWTAuthenticator>")]
     pub jwt: Vec<JWTAuthenticator>,
     pub oidc_provider: OidcProvider,
+    /// Configuration of the TokenReview validator, used when validating against TokenReview
+    /// Default : no audience check
+    #[serde(default)]
+    pub token_review: TokenReviewConfig,
+    /// Configuration of the webhook validator, required when validating against Webhook
+    pub webhook: Option<WebhookConfig>,
+    /// Static tokens loaded from a Secret, required when validating against StaticTokens
+    /// Meant for dev/lab environments without an IdP
+    pub static_tokens: Option<StaticTokensConfig>,
+    /// Mint a short-lived JWT for the cluster instead of forwarding the token of the user
+    /// Required to use the API tokens of ProxyAuthK8S with the cluster
+    /// Default : the token of the user is forwarded as is
+    pub issued_token: Option<IssuedTokenConfig>,
 
     /// Disable validation of the token against the configured JWT authenticators, OIDC provider or Kubernetes API
     /// If the AuthenticationConfiguration is not provided, does not validate the token against any of the configured JWT authenticators, OIDC provider or Kubernetes API
@@ -32,20 +53,144 @@ pub struct AuthenticationConfiguration {
     /// Default : OidcProvider if enabled, otherwise JwtAuthenticators if configured, otherwise Kubernetes
     #[serde(default = "default_validate_against")]
     pub validate_against: ValidateAgainst,
+    /// Ordered list of validators, the first one that positively identifies the token wins
+    /// A validator refusing a token it recognizes stops the chain, a token it doesn't know goes to the next one
+    /// Allow mixed populations, for example humans with OIDC tokens and CI jobs with ServiceAccount tokens
+    /// TokenReview and Webhook require issued_token or token_exchange, their tokens are never forwarded to the cluster
+    /// Default : empty, only validate_against is used
+    #[serde(default = "default_empty_array::<ValidateAgainst>")]
+    pub validators: Vec<ValidateAgainst>,
+    /// Step-up authentication requirements, checked against the acr, amr and auth_time claims of the token
+    /// The first requirement matching the verb and resource of the request must be satisfied
+    /// Require the token validation to be enabled
+    /// Default : empty, no step-up required
+    #[serde(default = "default_empty_array::<StepUpRequirement>")]
+    pub step_up: Vec<StepUpRequirement>,
 }
 
 impl AuthenticationConfiguration {
     pub fn validate(&self) -> Result<(), String> {
         // Validate that if validate_against is OidcProvider, then the OIDC provider is enabled
-        if let ValidateAgainst::OidcProvider = self.validate_against {
-            if !self.oidc_provider.enabled {
+        if self.validators().contains(&ValidateAgainst::OidcProvider) && !self.oidc_provider.enabled
+        {
+            return Err(
+                "validate_against is set to OidcProvider but the OIDC provider is not enabled"
+                    .to_string(),
+            );
+        }
+        let validators = self.validators();
+        match &self.webhook {
+            Some(webhook) => webhook.validate()?,
+            None if validators.contains(&ValidateAgainst::Webhook) => {
                 return Err(
-                    "validate_against is set to OidcProvider but the OIDC provider is not enabled"
-                        .to_string(),
+                    "validate_against is set to Webhook but no webhook is configured".to_string(),
+                )
+            }
+            None => {}
+        }
+        if self.static_tokens.is_none() && validators.contains(&ValidateAgainst::StaticTokens) {
+            return Err(
+                "validate_against is set to StaticTokens but no static_tokens is configured"
+                    .to_string(),
+            );
+        }
+        if validators
+            .iter()
+            .enumerate()
+            .any(|(index, validator)| validators[..index].contains(validator))
+        {
+            return Err("validators must not contain the same validator twice".to_string());
+        }
+        if !self.step_up.is_empty() && self.disable_validation {
+            return Err(
+                "step_up requirements are defined but the token validation is disabled".to_string(),
+            );
+        }
+        if self.oidc_provider.token_exchange.is_some() && self.issued_token.is_some() {
+            return Err(
+                "token_exchange and issued_token can't be both defined, only one token can be forwarded"
+                    .to_string(),
+            );
+        }
+        if self.issued_token.is_none()
+            && self.oidc_provider.token_exchange.is_none()
+            && validators.iter().any(|validator| {
+                matches!(
+                    validator,
+                    ValidateAgainst::TokenReview | ValidateAgainst::Webhook
+                )
+            })
+        {
+            // The token validated by the management cluster or the webhook would be forwarded to this cluster
+            return Err(
+                "validators TokenReview and Webhook require issued_token or token_exchange, their tokens must not be forwarded to the cluster"
+                    .to_string(),
+            );
+        }
+        if let Some(issued_token) = &self.issued_token {
+            if self.disable_validation {
+                return Err(
+                    "issued_token is defined but the token validation is disabled".to_string(),
                 );
             }
+            issued_token.validate()?;
+        }
+        for requirement in &self.step_up {
+            requirement.validate()?;
         }
         // TODO : validate that if validate_against is JwtAuthenticators, then at least one JWT authenticator is configured and enabled
         Ok(())
     }
+
+    /// Ordered validators to try, fallback to validate_against if no validators are defined
+    pub fn validators(&self) -> Vec<ValidateAgainst> {
+        if self.validators.is_empty() {
+            vec![self.validate_against.clone()]
+        } else {
+            self.validators.clone()
+        }
+    }
+
+    /// Get the first step-up requirement that applies to the request
+    pub fn step_up_requirement_for(
+        &self,
+        verb: &str,
+        resource: Option<&str>,
+        subresource: Option<&str>,
+    ) -> Option<&StepUpRequirement> {
+        self.step_up
+            .iter()
+            .find(|requirement| requirement.applies_to(verb, resource, subresource))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: serde_json::Value) -> AuthenticationConfiguration {
+        let mut config = serde_json::json!({
+            "oidc_provider": { "issuer_url": "https://idp.example.com", "client_id": "c1" },
+            "webhook": { "url": "https://webhook.example.com/authenticate" },
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_validate_forwarded_tokens() {
+        for validator in ["TokenReview", "Webhook"] {
+            let validators = serde_json::json!({ "validators": [validator] });
+            assert!(config(validators.clone()).validate().is_err());
+            let mut issued = validators.clone();
+            issued["issued_token"] = serde_json::json!({});
+            assert!(config(issued).validate().is_ok());
+        }
+        assert!(config(serde_json::json!({ "validators": ["Kubernetes"] }))
+            .validate()
+            .is_ok());
+    }
 }
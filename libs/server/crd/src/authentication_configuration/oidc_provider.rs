@@ -1,4 +1,9 @@
-use crate::default::{default_disabled, default_empty_string};
+use crate::authentication_configuration::token_exchange::TokenExchangeConfig;
+use crate::default::{
+    default_disabled, default_email_claim, default_empty_string, default_groups_claim,
+    default_username_claim,
+};
+use common::claim_mapping::ClaimMapping;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -11,4 +16,42 @@ pub struct OidcProvider {
     pub client_secret: Option<String>,
     #[serde(default = "default_empty_string")]
     pub extra_scope: String,
+    /// Claim used as username, either a claim name or a dotted path to a nested claim
+    /// Default : preferred_username
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    /// Claim used as email, either a claim name or a dotted path to a nested claim
+    /// Default : email
+    #[serde(default = "default_email_claim")]
+    pub email_claim: String,
+    /// Claim used as groups, either a claim name or a dotted path to a nested claim
+    /// for example `realm_access.roles` or `resource_access.<client_id>.roles` for Keycloak, `roles` for Azure AD
+    /// A string claim is split on commas
+    /// Default : groups
+    #[serde(default = "default_groups_claim")]
+    pub groups_claim: String,
+    /// Exchange the token of the user for a token whose audience is this client before forwarding it to the cluster
+    /// Avoid a dedicated login to each cluster when the clusters share the IdP of ProxyAuthK8S
+    /// Default : the token of the user is forwarded as is
+    pub token_exchange: Option<TokenExchangeConfig>,
+}
+
+impl OidcProvider {
+    pub fn claim_mapping(&self) -> ClaimMapping {
+        ClaimMapping {
+            username: self.username_claim.clone(),
+            email: self.email_claim.clone(),
+            groups: self.groups_claim.clone(),
+        }
+    }
+
+    /// Audience requested when exchanging the token of the user
+    pub fn token_exchange_audience(&self) -> Option<String> {
+        self.token_exchange.as_ref().map(|config| {
+            config
+                .audience
+                .clone()
+                .unwrap_or_else(|| self.client_id.clone())
+        })
+    }
 }
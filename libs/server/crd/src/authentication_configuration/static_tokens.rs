@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::Secret;
+use kube::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::default::default_static_tokens_key;
+
+/// Label the Secret of the static tokens must have, with the value `true`
+pub const STATIC_TOKENS_LABEL: &str = "proxyauthk8s.io/static-tokens";
+
+/// Static tokens for dev/lab environments without an IdP
+/// Reference a Secret containing a token file in the kube-apiserver `--token-auth-file` format :
+/// `token,user,uid,"group1,group2"` one token per line
+/// The Secret must be in the namespace of the ProxyKubeApi and labeled `proxyauthk8s.io/static-tokens: "true"`,
+/// the controller only watches the labeled Secrets, stores the hashed tokens and reloads them when the Secret changes
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct StaticTokensConfig {
+    /// Name of the Secret containing the token file
+    pub name: String,
+    /// Key of the token file in the Secret
+    /// Default : tokens.csv
+    #[serde(default = "default_static_tokens_key")]
+    pub key: String,
+}
+
+/// A token of the token file, only the hash of the token is kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StaticTokenEntry {
+    pub token_hash: String,
+    pub username: String,
+    pub uid: String,
+    pub groups: Vec<String>,
+}
+
+pub fn hash_static_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Split a CSV line, handling double quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// Parse a token file, empty lines and lines starting with # are ignored
+pub fn parse_token_file(content: &str) -> Result<Vec<StaticTokenEntry>, String> {
+    let mut entries: BTreeMap<String, StaticTokenEntry> = BTreeMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        if fields.len() < 3 || fields[..3].iter().any(|f| f.is_empty()) {
+            return Err(format!(
+                "line {}: expected at least token, user and uid",
+                index + 1
+            ));
+        }
+        let groups = fields
+            .get(3)
+            .map(|groups| {
+                groups
+                    .split(',')
+                    .map(|group| group.trim().to_string())
+                    .filter(|group| !group.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let token_hash = hash_static_token(&fields[0]);
+        if entries.contains_key(&token_hash) {
+            return Err(format!("line {}: duplicated token", index + 1));
+        }
+        entries.insert(
+            token_hash.clone(),
+            StaticTokenEntry {
+                token_hash,
+                username: fields[1].clone(),
+                uid: fields[2].clone(),
+                groups,
+            },
+        );
+    }
+    Ok(entries.into_values().collect())
+}
+
+impl StaticTokensConfig {
+    /// Load and parse the token file from the Secret, always read in the namespace of the ProxyKubeApi
+    pub async fn load(&self, client: Client, ns: &str) -> Result<Vec<StaticTokenEntry>, String> {
+        let secrets: kube::Api<Secret> = kube::Api::namespaced(client, ns);
+        let secret = secrets.get(&self.name).await.map_err(|e| e.to_string())?;
+        // The controller only watches the labeled Secrets, an unlabeled one wouldn't be reloaded
+        if secret
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(STATIC_TOKENS_LABEL))
+            != Some(&"true".to_string())
+        {
+            return Err(format!(
+                "Secret {} must be labeled {}=true",
+                self.name, STATIC_TOKENS_LABEL
+            ));
+        }
+        let content = match secret.data.as_ref().and_then(|data| data.get(&self.key)) {
+            Some(content) => String::from_utf8(content.0.clone()).map_err(|e| e.to_string())?,
+            None => {
+                return Err(format!(
+                    "Key {} not found in secret {}",
+                    self.key, self.name
+                ))
+            }
+        };
+        parse_token_file(&content)
+    }
+
+    /// Check if the Secret is the one referenced by the configuration, only a Secret of the proxy namespace can be
+    pub fn references(&self, secret_ns: &str, secret_name: &str, proxy_ns: &str) -> bool {
+        self.name == secret_name && secret_ns == proxy_ns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let config = StaticTokensConfig {
+            name: "tokens".to_string(),
+            key: default_static_tokens_key(),
+        };
+        assert!(config.references("team", "tokens", "team"));
+        assert!(!config.references("kube-system", "tokens", "team"));
+        assert!(!config.references("team", "other", "team"));
+    }
+
+    #[test]
+    fn test_parse_token_file() {
+        let content = r#"
+# lab tokens
+admin-token,admin,1,"system:masters,admins"
+dev-token,dev,2,developers
+ci-token,ci,3
+"#;
+        let entries = parse_token_file(content).unwrap();
+        assert_eq!(entries.len(), 3);
+        let admin = entries
+            .iter()
+            .find(|e| e.token_hash == hash_static_token("admin-token"))
+            .unwrap();
+        assert_eq!(admin.username, "admin");
+        assert_eq!(admin.uid, "1");
+        assert_eq!(admin.groups, vec!["system:masters", "admins"]);
+        let ci = entries.iter().find(|e| e.username == "ci").unwrap();
+        assert!(ci.groups.is_empty());
+        assert!(!entries.iter().any(|e| e.token_hash.contains("token")));
+    }
+
+    #[test]
+    fn test_parse_token_file_errors() {
+        assert!(parse_token_file("token,user").is_err());
+        assert!(parse_token_file(",user,1").is_err());
+        assert!(parse_token_file("token,a,1\ntoken,b,2").is_err());
+        assert!(parse_token_file("").unwrap().is_empty());
+    }
+}
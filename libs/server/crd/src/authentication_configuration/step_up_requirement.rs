@@ -0,0 +1,164 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::default::default_empty_array;
+
+/// Step-up authentication requirement
+/// Require a stronger or more recent authentication for some verbs or resources, based on the `acr`, `amr` and `auth_time` claims of the ID token
+/// A request matching the verbs and resources of a requirement that does not carry the expected claims is rejected with a 401 telling the client to login again with `acr_values`
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct StepUpRequirement {
+    /// Verbs the requirement applies to, as computed by the Kubernetes API server
+    /// (get, list, watch, create, update, patch, delete, deletecollection)
+    /// If empty, the requirement applies to every verb
+    #[serde(default = "default_empty_array::<String>")]
+    pub verbs: Vec<String>,
+    /// Resources the requirement applies to, either "resource" or "resource/subresource"
+    /// for example "secrets", "pods/exec", "pods/portforward", "pods/attach" or "*/exec"
+    /// If empty, the requirement applies to every resource and non resource path
+    #[serde(default = "default_empty_array::<String>")]
+    pub resources: Vec<String>,
+    /// Accepted authentication context class references, the `acr` claim must match one of them
+    /// Also sent back to the client as `acr_values` so it can request the right level on login
+    #[serde(default = "default_empty_array::<String>")]
+    pub acr_values: Vec<String>,
+    /// Authentication methods that must all be present in the `amr` claim, for example ["mfa"]
+    #[serde(default = "default_empty_array::<String>")]
+    pub amr: Vec<String>,
+    /// Maximum age in seconds of the authentication, computed from the `auth_time` claim
+    pub max_age: Option<u64>,
+}
+
+impl StepUpRequirement {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.acr_values.is_empty() && self.amr.is_empty() && self.max_age.is_none() {
+            return Err(
+                "step_up requirement must define at least one of acr_values, amr or max_age"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Check if the requirement applies to the given verb and resource
+    /// `resource` is None for non resource requests (e.g. /version, /healthz)
+    pub fn applies_to(
+        &self,
+        verb: &str,
+        resource: Option<&str>,
+        subresource: Option<&str>,
+    ) -> bool {
+        let verb_matches =
+            self.verbs.is_empty() || self.verbs.iter().any(|v| v == "*" || v == verb);
+        if !verb_matches {
+            return false;
+        }
+        if self.resources.is_empty() {
+            return true;
+        }
+        let resource = match resource {
+            Some(resource) => resource,
+            None => return false,
+        };
+        self.resources.iter().any(|pattern| {
+            let (pattern_resource, pattern_subresource) = match pattern.split_once('/') {
+                Some((res, sub)) => (res, Some(sub)),
+                None => (pattern.as_str(), None),
+            };
+            let resource_matches = pattern_resource == "*" || pattern_resource == resource;
+            let subresource_matches = match (pattern_subresource, subresource) {
+                (None, None) => true,
+                (Some("*"), _) => true,
+                (Some(expected), Some(actual)) => expected == actual,
+                _ => false,
+            };
+            resource_matches && subresource_matches
+        })
+    }
+
+    /// Check if the claims of the token satisfy the requirement
+    /// `now` and `auth_time` are unix timestamps in seconds
+    pub fn is_satisfied_by(
+        &self,
+        acr: Option<&str>,
+        amr: &[String],
+        auth_time: Option<i64>,
+        now: i64,
+    ) -> bool {
+        if !self.acr_values.is_empty()
+            && !acr.is_some_and(|acr| self.acr_values.iter().any(|expected| expected == acr))
+        {
+            return false;
+        }
+        if !self
+            .amr
+            .iter()
+            .all(|expected| amr.iter().any(|method| method == expected))
+        {
+            return false;
+        }
+        if let Some(max_age) = self.max_age {
+            match auth_time {
+                Some(auth_time) => {
+                    if now.saturating_sub(auth_time) > max_age as i64 {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(verbs: &[&str], resources: &[&str]) -> StepUpRequirement {
+        StepUpRequirement {
+            verbs: verbs.iter().map(|v| v.to_string()).collect(),
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+            acr_values: vec!["mfa".to_string()],
+            amr: vec![],
+            max_age: Some(300),
+        }
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let exec = requirement(&[], &["pods/exec", "pods/portforward"]);
+        assert!(exec.applies_to("create", Some("pods"), Some("exec")));
+        assert!(exec.applies_to("get", Some("pods"), Some("portforward")));
+        assert!(!exec.applies_to("get", Some("pods"), None));
+        assert!(!exec.applies_to("get", None, None));
+
+        let delete = requirement(&["delete", "deletecollection"], &[]);
+        assert!(delete.applies_to("delete", Some("secrets"), None));
+        assert!(delete.applies_to("deletecollection", None, None));
+        assert!(!delete.applies_to("get", Some("secrets"), None));
+
+        let wildcard = requirement(&[], &["*/exec", "secrets/*"]);
+        assert!(wildcard.applies_to("create", Some("pods"), Some("exec")));
+        assert!(wildcard.applies_to("get", Some("secrets"), None));
+        assert!(!wildcard.applies_to("get", Some("pods"), Some("log")));
+    }
+
+    #[test]
+    fn test_is_satisfied_by() {
+        let req = StepUpRequirement {
+            verbs: vec![],
+            resources: vec![],
+            acr_values: vec!["gold".to_string(), "platinum".to_string()],
+            amr: vec!["mfa".to_string()],
+            max_age: Some(300),
+        };
+        let mfa = vec!["pwd".to_string(), "mfa".to_string()];
+        assert!(req.is_satisfied_by(Some("gold"), &mfa, Some(1_000), 1_200));
+        assert!(!req.is_satisfied_by(Some("bronze"), &mfa, Some(1_000), 1_200));
+        assert!(!req.is_satisfied_by(None, &mfa, Some(1_000), 1_200));
+        assert!(!req.is_satisfied_by(Some("gold"), &["pwd".to_string()], Some(1_000), 1_200));
+        assert!(!req.is_satisfied_by(Some("gold"), &mfa, Some(1_000), 1_400));
+        assert!(!req.is_satisfied_by(Some("gold"), &mfa, None, 1_200));
+    }
+}
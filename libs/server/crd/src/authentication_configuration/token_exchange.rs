@@ -0,0 +1,16 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Exchange the token of the user (RFC 8693) for a token dedicated to the cluster before forwarding the request
+/// The exchange is made against the token endpoint of the oidc_provider issuer, authenticated with the client_id and client_secret of the oidc_provider
+/// The OIDC client of ProxyAuthK8S is only used when the oidc_provider has no client_secret and shares its issuer
+/// The exchanged tokens are cached until they expire
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct TokenExchangeConfig {
+    /// Audience of the exchanged token
+    /// Default : the client_id of the oidc_provider
+    pub audience: Option<String>,
+    /// Scope of the exchanged token, space separated
+    /// Default : no scope requested
+    pub scope: Option<String>,
+}
@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::default::default_empty_array;
+
+/// Validate the token with a TokenReview against the management cluster, where ProxyAuthK8S runs
+/// Allow the ServiceAccount tokens of the management cluster to reach the remote cluster without IdP
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct TokenReviewConfig {
+    /// Audiences the token must be valid for, at least one must match
+    /// Default : empty, the audiences of the management cluster API server
+    #[serde(default = "default_empty_array::<String>")]
+    pub audiences: Vec<String>,
+}
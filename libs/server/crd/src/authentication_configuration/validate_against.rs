@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -5,9 +7,34 @@ use serde::{Deserialize, Serialize};
 /// - [TODO] the JWT authenticators, by validating the token signature and claims according to the configured rules
 /// - the OIDC provider, by validating the token by calling the provider's userinfo endpoint and validating the response according to the configured rules
 /// - the kubernetes API, by validating the token by calling the SelfSubjectAccessReview API
-#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+/// - the management cluster, by sending a TokenReview to the cluster where ProxyAuthK8S runs, configured by token_review
+/// - a webhook, by sending a TokenReview to it, configured by webhook
+/// - static tokens loaded from a Secret, configured by static_tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub enum ValidateAgainst {
     // TODO : JwtAuthenticators,
     OidcProvider,
     Kubernetes,
+    TokenReview,
+    Webhook,
+    StaticTokens,
+}
+
+impl ValidateAgainst {
+    /// Name of the validator, used in traces and metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValidateAgainst::OidcProvider => "oidc_provider",
+            ValidateAgainst::Kubernetes => "kubernetes",
+            ValidateAgainst::TokenReview => "token_review",
+            ValidateAgainst::Webhook => "webhook",
+            ValidateAgainst::StaticTokens => "static_tokens",
+        }
+    }
+}
+
+impl Display for ValidateAgainst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
 }
@@ -0,0 +1,54 @@
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{certificate::CertSource, default::default_webhook_cache_ttl};
+
+/// Validate the token by sending an `authentication.k8s.io/v1` TokenReview to a webhook
+/// Same contract as the webhook token authentication of the Kubernetes API server
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct WebhookConfig {
+    /// URL of the webhook, the TokenReview is POSTed to it, must be https
+    pub url: String,
+    /// CA used to validate the certificate of the webhook
+    /// Default : the platform CA
+    pub cert: Option<CertSource>,
+    /// Time in seconds the authenticated tokens are cached, never beyond the expiration of a JWT
+    /// Rejected tokens are cached 10 seconds at most, 0 disable the cache
+    /// Default : 120
+    #[serde(default = "default_webhook_cache_ttl")]
+    pub cache_ttl: u64,
+}
+
+impl WebhookConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match Url::parse(&self.url) {
+            // The token of the user is sent to the webhook, it must never travel in clear
+            Ok(url) if url.scheme() == "https" => Ok(()),
+            Ok(url) => Err(format!("webhook url must be https, got {}", url.scheme())),
+            Err(e) => Err(format!("webhook url is invalid: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let webhook = |url: &str| WebhookConfig {
+            url: url.to_string(),
+            cert: None,
+            cache_ttl: default_webhook_cache_ttl(),
+        };
+        assert!(webhook("https://webhook.example.com/authenticate")
+            .validate()
+            .is_ok());
+        assert!(webhook("http://webhook.example.com/authenticate")
+            .validate()
+            .is_err());
+        assert!(webhook("ftp://webhook.example.com").validate().is_err());
+        assert!(webhook("not an url").validate().is_err());
+    }
+}
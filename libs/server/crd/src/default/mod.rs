@@ -27,6 +27,30 @@ pub fn default_empty_string() -> String {
     String::new()
 }
 
+pub fn default_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+pub fn default_email_claim() -> String {
+    "email".to_string()
+}
+
+pub fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+pub fn default_webhook_cache_ttl() -> u64 {
+    120
+}
+
+pub fn default_issued_token_ttl() -> u64 {
+    300
+}
+
+pub fn default_static_tokens_key() -> String {
+    "tokens.csv".to_string()
+}
+
 pub fn default_validate_against() -> ValidateAgainst {
     ValidateAgainst::Kubernetes
 }
@@ -54,6 +54,18 @@ pub struct ProxyKubeApiSpec {
     pub dashboard_group: Option<String>,
 }
 
+/// Validate the redirect uri of the kubectl plugin
+/// The uri need to have no path, no query and no fragment and its host should be localhost or 127.0.0.1
+fn is_kubectl_redirect_uri(redirect_kubectl_uri: &str) -> bool {
+    let Ok(parsed_uri) = Url::parse(redirect_kubectl_uri) else {
+        return false;
+    };
+    parsed_uri.path() == "/"
+        && parsed_uri.query().is_none()
+        && parsed_uri.fragment().is_none()
+        && matches!(parsed_uri.host_str(), Some("localhost" | "127.0.0.1"))
+}
+
 impl ProxyKubeApi {
     pub fn validate(&self) -> Result<(), String> {
         if self.spec.enabled {
@@ -75,6 +87,10 @@ impl ProxyKubeApi {
             self.name_any()
         )
     }
+    /// Redis key of the hashed static tokens of the proxy
+    pub fn to_static_tokens_identifier(&self) -> String {
+        format!("static_tokens:{}", self.to_path())
+    }
     pub fn to_path(&self) -> String {
         format!(
             "{}/{}",
@@ -107,6 +123,14 @@ impl ProxyKubeApi {
         user_groups.iter().any(|g| g == &dashboard_group)
     }
 
+    /// API tokens only reach the clusters trusting the ProxyAuthK8S issuer, the proxy presents a minted token for them
+    pub fn accepts_api_tokens(&self) -> bool {
+        self.spec
+            .auth_config
+            .as_ref()
+            .is_some_and(|auth_config| auth_config.issued_token.is_some())
+    }
+
     pub fn need_token_validation(&self) -> bool {
         if let Some(auth_config) = &self.spec.auth_config {
             !auth_config.disable_validation
@@ -215,20 +239,7 @@ impl ProxyKubeApi {
     ) -> Option<common::oidc_conf::OidcConf> {
         if let Some(redirect_kubectl_uri) = redirect_kubectl.clone() {
             // Validate the redirect uri
-            // The uri need to be have no path, no query and no fragment and uri should be localhost
-            let parsed_uri = match Url::parse(&redirect_kubectl_uri) {
-                Ok(uri) => uri,
-                Err(_) => {
-                    tracing::error!("Invalid redirect uri: {}", redirect_kubectl_uri);
-                    return None;
-                }
-            };
-            if parsed_uri.path() != "/"
-                || parsed_uri.query().is_some()
-                || parsed_uri.fragment().is_some()
-                || parsed_uri.host_str().is_none()
-                || parsed_uri.host_str().unwrap() != "localhost"
-            {
+            if !is_kubectl_redirect_uri(&redirect_kubectl_uri) {
                 tracing::error!("Invalid redirect uri: {}", redirect_kubectl_uri);
                 return None;
             }
@@ -249,6 +260,8 @@ impl ProxyKubeApi {
                         scopes: auth_config.oidc_provider.extra_scope.clone(),
                         audience: auth_config.oidc_provider.client_id.clone(),
                         redirect_url,
+                        claims: auth_config.oidc_provider.claim_mapping(),
+                        cli_client_id: None,
                     });
                 }
                 None
@@ -333,3 +346,18 @@ impl ObjectRedis for ProxyKubeApi {
         serde_json::from_str(json).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_kubectl_redirect_uri() {
+        assert!(is_kubectl_redirect_uri("http://127.0.0.1:8000"));
+        assert!(is_kubectl_redirect_uri("http://localhost:8000/"));
+        assert!(!is_kubectl_redirect_uri("http://127.0.0.1:8000/callback"));
+        assert!(!is_kubectl_redirect_uri("http://localhost:8000/?next=1"));
+        assert!(!is_kubectl_redirect_uri("http://example.com:8000"));
+        assert!(!is_kubectl_redirect_uri("not a url"));
+    }
+}